@@ -0,0 +1,180 @@
+//! Benchmarks of the export hot path
+//!
+//! Run from the lighthouse checkout with `cargo bench -p xatu --bench pipeline`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::thread;
+use types::{ChainSpec, ForkName, Hash256, MainnetEthSpec};
+use xatu::events::{EventData, EventMeta, SlotTiming, EVENT_SCHEMA_VERSION};
+use xatu::forks::ForkSchedule;
+use xatu::testing::generator::{GeneratedMessage, GeneratorConfig, GossipGenerator, MessageMeta};
+
+type E = MainnetEthSpec;
+
+/// Batch sizes the serialization and channel benchmarks run with
+const BATCH_SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+fn generator() -> (GossipGenerator<E>, SlotTiming, ForkSchedule) {
+    let config = GeneratorConfig::fulu().with_seed(42);
+    let timing = SlotTiming::from_network_info(&config.network_info());
+    let spec = ForkName::Fulu.make_genesis_spec(ChainSpec::mainnet());
+    let forks = ForkSchedule::default();
+    forks.set_fork_schedule(&spec, Some(Hash256::repeat_byte(0xaa)));
+    (GossipGenerator::new(config, &spec), timing, forks)
+}
+
+fn event_meta(meta: &MessageMeta) -> EventMeta {
+    EventMeta::new(
+        meta.peer_id.to_string(),
+        &meta.message_id,
+        meta.topic.clone(),
+        meta.message_size,
+        meta.timestamp_millis,
+        meta.client.clone(),
+    )
+}
+
+/// Build the event the exporter would export for a generated message
+fn build_event(
+    message: &GeneratedMessage<E>,
+    timing: &SlotTiming,
+    forks: &ForkSchedule,
+) -> EventData {
+    let meta = event_meta(message.meta());
+    match message {
+        GeneratedMessage::Block { block, .. } => {
+            EventData::beacon_block(meta, block, timing, forks)
+        }
+        GeneratedMessage::Attestation {
+            attestation,
+            subnet_id,
+            ..
+        } => EventData::attestation(meta, attestation, *subnet_id, true, timing, forks),
+        GeneratedMessage::Aggregate { aggregate, .. } => {
+            EventData::aggregate_and_proof(meta, aggregate, timing, forks)
+        }
+        GeneratedMessage::BlobSidecar {
+            blob_index,
+            blob_sidecar,
+            ..
+        } => EventData::blob_sidecar(meta, *blob_index, blob_sidecar, timing, forks),
+        GeneratedMessage::DataColumnSidecar {
+            column_sidecar,
+            subnet_id,
+            ..
+        } => EventData::data_column_sidecar(meta, column_sidecar, *subnet_id, timing, forks),
+    }
+}
+
+/// At least `count` events of a mainnet-like mix
+fn events(count: usize) -> Vec<EventData> {
+    let (mut generator, timing, forks) = generator();
+    let mut events = Vec::with_capacity(count);
+    while events.len() < count {
+        events.extend(
+            generator
+                .next_slot_messages()
+                .iter()
+                .map(|message| build_event(message, &timing, &forks)),
+        );
+    }
+    events.truncate(count);
+    events
+}
+
+fn event_construction(c: &mut Criterion) {
+    let (mut generator, timing, forks) = generator();
+    let messages = generator.next_slot_messages();
+    let mut group = c.benchmark_group("event_construction");
+
+    let mut bench_first = |name: &str, is_kind: fn(&GeneratedMessage<E>) -> bool| {
+        if let Some(message) = messages.iter().find(|message| is_kind(message)) {
+            group.bench_function(name, |b| {
+                b.iter(|| build_event(black_box(message), &timing, &forks))
+            });
+        }
+    };
+    bench_first("beacon_block", |m| {
+        matches!(m, GeneratedMessage::Block { .. })
+    });
+    bench_first("attestation", |m| {
+        matches!(m, GeneratedMessage::Attestation { .. })
+    });
+    bench_first("aggregate_and_proof", |m| {
+        matches!(m, GeneratedMessage::Aggregate { .. })
+    });
+    bench_first("data_column_sidecar", |m| {
+        matches!(m, GeneratedMessage::DataColumnSidecar { .. })
+    });
+
+    group.throughput(Throughput::Elements(messages.len() as u64));
+    group.bench_function("slot_mix", |b| {
+        b.iter(|| {
+            for message in &messages {
+                black_box(build_event(message, &timing, &forks));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn hex_encoding(c: &mut Criterion) {
+    let root = [0x5a_u8; 32];
+    let signature = [0xa5_u8; 96];
+    let mut group = c.benchmark_group("hex_encoding");
+    group.bench_function("root", |b| {
+        b.iter(|| format!("0x{}", hex::encode(black_box(root))))
+    });
+    group.bench_function("signature", |b| {
+        b.iter(|| format!("0x{}", hex::encode(black_box(signature))))
+    });
+    group.finish();
+}
+
+fn batch_serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_serialization");
+    for size in BATCH_SIZES {
+        let batch = events(size);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &batch, |b, batch| {
+            b.iter(|| EventData::to_json_batch(black_box(batch), EVENT_SCHEMA_VERSION).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn channel_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("channel_throughput");
+    for size in BATCH_SIZES {
+        let batch = events(size);
+        group.throughput(Throughput::Elements(size as u64));
+        // Mirrors the observer queue: gossip threads send, the batch thread drains
+        group.bench_with_input(BenchmarkId::from_parameter(size), &batch, |b, batch| {
+            b.iter_batched(
+                || batch.clone(),
+                |batch| {
+                    let (sender, receiver) = crossbeam_channel::unbounded::<EventData>();
+                    let producer = thread::spawn(move || {
+                        for event in batch {
+                            sender.send(event).unwrap();
+                        }
+                    });
+                    let received = receiver.iter().count();
+                    producer.join().unwrap();
+                    received
+                },
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    event_construction,
+    hex_encoding,
+    batch_serialization,
+    channel_throughput
+);
+criterion_main!(benches);
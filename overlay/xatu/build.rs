@@ -1,32 +1,107 @@
 use std::env;
 use std::fs;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 // Version of xatu-sidecar to download from GitHub releases
 // Update this when new versions are released: https://github.com/ethpandaops/xatu-sidecar/releases
 const XATU_SIDECAR_VERSION: &str = "v0.0.6";
 
+// Path to a locally built sidecar library used instead of the release download.
+// Set it in the environment or in the `[env]` table of `.cargo/config.toml`.
+const XATU_SIDECAR_LIB_ENV: &str = "XATU_SIDECAR_LIB";
+
+// Import library shipped next to `xatu.dll`, MSVC links against it rather than the DLL
+const WINDOWS_IMPORT_LIB: &str = "xatu.lib";
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
     let lib_dir = Path::new(&manifest_dir).join("src");
 
-    // Use platform-appropriate library extension
-    let lib_ext = if cfg!(target_os = "macos") {
-        "dylib"
+    // Reported in the NODE_INFO event
+    println!(
+        "cargo:rustc-env=XATU_SIDECAR_VERSION={}",
+        XATU_SIDECAR_VERSION
+    );
+
+    // The `no-sidecar` feature exports natively and `fake-sidecar` provides the symbols
+    // in-process, either way there is nothing to download or link
+    if env::var_os("CARGO_FEATURE_NO_SIDECAR").is_some()
+        || env::var_os("CARGO_FEATURE_FAKE_SIDECAR").is_some()
+    {
+        return Ok(());
+    }
+
+    // The `static-sidecar` feature links the sidecar's c-archive build into the binary
+    let static_link = env::var_os("CARGO_FEATURE_STATIC_SIDECAR").is_some();
+
+    // Use platform-appropriate library name
+    let lib_filename = if static_link {
+        "libxatu.a"
+    } else if cfg!(target_os = "macos") {
+        "libxatu.dylib"
+    } else if cfg!(target_os = "windows") {
+        "xatu.dll"
     } else {
-        "so"
+        "libxatu.so"
     };
-    let lib_filename = format!("libxatu.{}", lib_ext);
-    let lib_path = lib_dir.join(&lib_filename);
+    let lib_path = lib_dir.join(lib_filename);
 
-    // Check if we need to download the library
-    if !lib_path.exists() || should_update_library(&lib_path) {
-        download_xatu_sidecar(&lib_dir)?;
+    // A local build always wins, otherwise check if we need to download the library
+    println!("cargo:rerun-if-env-changed={}", XATU_SIDECAR_LIB_ENV);
+    if let Some(local_lib) = env::var_os(XATU_SIDECAR_LIB_ENV).map(PathBuf::from) {
+        if !local_lib.is_file() {
+            return Err(format!(
+                "{} points at {}, which is not a file",
+                XATU_SIDECAR_LIB_ENV,
+                local_lib.display()
+            )
+            .into());
+        }
+        println!("cargo:rerun-if-changed={}", local_lib.display());
+        println!(
+            "cargo:warning=Using local xatu-sidecar library {}",
+            local_lib.display()
+        );
+        fs::copy(&local_lib, &lib_path)?;
+        if cfg!(target_os = "windows") && !static_link {
+            let import_lib = local_lib.with_file_name(WINDOWS_IMPORT_LIB);
+            if import_lib.is_file() {
+                fs::copy(&import_lib, lib_dir.join(WINDOWS_IMPORT_LIB))?;
+            }
+        }
+    } else if !lib_path.exists() || should_update_library(&lib_path) {
+        download_xatu_sidecar(&lib_dir, lib_filename)?;
     }
 
     // Tell cargo where to find the library
     println!("cargo:rustc-link-search=native={}", lib_dir.display());
+
+    if static_link {
+        println!("cargo:rustc-link-lib=static=xatu");
+        // The Go runtime inside the archive needs these from the system
+        #[cfg(target_os = "macos")]
+        {
+            println!("cargo:rustc-link-lib=framework=CoreFoundation");
+            println!("cargo:rustc-link-lib=framework=Security");
+            println!("cargo:rustc-link-lib=resolv");
+        }
+        #[cfg(target_os = "windows")]
+        {
+            println!("cargo:rustc-link-lib=winmm");
+            println!("cargo:rustc-link-lib=ntdll");
+            println!("cargo:rustc-link-lib=ws2_32");
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            println!("cargo:rustc-link-lib=pthread");
+            println!("cargo:rustc-link-lib=dl");
+            println!("cargo:rustc-link-lib=resolv");
+        }
+        // Nothing to copy next to the binary or to find at runtime
+        return Ok(());
+    }
+
     println!("cargo:rustc-link-lib=dylib=xatu");
 
     // Copy the library to the output directory
@@ -39,8 +114,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .parent()
         .unwrap();
 
-    let lib_file = lib_dir.join(&lib_filename);
-    let dest_file = target_dir.join(&profile).join(&lib_filename);
+    let lib_file = lib_dir.join(lib_filename);
+    let dest_file = target_dir.join(&profile).join(lib_filename);
 
     if lib_file.exists() {
         std::fs::copy(&lib_file, &dest_file).expect("Failed to copy libxatu to output directory");
@@ -61,12 +136,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Set rpath to look in the same directory as the binary
     // These need to be passed to the final binary, not just this crate
+    // Windows needs nothing here, DLLs next to the executable are found by default
     #[cfg(target_os = "macos")]
     {
         println!("cargo:rustc-link-arg=-Wl,-rpath,@loader_path");
         println!("cargo:rustc-link-arg=-Wl,-rpath,@loader_path/../lib");
     }
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(all(unix, not(target_os = "macos")))]
     {
         println!("cargo:rustc-link-arg=-Wl,-rpath,$ORIGIN");
         println!("cargo:rustc-link-arg=-Wl,-rpath,$ORIGIN/../lib");
@@ -82,12 +158,13 @@ fn should_update_library(_lib_path: &Path) -> bool {
     false
 }
 
-fn download_xatu_sidecar(lib_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+fn download_xatu_sidecar(lib_dir: &Path, lib_name: &str) -> Result<(), Box<dyn std::error::Error>> {
     let platform = match (env::consts::OS, env::consts::ARCH) {
         ("linux", "x86_64") => "linux_amd64",
         ("linux", "aarch64") => "linux_arm64",
         ("macos", "x86_64") => "darwin_amd64",
         ("macos", "aarch64") => "darwin_arm64",
+        ("windows", "x86_64") => "windows_amd64",
         _ => {
             return Err(format!(
                 "Unsupported platform: {} {}",
@@ -98,12 +175,6 @@ fn download_xatu_sidecar(lib_dir: &Path) -> Result<(), Box<dyn std::error::Error
         }
     };
 
-    let lib_name = if env::consts::OS == "macos" {
-        "libxatu.dylib"
-    } else {
-        "libxatu.so"
-    };
-
     let url = format!(
         "https://github.com/ethpandaops/xatu-sidecar/releases/download/{}/xatu-sidecar_{}_{}.tar.gz",
         XATU_SIDECAR_VERSION,
@@ -125,21 +196,30 @@ fn download_xatu_sidecar(lib_dir: &Path) -> Result<(), Box<dyn std::error::Error
     let tar = flate2::read::GzDecoder::new(&data[..]);
     let mut archive = tar::Archive::new(tar);
 
-    // Determine output filename based on platform
-    let output_lib_name = if env::consts::OS == "macos" {
-        "libxatu.dylib"
-    } else {
-        "libxatu.so"
-    };
-
+    let mut found = false;
     for entry in archive.entries()? {
         let mut entry = entry?;
-        let path = entry.path()?;
-        if path.file_name() == Some(std::ffi::OsStr::new(lib_name)) {
-            let dest_path = lib_dir.join(output_lib_name);
+        let file_name = entry.path()?.file_name().map(|name| name.to_os_string());
+
+        if cfg!(target_os = "windows")
+            && file_name.as_deref() == Some(std::ffi::OsStr::new(WINDOWS_IMPORT_LIB))
+        {
+            entry.unpack(lib_dir.join(WINDOWS_IMPORT_LIB))?;
+            continue;
+        }
+
+        if file_name.as_deref() == Some(std::ffi::OsStr::new(lib_name)) {
+            let dest_path = lib_dir.join(lib_name);
             let mut dest_file = fs::File::create(&dest_path)?;
             std::io::copy(&mut entry, &mut dest_file)?;
 
+            found = true;
+
+            // Archives and DLLs are used as-is
+            if lib_name.ends_with(".a") || lib_name.ends_with(".dll") {
+                continue;
+            }
+
             // Make the library executable
             #[cfg(unix)]
             {
@@ -160,11 +240,18 @@ fn download_xatu_sidecar(lib_dir: &Path) -> Result<(), Box<dyn std::error::Error
                     return Err("install_name_tool failed to set install name".into());
                 }
             }
-
-            println!("cargo:warning=Successfully downloaded xatu-sidecar library");
-            return Ok(());
         }
     }
 
-    Err(format!("Library {} not found in release archive", lib_name).into())
+    if found {
+        println!("cargo:warning=Successfully downloaded xatu-sidecar library");
+        return Ok(());
+    }
+
+    Err(format!(
+        "Library {} not found in release archive, place it in {} to build without downloading",
+        lib_name,
+        lib_dir.display()
+    )
+    .into())
 }
@@ -0,0 +1,37 @@
+//! Batch completion acknowledgements
+//!
+//! Batches are submitted with an id and the sink reports each outcome later, from its own
+//! threads. Outcomes are funneled through a process-global channel to the batch thread,
+//! which retries failed batches and tracks what is still in flight.
+
+use crate::error::XatuError;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+
+/// Outcome of a submitted batch
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BatchAck {
+    pub(crate) batch_id: u64,
+    pub(crate) result: Result<(), XatuError>,
+}
+
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(1);
+
+static CHANNEL: LazyLock<(Sender<BatchAck>, Receiver<BatchAck>)> = LazyLock::new(unbounded);
+
+/// Unique id for the next submitted batch
+pub(crate) fn next_batch_id() -> u64 {
+    NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Report the outcome of a batch to the batch thread
+pub(crate) fn complete(batch_id: u64, result: Result<(), XatuError>) {
+    // The channel lives as long as the process, so this can't fail
+    let _ = CHANNEL.0.send(BatchAck { batch_id, result });
+}
+
+/// Receiver of batch outcomes
+pub(crate) fn receiver() -> Receiver<BatchAck> {
+    CHANNEL.1.clone()
+}
@@ -0,0 +1,46 @@
+//! Grouping of attestations with identical data within a batch
+//!
+//! With `aggregation.attestationData` set, the batch thread replaces the attestations of each
+//! batch that share their `AttestationData` by one `ATTESTATION_GROUP` event, placed where
+//! the first of them was.
+
+use crate::events::AttestationGroupEvent;
+use crate::schema::EventData;
+use std::collections::HashMap;
+
+/// Slot, committee index, beacon block root, source and target checkpoints
+type DataKey = (u64, u64, String, u64, String, u64, String);
+
+/// Replace the attestations of the batch by one group per distinct attestation data
+pub(crate) fn group_attestations(batch: Vec<EventData>) -> Vec<EventData> {
+    let mut grouped = Vec::with_capacity(batch.len());
+    let mut groups: HashMap<DataKey, usize> = HashMap::new();
+
+    for event in batch {
+        let EventData::Attestation(attestation) = event else {
+            grouped.push(event);
+            continue;
+        };
+        let key = (
+            attestation.slot,
+            attestation.committee_index,
+            attestation.attestation_data_root.clone(),
+            attestation.source_epoch,
+            attestation.source_root.clone(),
+            attestation.target_epoch,
+            attestation.target_root.clone(),
+        );
+        match groups.get(&key) {
+            Some(&index) => {
+                if let EventData::AttestationGroup(group) = &mut grouped[index] {
+                    group.add(attestation);
+                }
+            }
+            None => {
+                groups.insert(key, grouped.len());
+                grouped.push(AttestationGroupEvent::new(attestation).into());
+            }
+        }
+    }
+    grouped
+}
@@ -0,0 +1,173 @@
+//! Output auth tokens read from a file or command
+//!
+//! Tokens are resolved into the output headers before the config reaches the sidecar, then
+//! re-read on their refresh interval and pushed to the sidecar whenever they change.
+
+use crate::config::{AuthTokenConfig, XatuOutput};
+use crate::error::XatuError;
+use crate::ffi::XatuFFI;
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use std::process::Command;
+use std::thread;
+use tracing::{debug, info, warn};
+
+/// Read the current token with its prefix
+pub(crate) fn read_token(config: &AuthTokenConfig) -> Result<String, XatuError> {
+    let raw = match (&config.file, &config.command) {
+        (Some(path), None) => std::fs::read_to_string(path).map_err(|e| {
+            XatuError::ConfigIo(format!("Failed to read auth token file {}: {}", path, e))
+        })?,
+        (None, Some(command)) => run_command(command)?,
+        _ => {
+            return Err(XatuError::InvalidConfig(
+                "authToken needs exactly one of file or command".to_string(),
+            ))
+        }
+    };
+
+    let token = raw.trim();
+    if token.is_empty() {
+        return Err(XatuError::ConfigIo("Auth token is empty".to_string()));
+    }
+    Ok(format!(
+        "{}{}",
+        config.prefix.as_deref().unwrap_or(""),
+        token
+    ))
+}
+
+fn run_command(command: &str) -> Result<String, XatuError> {
+    let output = shell(command)
+        .output()
+        .map_err(|e| XatuError::ConfigIo(format!("Failed to run auth token command: {}", e)))?;
+    if !output.status.success() {
+        return Err(XatuError::ConfigIo(format!(
+            "Auth token command exited with {}",
+            output.status
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|e| XatuError::ConfigIo(format!("Auth token command output: {}", e)))
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+/// Put the current token of every output that has one into its headers
+pub(crate) fn inject_tokens(outputs: &mut [XatuOutput]) -> Result<(), XatuError> {
+    for output in outputs {
+        let Some(auth_token) = &output.config.auth_token else {
+            continue;
+        };
+        let token = read_token(auth_token).map_err(|e| {
+            XatuError::InvalidConfig(format!("Output '{}' authToken: {}", output.name, e))
+        })?;
+        output
+            .config
+            .headers
+            .insert(auth_token.header_name().to_string(), token);
+    }
+    Ok(())
+}
+
+/// Threads re-reading the output tokens, stopped when dropped
+pub(crate) struct TokenRefresher {
+    _stop: Sender<()>,
+}
+
+impl TokenRefresher {
+    /// Start refreshing the tokens of outputs that have one, `None` if there are none
+    /// Expects the outputs with their tokens already injected
+    pub(crate) fn start(outputs: &[XatuOutput]) -> Option<Self> {
+        let (stop_sender, stop_receiver) = bounded::<()>(0);
+        let mut started = 0;
+
+        for output in outputs {
+            let Some(auth_token) = output.config.auth_token.clone() else {
+                continue;
+            };
+            // Validated before init
+            let Ok(interval) = auth_token.refresh_interval() else {
+                continue;
+            };
+            let header = auth_token.header_name().to_string();
+            let current = output.config.headers.get(&header).cloned();
+            let output_name = output.name.clone();
+            let stop = stop_receiver.clone();
+
+            let spawned = thread::Builder::new()
+                .name(format!("xatu-auth-{}", output_name))
+                .spawn(move || {
+                    refresh_loop(output_name, auth_token, header, current, interval, stop)
+                });
+            if let Err(e) = spawned {
+                warn!(
+                    "Failed to start auth token refresh of output '{}': {}",
+                    output.name, e
+                );
+                continue;
+            }
+            started += 1;
+        }
+
+        if started == 0 {
+            return None;
+        }
+        info!("Refreshing auth tokens of {} Xatu output(s)", started);
+        Some(Self { _stop: stop_sender })
+    }
+}
+
+fn refresh_loop(
+    output_name: String,
+    auth_token: AuthTokenConfig,
+    header: String,
+    mut current: Option<String>,
+    interval: std::time::Duration,
+    stop: Receiver<()>,
+) {
+    loop {
+        match stop.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => {}
+            // The refresher was dropped
+            _ => return,
+        }
+
+        // Keep sending the previous token until a new one can be read
+        let token = match read_token(&auth_token) {
+            Ok(token) => token,
+            Err(e) => {
+                warn!(
+                    "Failed to refresh auth token of Xatu output '{}': {}",
+                    output_name, e
+                );
+                continue;
+            }
+        };
+        if current.as_ref() == Some(&token) {
+            continue;
+        }
+
+        match XatuFFI::set_output_header(&output_name, &header, &token) {
+            Ok(()) => {
+                debug!("Refreshed auth token of Xatu output '{}'", output_name);
+                current = Some(token);
+            }
+            Err(e) => warn!(
+                "Failed to update auth token of Xatu output '{}': {}",
+                output_name, e
+            ),
+        }
+    }
+}
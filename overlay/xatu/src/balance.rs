@@ -0,0 +1,163 @@
+//! Spreading an output's batches over several addresses
+//!
+//! An `http` output that lists `addresses` gets a writer per address, and each batch goes to
+//! one of them, either round robin or to the one with the fewest outstanding events. An
+//! address whose last `FAILURE_THRESHOLD` writes failed is skipped until `RETRY_INTERVAL`
+//! has passed, then gets a single batch again and is healthy once a write succeeds. While
+//! every address is unhealthy batches go to the one due a retry soonest, so the output
+//! keeps trying instead of dropping everything.
+
+use crate::config::LoadBalancing;
+use crate::error::XatuError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Failed writes in a row that mark an address unhealthy
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long an unhealthy address is skipped before it gets another batch
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct Health {
+    consecutive_failures: u32,
+    /// Set while the address is unhealthy, with the time it may get a batch again
+    retry_at: Option<Instant>,
+}
+
+/// One address of a balanced output
+#[derive(Debug)]
+pub(crate) struct Address {
+    output: String,
+    address: String,
+    /// Events queued on or being written by this address's writer
+    outstanding: AtomicUsize,
+    health: Mutex<Health>,
+}
+
+impl Address {
+    fn new(output: &str, address: &str) -> Self {
+        crate::metrics::set_output_address_healthy(output, address, true);
+        Self {
+            output: output.to_string(),
+            address: address.to_string(),
+            outstanding: AtomicUsize::new(0),
+            health: Mutex::new(Health::default()),
+        }
+    }
+
+    pub(crate) fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Count events handed to this address's writer
+    pub(crate) fn start(&self, events: usize) {
+        self.outstanding.fetch_add(events, Ordering::Relaxed);
+    }
+
+    /// Count events the writer is done with, written or not
+    pub(crate) fn finish(&self, events: usize) {
+        self.outstanding.fetch_sub(events, Ordering::Relaxed);
+    }
+
+    /// Record the outcome of a write, updating the address's health
+    pub(crate) fn record(&self, result: &Result<(), XatuError>) {
+        let mut health = self.lock();
+        match result {
+            Ok(()) => {
+                health.consecutive_failures = 0;
+                if health.retry_at.take().is_some() {
+                    info!(
+                        "Output '{}' address {} recovered",
+                        self.output, self.address
+                    );
+                    crate::metrics::set_output_address_healthy(&self.output, &self.address, true);
+                }
+            }
+            Err(e) => {
+                health.consecutive_failures += 1;
+                if health.consecutive_failures < FAILURE_THRESHOLD {
+                    return;
+                }
+                if health.retry_at.is_none() {
+                    warn!(
+                        "Output '{}' address {} failed {} times in a row, skipping it: {}",
+                        self.output, self.address, health.consecutive_failures, e
+                    );
+                    crate::metrics::set_output_address_healthy(&self.output, &self.address, false);
+                }
+                health.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+            }
+        }
+    }
+
+    /// `None` while healthy, otherwise when the address may get a batch again
+    fn retry_at(&self) -> Option<Instant> {
+        self.lock().retry_at
+    }
+
+    /// Take the retry of an unhealthy address, pushing the next one out
+    fn claim_retry(&self) {
+        let mut health = self.lock();
+        if health.retry_at.is_some() {
+            health.retry_at = Some(Instant::now() + RETRY_INTERVAL);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Health> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Picks the address each batch of an output goes to
+#[derive(Debug)]
+pub(crate) struct Balancer {
+    strategy: LoadBalancing,
+    addresses: Vec<Address>,
+    /// Position of the next round robin pick
+    next: AtomicUsize,
+}
+
+impl Balancer {
+    pub(crate) fn new(output: &str, strategy: LoadBalancing, addresses: &[String]) -> Self {
+        Self {
+            strategy,
+            addresses: addresses
+                .iter()
+                .map(|address| Address::new(output, address))
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn addresses(&self) -> &[Address] {
+        &self.addresses
+    }
+
+    /// Index of the address the next batch goes to
+    pub(crate) fn pick(&self) -> usize {
+        let now = Instant::now();
+        let due = |address: &Address| !address.retry_at().is_some_and(|at| now < at);
+        let count = self.addresses.len();
+        let picked = match self.strategy {
+            LoadBalancing::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..count)
+                    .map(|offset| (start + offset) % count)
+                    .find(|index| due(&self.addresses[*index]))
+            }
+            LoadBalancing::LeastOutstanding => (0..count)
+                .filter(|index| due(&self.addresses[*index]))
+                .min_by_key(|index| self.addresses[*index].outstanding.load(Ordering::Relaxed)),
+        };
+        let index = picked.unwrap_or_else(|| {
+            (0..count)
+                .min_by_key(|index| self.addresses[*index].retry_at())
+                .unwrap_or(0)
+        });
+        self.addresses[index].claim_retry();
+        index
+    }
+}
@@ -0,0 +1,142 @@
+//! Gossip bandwidth accounting per topic and peer
+//!
+//! Message sizes are accumulated as they are observed and taken as a whole by the batch
+//! thread, which exports each interval's totals as a `BANDWIDTH` event.
+
+use crate::events::{PeerBandwidth, TopicBandwidth};
+use crate::privacy::PeerAnonymizer;
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Bytes and messages in each direction
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    bytes_in: u64,
+    bytes_out: u64,
+    messages_in: u64,
+    messages_out: u64,
+}
+
+impl Totals {
+    fn add_inbound(&mut self, size: usize) {
+        self.bytes_in = self.bytes_in.saturating_add(size as u64);
+        self.messages_in += 1;
+    }
+
+    fn add_outbound(&mut self, size: usize) {
+        self.bytes_out = self.bytes_out.saturating_add(size as u64);
+        self.messages_out += 1;
+    }
+}
+
+#[derive(Debug)]
+struct Interval {
+    started: Instant,
+    topics: HashMap<String, Totals>,
+    peers: HashMap<PeerId, Totals>,
+}
+
+impl Default for Interval {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            topics: HashMap::new(),
+            peers: HashMap::new(),
+        }
+    }
+}
+
+/// Totals of one interval, ready to export
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BandwidthSummary {
+    pub(crate) interval: Duration,
+    pub(crate) topics: Vec<TopicBandwidth>,
+    pub(crate) peers: Vec<PeerBandwidth>,
+}
+
+/// Gossip bytes accumulated since the last summary
+#[derive(Debug, Default)]
+pub(crate) struct BandwidthTracker {
+    current: Mutex<Interval>,
+}
+
+impl BandwidthTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account a message received from a peer
+    pub(crate) fn inbound(&self, topic: &str, peer_id: &PeerId, size: usize) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        topic_totals(&mut current.topics, topic).add_inbound(size);
+        current.peers.entry(*peer_id).or_default().add_inbound(size);
+    }
+
+    /// Account a message sent to a peer, or published to the mesh when `peer_id` is `None`
+    pub(crate) fn outbound(&self, topic: &str, peer_id: Option<&PeerId>, size: usize) {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        topic_totals(&mut current.topics, topic).add_outbound(size);
+        if let Some(peer_id) = peer_id {
+            current
+                .peers
+                .entry(*peer_id)
+                .or_default()
+                .add_outbound(size);
+        }
+    }
+
+    /// Take the totals accumulated so far and start a new interval
+    /// `None` if no gossip was observed, so idle nodes don't export empty summaries
+    pub(crate) fn take(&self, anonymizer: &PeerAnonymizer) -> Option<BandwidthSummary> {
+        let interval = {
+            let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+            if current.topics.is_empty() {
+                return None;
+            }
+            std::mem::take(&mut *current)
+        };
+
+        let mut topics: Vec<TopicBandwidth> = interval
+            .topics
+            .into_iter()
+            .map(|(topic, totals)| TopicBandwidth {
+                topic,
+                bytes_in: totals.bytes_in,
+                bytes_out: totals.bytes_out,
+                messages_in: totals.messages_in,
+                messages_out: totals.messages_out,
+            })
+            .collect();
+        topics.sort_by(|a, b| a.topic.cmp(&b.topic));
+
+        // Heaviest peers first
+        let mut peers: Vec<PeerBandwidth> = interval
+            .peers
+            .into_iter()
+            .map(|(peer_id, totals)| PeerBandwidth {
+                peer_id: anonymizer.peer_id(&peer_id),
+                bytes_in: totals.bytes_in,
+                bytes_out: totals.bytes_out,
+                messages_in: totals.messages_in,
+                messages_out: totals.messages_out,
+            })
+            .collect();
+        peers.sort_by_key(|peer| std::cmp::Reverse(peer.bytes_in + peer.bytes_out));
+
+        Some(BandwidthSummary {
+            interval: interval.started.elapsed(),
+            topics,
+            peers,
+        })
+    }
+}
+
+/// Totals of the topic, allocating its name only the first time it is seen
+fn topic_totals<'a>(topics: &'a mut HashMap<String, Totals>, topic: &str) -> &'a mut Totals {
+    if !topics.contains_key(topic) {
+        topics.insert(topic.to_string(), Totals::default());
+    }
+    topics.get_mut(topic).expect("inserted above")
+}
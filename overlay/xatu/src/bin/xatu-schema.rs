@@ -0,0 +1,47 @@
+//! Emit JSON Schema documents for the Xatu event format
+//!
+//! Usage: `xatu-schema [OUTPUT_DIR]`
+//! Without an output directory the combined schema is printed to stdout, otherwise
+//! one `<EVENT_TYPE>.schema.json` file is written per event type.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match std::env::args().nth(1) {
+        Some(dir) => match write_schemas(Path::new(&dir)) {
+            Ok(count) => {
+                eprintln!("Wrote {} schemas to {}", count, dir);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Failed to write schemas: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        None => {
+            let schema = xatu::schema::event_schema();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).expect("schema should serialize")
+            );
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+fn write_schemas(dir: &Path) -> Result<usize, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let schemas = xatu::schema::event_type_schemas();
+    for (event_type, schema) in &schemas {
+        let path = dir.join(format!("{}.schema.json", event_type));
+        let contents = serde_json::to_string_pretty(schema)
+            .map_err(|e| format!("Failed to serialize {} schema: {}", event_type, e))?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    Ok(schemas.len())
+}
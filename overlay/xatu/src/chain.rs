@@ -1,33 +1,234 @@
-//! Wrapper to maintain backwards compatibility with Lighthouse integration
+//! Chain of exporters used by the Lighthouse integration
 
-use crate::{ObserverResult, Xatu};
+use crate::config::NetworkInfo;
+use crate::context::XatuContext;
+use crate::filter::EventKind;
+use crate::handle::XatuHandle;
+use crate::peers::{PeerDetails, PeerSubnets};
+use crate::{ObserverResult, Xatu, XatuConfig};
 use libp2p::PeerId;
-use lighthouse_network::MessageId;
+use lighthouse_network::types::GossipKind;
+use lighthouse_network::{MessageId, SyncState};
 use std::sync::Arc;
+use tracing::trace_span;
 use types::EthSpec;
 
-/// A wrapper that looks like a chain but just holds a single exporter
-/// This is kept for backwards compatibility with the Lighthouse integration
+/// A chain of exporters, every gossip callback is fanned out to each of them
 pub struct XatuChain<E: EthSpec> {
-    exporter: Option<Arc<dyn Xatu<E>>>,
+    exporters: Vec<Arc<dyn Xatu<E>>>,
+    handle: Option<XatuHandle>,
+    /// State the exporters built by `init` share
+    context: Arc<XatuContext>,
+    /// Config the chain was built from, if it was built by `init`
+    config: Option<XatuConfig>,
+}
+
+impl<E: EthSpec> Default for XatuChain<E> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<E: EthSpec> XatuChain<E> {
     /// Create a new empty chain
     pub fn new() -> Self {
-        Self { exporter: None }
+        Self {
+            exporters: Vec::new(),
+            handle: None,
+            context: Arc::default(),
+            config: None,
+        }
     }
 
     /// Create a chain with an exporter
     pub fn with_exporter(exporter: Arc<dyn Xatu<E>>) -> Self {
         Self {
-            exporter: Some(exporter),
+            exporters: vec![exporter],
+            handle: None,
+            context: Arc::default(),
+            config: None,
         }
     }
 
+    /// Attach the handle controlling the sidecar exporter
+    pub fn with_handle(mut self, handle: Option<XatuHandle>) -> Self {
+        self.handle = handle;
+        self
+    }
+
+    /// Share the context the exporters were built with, and the handle started with it
+    pub fn with_context(mut self, context: Arc<XatuContext>) -> Self {
+        self.handle = context.handle();
+        self.context = context;
+        self
+    }
+
+    /// Keep the config the chain was built from, for `refresh_network`
+    pub fn with_config(mut self, config: XatuConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub(crate) fn config(&self) -> Option<&XatuConfig> {
+        self.config.as_ref()
+    }
+
+    /// Handle to flush, shut down or inspect the sidecar exporter, if the chain has one
+    pub fn handle(&self) -> Option<&XatuHandle> {
+        self.handle.as_ref()
+    }
+
+    /// State shared by the exporters, where lighthouse registers the pubkey lookup and the
+    /// fork digests once its beacon chain is built
+    pub fn context(&self) -> &Arc<XatuContext> {
+        &self.context
+    }
+
+    /// Add another exporter to the chain
+    pub fn add_exporter(&mut self, exporter: Arc<dyn Xatu<E>>) {
+        self.exporters.push(exporter);
+    }
+
+    /// Number of exporters in the chain
+    pub fn len(&self) -> usize {
+        self.exporters.len()
+    }
+
+    /// Check if the chain has no exporters
+    pub fn is_empty(&self) -> bool {
+        self.exporters.is_empty()
+    }
+
     /// Check if the chain has an exporter
     pub fn is_enabled(&self) -> bool {
-        self.exporter.is_some()
+        !self.exporters.is_empty()
+    }
+
+    /// Notify every exporter of a new peer connection
+    pub fn on_peer_connected(&self, peer_id: &PeerId, details: &PeerDetails) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_peer_connected(peer_id, details)),
+        )
+    }
+
+    /// Notify every exporter that a peer disconnected
+    pub fn on_peer_disconnected(&self, peer_id: &PeerId) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_peer_disconnected(peer_id)),
+        )
+    }
+
+    /// Notify every exporter that the chain is starting
+    pub fn on_start(&self, network_info: &NetworkInfo) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_start(network_info)),
+        )
+    }
+
+    /// Notify every exporter of a sync state change
+    pub fn on_sync_state_changed(&self, old: &SyncState, new: &SyncState) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_sync_state_changed(old, new)),
+        )
+    }
+
+    /// Notify every exporter of the local node's PeerDAS custody
+    pub fn on_custody_changed(
+        &self,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_custody_changed(custody_group_count, custody_columns)),
+        )
+    }
+
+    /// Notify every exporter of the PeerDAS custody a peer advertises
+    pub fn on_peer_custody(
+        &self,
+        peer_id: &PeerId,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        ObserverResult::combine(self.exporters.iter().map(|exporter| {
+            exporter.on_peer_custody(peer_id, custody_group_count, custody_columns)
+        }))
+    }
+
+    /// Notify every exporter of the gossip subnets a peer advertises
+    pub fn on_peer_subnets(&self, peer_id: &PeerId, subnets: &PeerSubnets) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_peer_subnets(peer_id, subnets)),
+        )
+    }
+
+    /// Notify every exporter of the actual genesis time, once it is known
+    pub fn on_genesis_time_changed(&self, genesis_time: u64) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_genesis_time_changed(genesis_time)),
+        )
+    }
+
+    /// Notify every exporter that the node moved to another network
+    pub fn on_network_changed(&self, network_info: &NetworkInfo) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_network_changed(network_info)),
+        )
+    }
+
+    /// Make every exporter send what it has queued, waiting for delivery
+    pub fn flush(&self) -> ObserverResult {
+        ObserverResult::combine(self.exporters.iter().map(|exporter| exporter.flush()))
+    }
+
+    /// Notify every exporter that the node is shutting down
+    pub fn on_shutdown(&self) -> ObserverResult {
+        ObserverResult::combine(self.exporters.iter().map(|exporter| exporter.on_shutdown()))
+    }
+
+    /// Account a gossip message of any type and pass it to every exporter
+    pub fn on_gossip_message(
+        &self,
+        topic: &str,
+        message_id: &MessageId,
+        peer_id: &PeerId,
+        raw_size: usize,
+        decoded_kind: &GossipKind,
+    ) -> ObserverResult {
+        crate::metrics::inc_gossip_messages(decoded_kind, raw_size);
+        ObserverResult::combine(self.exporters.iter().map(|exporter| {
+            exporter.on_gossip_message(topic, message_id, peer_id, raw_size, decoded_kind)
+        }))
+    }
+
+    /// Account a sent gossip message in every exporter
+    pub fn on_gossip_sent(
+        &self,
+        topic: &str,
+        peer_id: Option<&PeerId>,
+        size: usize,
+    ) -> ObserverResult {
+        ObserverResult::combine(
+            self.exporters
+                .iter()
+                .map(|exporter| exporter.on_gossip_sent(topic, peer_id, size)),
+        )
     }
 
     /// Process a gossip block
@@ -41,18 +242,17 @@ impl<E: EthSpec> XatuChain<E> {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        if let Some(exporter) = &self.exporter {
+        self.fan_out(EventKind::Block, |exporter| {
             exporter.on_gossip_block(
-                message_id,
+                message_id.clone(),
                 peer_id,
-                client,
-                block,
+                client.clone(),
+                block.clone(),
                 timestamp.as_millis() as u64,
-                topic,
+                topic.clone(),
                 message_size,
-            );
-        }
-        ObserverResult::Ok
+            )
+        })
     }
 
     /// Process a gossip attestation
@@ -67,19 +267,44 @@ impl<E: EthSpec> XatuChain<E> {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        if let Some(exporter) = &self.exporter {
+        self.fan_out(EventKind::Attestation, |exporter| {
             exporter.on_gossip_attestation(
-                message_id,
+                message_id.clone(),
                 peer_id,
-                attestation,
+                attestation.clone(),
                 subnet_id,
                 should_process,
                 timestamp.as_millis() as u64,
-                topic,
+                topic.clone(),
                 message_size,
-            );
-        }
-        ObserverResult::Ok
+            )
+        })
+    }
+
+    /// Process a gossip attestation in the pre-Electra `Attestation` format
+    pub fn process_gossip_unaggregated_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<types::Attestation<E>>,
+        subnet_id: types::SubnetId,
+        should_process: bool,
+        timestamp: std::time::Duration,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.fan_out(EventKind::Attestation, |exporter| {
+            exporter.on_gossip_unaggregated_attestation(
+                message_id.clone(),
+                peer_id,
+                attestation.clone(),
+                subnet_id,
+                should_process,
+                timestamp.as_millis() as u64,
+                topic.clone(),
+                message_size,
+            )
+        })
     }
 
     /// Process a gossip aggregate and proof
@@ -92,17 +317,16 @@ impl<E: EthSpec> XatuChain<E> {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        if let Some(exporter) = &self.exporter {
+        self.fan_out(EventKind::Aggregate, |exporter| {
             exporter.on_gossip_aggregate_and_proof(
-                message_id,
+                message_id.clone(),
                 peer_id,
-                aggregate,
+                aggregate.clone(),
                 timestamp.as_millis() as u64,
-                topic,
+                topic.clone(),
                 message_size,
-            );
-        }
-        ObserverResult::Ok
+            )
+        })
     }
 
     /// Process a gossip blob sidecar
@@ -117,19 +341,18 @@ impl<E: EthSpec> XatuChain<E> {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        if let Some(exporter) = &self.exporter {
+        self.fan_out(EventKind::BlobSidecar, |exporter| {
             exporter.on_gossip_blob_sidecar(
-                message_id,
+                message_id.clone(),
                 peer_id,
-                client,
+                client.clone(),
                 blob_index,
-                blob_sidecar,
+                blob_sidecar.clone(),
                 timestamp.as_millis() as u64,
-                topic,
+                topic.clone(),
                 message_size,
-            );
-        }
-        ObserverResult::Ok
+            )
+        })
     }
 
     /// Process a gossip data column sidecar
@@ -144,18 +367,32 @@ impl<E: EthSpec> XatuChain<E> {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        if let Some(exporter) = &self.exporter {
+        self.fan_out(EventKind::DataColumnSidecar, |exporter| {
             exporter.on_gossip_data_column_sidecar(
-                message_id,
+                message_id.clone(),
                 peer_id,
-                client,
+                client.clone(),
                 subnet_id,
-                column_sidecar,
+                column_sidecar.clone(),
                 timestamp.as_millis() as u64,
-                topic,
+                topic.clone(),
                 message_size,
-            );
+            )
+        })
+    }
+
+    /// Call every exporter, tagging failures with the event type and counting them
+    fn fan_out(
+        &self,
+        kind: EventKind,
+        call: impl Fn(&Arc<dyn Xatu<E>>) -> ObserverResult,
+    ) -> ObserverResult {
+        let _span = trace_span!("xatu_intake", event_type = kind.event_type()).entered();
+        let result = ObserverResult::combine(self.exporters.iter().map(call)).with_kind(kind);
+        let error_count = result.error_count();
+        if error_count > 0 {
+            crate::metrics::inc_chain_errors(kind, error_count);
         }
-        ObserverResult::Ok
+        result
     }
 }
@@ -0,0 +1,86 @@
+//! Normalization of peer client agent strings
+//!
+//! Lighthouse hands us either a raw libp2p agent string (`Lighthouse/v5.3.0-abcdef/x86_64-linux`)
+//! or its own `Client` display form (`Lighthouse: version: v5.3.0-abcdef, os_version: x86_64-linux`).
+//! Both are reduced to the same `{implementation, version, platform}` triple.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Normalized client details of the peer that sent a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ClientFingerprint {
+    /// Lowercase implementation name, e.g. `lighthouse`
+    pub implementation: String,
+    /// Release version without a leading `v` or commit suffix, e.g. `5.3.0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Platform as reported by the client, e.g. `x86_64-linux`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+}
+
+impl ClientFingerprint {
+    /// Parse an agent string in either the raw or the lighthouse display form
+    pub fn parse(agent: &str) -> Self {
+        let agent = agent.trim();
+
+        if let Some((kind, rest)) = agent.split_once(": version: ") {
+            let (version, platform) = match rest.split_once(", os_version: ") {
+                Some((version, platform)) => (version, Some(platform)),
+                None => (rest, None),
+            };
+            return Self::new(kind, Some(version), platform);
+        }
+
+        // Unknown clients are displayed with their raw agent string
+        let agent = agent.strip_prefix("Unknown: ").unwrap_or(agent);
+
+        let mut parts = agent
+            .split('/')
+            .map(str::trim)
+            .filter(|part| !part.is_empty());
+        let implementation = parts.next().unwrap_or_default();
+        let rest: Vec<&str> = parts.collect();
+
+        // Some clients repeat their name before the version (e.g. `teku/teku/v24.1.0/...`)
+        let version_index = rest.iter().position(|part| looks_like_version(part));
+        let version = version_index.map(|i| rest[i]);
+        let platform = version_index.and_then(|i| rest.get(i + 1).copied());
+
+        Self::new(implementation, version, platform)
+    }
+
+    fn new(implementation: &str, version: Option<&str>, platform: Option<&str>) -> Self {
+        let implementation = implementation.trim().to_ascii_lowercase();
+        Self {
+            implementation: if implementation.is_empty() {
+                "unknown".to_string()
+            } else {
+                implementation
+            },
+            version: version.map(normalize_version).filter(|v| !v.is_empty()),
+            platform: platform
+                .map(str::trim)
+                .filter(|p| !p.is_empty() && !p.eq_ignore_ascii_case("unknown"))
+                .map(str::to_string),
+        }
+    }
+}
+
+fn looks_like_version(part: &str) -> bool {
+    part.trim_start_matches(['v', 'V'])
+        .starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Strip the leading `v`, build metadata and a trailing commit hash, keeping pre-release tags
+fn normalize_version(version: &str) -> String {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let version = version.split('+').next().unwrap_or_default();
+    match version.rsplit_once('-') {
+        Some((release, suffix)) if suffix.chars().all(|c| c.is_ascii_hexdigit()) => {
+            release.to_string()
+        }
+        _ => version.to_string(),
+    }
+}
@@ -0,0 +1,116 @@
+//! NTP clock correction for event timestamps
+//!
+//! The sidecar corrects its own clock from `ntpServer`, but event timestamps are taken on
+//! the lighthouse side. This module periodically measures the host clock offset with SNTP
+//! so it can be applied to `timestamp_ms` before events are queued.
+
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// How often the clock offset is measured
+const SYNC_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How long to wait for an NTP response
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Seconds between the NTP epoch (1900) and the unix epoch (1970)
+const NTP_UNIX_OFFSET_SECS: u64 = 2_208_988_800;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static SYNCED: AtomicBool = AtomicBool::new(false);
+static OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+
+/// Start measuring the clock offset against the server in the background
+/// Only the first call starts a sync thread; later calls are ignored
+pub fn start(server: &str) {
+    if STARTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    let server = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:123", server)
+    };
+    info!("Starting Xatu NTP clock sync against {}", server);
+
+    let spawned = thread::Builder::new()
+        .name("xatu-clock".to_string())
+        .spawn(move || loop {
+            match query_offset_ms(&server) {
+                Ok(offset_ms) => {
+                    debug!("Xatu NTP clock offset: {}ms", offset_ms);
+                    OFFSET_MS.store(offset_ms, Ordering::Relaxed);
+                    SYNCED.store(true, Ordering::Relaxed);
+                }
+                Err(e) => warn!("Xatu NTP query to {} failed: {}", server, e),
+            }
+            thread::sleep(SYNC_INTERVAL);
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start Xatu NTP clock sync: {}", e);
+    }
+}
+
+/// Offset to add to the host clock, `None` until the first successful measurement
+pub fn offset_ms() -> Option<i64> {
+    SYNCED
+        .load(Ordering::Relaxed)
+        .then(|| OFFSET_MS.load(Ordering::Relaxed))
+}
+
+/// Host time in milliseconds since the unix epoch, corrected by the measured offset
+pub(crate) fn corrected_now_ms() -> i64 {
+    now_ms().saturating_add(offset_ms().unwrap_or(0))
+}
+
+/// Measure the clock offset with a single SNTP request
+fn query_offset_ms(server: &str) -> std::io::Result<i64> {
+    let address = server.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "server did not resolve")
+    })?;
+    let socket = UdpSocket::bind(if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    })?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+
+    // LI = 0, version = 4, mode = 3 (client)
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+
+    let originate_ms = now_ms();
+    socket.send_to(&request, address)?;
+    let mut response = [0u8; 48];
+    let (len, _) = socket.recv_from(&mut response)?;
+    let destination_ms = now_ms();
+
+    if len < 48 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("short NTP response of {} bytes", len),
+        ));
+    }
+
+    let receive_ms = ntp_timestamp_ms(&response[32..40]);
+    let transmit_ms = ntp_timestamp_ms(&response[40..48]);
+    Ok(((receive_ms - originate_ms) + (transmit_ms - destination_ms)) / 2)
+}
+
+fn ntp_timestamp_ms(bytes: &[u8]) -> i64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as u64;
+    let unix_seconds = seconds.saturating_sub(NTP_UNIX_OFFSET_SECS);
+    (unix_seconds * 1000 + ((fraction * 1000) >> 32)) as i64
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
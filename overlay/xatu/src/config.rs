@@ -1,8 +1,12 @@
+use crate::error::XatuError;
+use crate::events::EventData;
+use crate::transform::Transforms;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Network information passed from Lighthouse
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct NetworkInfo {
     pub genesis_time: u64,
     pub network_name: String,
@@ -17,12 +21,70 @@ pub struct XatuConfig {
     pub enabled: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Name of the registered exporter to use (defaults to "ffi")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exporter: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub outputs: Option<Vec<XatuOutput>>,
+    #[serde(flatten)]
+    pub settings: ExportSettings,
+}
+
+/// Settings of the export pipeline, shared by the config file and the config exporters
+/// are started with
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ExportSettings {
     #[serde(skip_serializing_if = "Option::is_none", rename = "ntpServer")]
     pub ntp_server: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ethereum: Option<EthereumConfig>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "peerFilter")]
+    pub peer_filter: Option<PeerFilterConfig>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "slotRange")]
+    pub slot_range: Option<SlotRangeConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<AggregationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy: Option<PrivacyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingConfig>,
+    /// Seconds between pipeline summary log lines, 0 disables them (defaults to 60)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "summaryInterval")]
+    pub summary_interval: Option<u64>,
+    /// Gossip events more than this many slots behind the wallclock are dropped when the
+    /// batch thread dequeues them (unset keeps every event)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "maxEventAgeSlots")]
+    pub max_event_age_slots: Option<u64>,
+    /// Clock skew in milliseconds above which `CLOCK_SKEW` events are emitted (defaults to 500)
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        rename = "clockSkewThresholdMs"
+    )]
+    pub clock_skew_threshold_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "rateLimit")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Serialized size in bytes at which a batch is sent without waiting for the batch
+    /// interval (defaults to 4 MiB, 0 disables); counted from each event's own JSON, so
+    /// schema 3 batches sharing a dictionary come out smaller
+    #[serde(skip_serializing_if = "Option::is_none", rename = "maxBatchBytes")]
+    pub max_batch_bytes: Option<u64>,
+    /// Approximate bytes the event queue may hold, lower priority events are dropped first
+    /// once it fills up (unset only bounds the queue by its event count)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "maxQueueBytes")]
+    pub max_queue_bytes: Option<u64>,
+    /// Initialize the sidecar in the background instead of blocking observer creation on it
+    #[serde(skip_serializing_if = "Option::is_none", rename = "lazyInit")]
+    pub lazy_init: Option<LazyInitConfig>,
+    /// Handling of gossip events received before genesis (defaults to buffering them)
+    #[serde(skip_serializing_if = "Option::is_none", rename = "preGenesis")]
+    pub pre_genesis: Option<PreGenesisConfig>,
+    /// Transforms run on every event before it is sent, only settable from code
+    #[serde(skip)]
+    pub transforms: Transforms,
 }
 
 /// Node configuration
@@ -41,6 +103,217 @@ pub struct EthereumConfig {
     pub override_network_name: Option<String>,
 }
 
+/// Peer filtering configuration
+/// If `allow` is non-empty only events from those peers are exported; `deny` always wins
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct PeerFilterConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+/// Slot/epoch window outside of which events are skipped
+/// All bounds are inclusive; when both slot and epoch bounds are set the narrower one applies
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct SlotRangeConfig {
+    #[serde(rename = "startSlot", skip_serializing_if = "Option::is_none")]
+    pub start_slot: Option<u64>,
+    #[serde(rename = "endSlot", skip_serializing_if = "Option::is_none")]
+    pub end_slot: Option<u64>,
+    #[serde(rename = "startEpoch", skip_serializing_if = "Option::is_none")]
+    pub start_epoch: Option<u64>,
+    #[serde(rename = "endEpoch", skip_serializing_if = "Option::is_none")]
+    pub end_epoch: Option<u64>,
+}
+
+/// Per-event-type sample rates between 0.0 and 1.0 (unset means export everything)
+/// Sampling is keyed on the gossip message id so every node keeps the same subset
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct SamplingConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<f64>,
+    #[serde(rename = "blobSidecar", skip_serializing_if = "Option::is_none")]
+    pub blob_sidecar: Option<f64>,
+    #[serde(rename = "dataColumnSidecar", skip_serializing_if = "Option::is_none")]
+    pub data_column_sidecar: Option<f64>,
+}
+
+/// Per-slot aggregation of gossip events
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct AggregationConfig {
+    #[serde(default)]
+    pub mode: AggregationMode,
+    /// Group attestations with identical data within each batch into `ATTESTATION_GROUP`
+    /// events, independently of `mode`
+    #[serde(rename = "attestationData", default)]
+    pub attestation_data: bool,
+}
+
+/// Whether `SLOT_AGGREGATE` events are exported, and whether they replace the raw events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregationMode {
+    /// Raw events only
+    #[default]
+    Off,
+    /// Slot aggregates only, raw gossip events are dropped
+    Aggregate,
+    /// Raw events and slot aggregates
+    Both,
+}
+
+/// Duplicate suppression applied before events are queued
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct DedupConfig {
+    /// Export only the first attestation seen per (slot, committee, attester), the attester
+    /// being the validator index or, before Electra, the one aggregation bit set
+    #[serde(rename = "firstSeenAttestations", default)]
+    pub first_seen_attestations: bool,
+    /// Keys remembered before the oldest are forgotten (defaults to 262144)
+    #[serde(rename = "maxEntries", skip_serializing_if = "Option::is_none")]
+    pub max_entries: Option<usize>,
+}
+
+/// Limits on what is sent toward the sink, smoothed over `burstSeconds`
+/// Up to 16 batches wait for the limit, further batches are dropped until it catches up
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RateLimitConfig {
+    #[serde(rename = "eventsPerSecond", skip_serializing_if = "Option::is_none")]
+    pub events_per_second: Option<u64>,
+    /// Limit on the serialized size of the batches
+    #[serde(rename = "bytesPerSecond", skip_serializing_if = "Option::is_none")]
+    pub bytes_per_second: Option<u64>,
+    /// Seconds of the rate that may go out at once after a quiet period (defaults to 1)
+    #[serde(rename = "burstSeconds", skip_serializing_if = "Option::is_none")]
+    pub burst_seconds: Option<f64>,
+}
+
+/// Background initialization of the sidecar, retried until it succeeds
+/// Events are buffered meanwhile, so an unreachable sink doesn't hold up node startup
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct LazyInitConfig {
+    /// Events held until the sidecar is initialized, newer ones are dropped beyond it
+    /// (defaults to 10000)
+    #[serde(rename = "bufferEvents", skip_serializing_if = "Option::is_none")]
+    pub buffer_events: Option<usize>,
+    /// Delay between init attempts, e.g. `5s` (defaults to 5s)
+    #[serde(rename = "retryInterval", skip_serializing_if = "Option::is_none")]
+    pub retry_interval: Option<String>,
+}
+
+impl LazyInitConfig {
+    /// Events buffered when `bufferEvents` is unset
+    pub const DEFAULT_BUFFER_EVENTS: usize = 10000;
+
+    /// Retry interval used when `retryInterval` is unset
+    pub const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn buffer_events(&self) -> usize {
+        self.buffer_events.unwrap_or(Self::DEFAULT_BUFFER_EVENTS)
+    }
+
+    /// Interval between init attempts, failing on malformed or zero durations
+    pub fn retry_interval(&self) -> Result<Duration, XatuError> {
+        let interval = self
+            .retry_interval
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?
+            .unwrap_or(Self::DEFAULT_RETRY_INTERVAL);
+        if interval.is_zero() {
+            return Err(XatuError::InvalidConfig(
+                "lazyInit retryInterval must be above zero".to_string(),
+            ));
+        }
+        Ok(interval)
+    }
+}
+
+/// Gossip events received before genesis, whose slot fields are relative to a chain that
+/// hasn't started, are held back until genesis and follow a `GENESIS` marker event
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct PreGenesisConfig {
+    #[serde(default)]
+    pub mode: PreGenesisMode,
+    /// Events held until genesis in `buffer` mode, newer ones are dropped beyond it
+    /// (defaults to 10000)
+    #[serde(rename = "bufferEvents", skip_serializing_if = "Option::is_none")]
+    pub buffer_events: Option<usize>,
+}
+
+impl PreGenesisConfig {
+    /// Events buffered when `bufferEvents` is unset
+    pub const DEFAULT_BUFFER_EVENTS: usize = 10000;
+
+    /// Events held until genesis, none in `drop` mode
+    pub fn buffer_events(&self) -> usize {
+        match self.mode {
+            PreGenesisMode::Buffer => self.buffer_events.unwrap_or(Self::DEFAULT_BUFFER_EVENTS),
+            PreGenesisMode::Drop => 0,
+        }
+    }
+}
+
+/// What happens to gossip events received before genesis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PreGenesisMode {
+    /// Hold them back and export them after the `GENESIS` marker
+    #[default]
+    Buffer,
+    /// Drop them, counting how many in the `GENESIS` marker
+    Drop,
+}
+
+/// Privacy configuration
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct PrivacyConfig {
+    /// Replace peer ids with a salted hash in all exported events
+    #[serde(rename = "anonymizePeerIds", default)]
+    pub anonymize_peer_ids: bool,
+    /// Salt for the peer id hash; a random salt is generated per run if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+}
+
+/// Sidecar logging configuration, independent of the lighthouse log settings
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct LoggingConfig {
+    /// One of trace, debug, info, warn or error (defaults to info)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// Log output format, e.g. text or json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Write sidecar logs to this file instead of the lighthouse log (`xatu_sidecar` target)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl LoggingConfig {
+    /// Valid sidecar log levels
+    pub const LEVELS: [&'static str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+    /// Get the normalized log level, failing on unknown levels
+    pub fn level(&self) -> Result<String, XatuError> {
+        let level = self.level.as_deref().unwrap_or("info").to_ascii_lowercase();
+        if Self::LEVELS.contains(&level.as_str()) {
+            Ok(level)
+        } else {
+            Err(XatuError::InvalidConfig(format!(
+                "Invalid logging level '{}', expected one of {:?}",
+                level,
+                Self::LEVELS
+            )))
+        }
+    }
+}
+
 /// Output configuration wrapper
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct XatuOutput {
@@ -56,10 +329,8 @@ pub struct FullConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub node: Option<NodeConfig>,
     pub outputs: Vec<XatuOutput>,
-    #[serde(skip_serializing_if = "Option::is_none", rename = "ntpServer")]
-    pub ntp_server: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub ethereum: Option<EthereumConfig>,
+    #[serde(flatten)]
+    pub settings: ExportSettings,
 }
 
 /// Output configuration
@@ -70,6 +341,15 @@ pub struct OutputConfig {
     pub headers: HashMap<String, String>,
     #[serde(default)]
     pub tls: bool,
+    /// Certificates and verification settings, only used with `tls: true`
+    #[serde(rename = "tlsConfig", skip_serializing_if = "Option::is_none")]
+    pub tls_config: Option<TlsConfig>,
+    /// Token added to `headers` and kept fresh while the node runs
+    #[serde(rename = "authToken", skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<AuthTokenConfig>,
+    /// Connection tuning of `xatu` (gRPC) outputs, sidecar defaults where unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grpc: Option<GrpcConfig>,
     #[serde(rename = "maxQueueSize", skip_serializing_if = "Option::is_none")]
     pub max_queue_size: Option<u64>,
     #[serde(rename = "batchTimeout", skip_serializing_if = "Option::is_none")]
@@ -80,24 +360,332 @@ pub struct OutputConfig {
     pub max_export_batch_size: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workers: Option<u64>,
+    /// Name of the output this one stands in for, it only receives batches while that
+    /// output is failing (native outputs only)
+    #[serde(rename = "fallbackFor", skip_serializing_if = "Option::is_none")]
+    pub fallback_for: Option<String>,
+    /// Further addresses of the same ingest tier, batches are spread over `address` and
+    /// these (native `http` outputs only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+    /// How batches are spread over the addresses, round robin where unset
+    #[serde(rename = "loadBalancing", skip_serializing_if = "Option::is_none")]
+    pub load_balancing: Option<LoadBalancing>,
+}
+
+/// How an output with several addresses picks the one each batch goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum LoadBalancing {
+    /// Each healthy address in turn
+    #[default]
+    RoundRobin,
+    /// The healthy address with the fewest events queued or being written
+    LeastOutstanding,
+}
+
+/// TLS settings of an output
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct TlsConfig {
+    /// PEM bundle of CAs to verify the server with, instead of the system roots
+    #[serde(rename = "caFile", skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    /// PEM client certificate for mTLS, requires `keyFile`
+    #[serde(rename = "certFile", skip_serializing_if = "Option::is_none")]
+    pub cert_file: Option<String>,
+    /// PEM private key of the client certificate, requires `certFile`
+    #[serde(rename = "keyFile", skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+    /// Skip server certificate verification, for testing only
+    #[serde(rename = "insecureSkipVerify", default)]
+    pub insecure_skip_verify: bool,
+    /// Server name to send in SNI and verify the certificate against, instead of the address host
+    #[serde(rename = "serverName", skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    /// Check the settings are consistent and the referenced files are readable
+    pub fn validate(&self, output_name: &str) -> Result<(), XatuError> {
+        match (&self.cert_file, &self.key_file) {
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Output '{}' tlsConfig needs both certFile and keyFile for mTLS",
+                    output_name
+                )));
+            }
+            _ => {}
+        }
+
+        if self.server_name.as_deref().is_some_and(str::is_empty) {
+            return Err(XatuError::InvalidConfig(format!(
+                "Output '{}' tlsConfig serverName is empty",
+                output_name
+            )));
+        }
+
+        for (field, path) in [
+            ("caFile", &self.ca_file),
+            ("certFile", &self.cert_file),
+            ("keyFile", &self.key_file),
+        ] {
+            let Some(path) = path else {
+                continue;
+            };
+            std::fs::File::open(path).map_err(|e| {
+                XatuError::InvalidConfig(format!(
+                    "Output '{}' tlsConfig {} '{}': {}",
+                    output_name, field, path, e
+                ))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Auth token read from a file or command, sent as a header and re-read on an interval
+/// so credentials rotated by Vault or Kubernetes secrets are picked up without a restart
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct AuthTokenConfig {
+    /// File holding the token, e.g. a mounted secret
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Shell command printing the token on stdout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Header the token is sent in (defaults to `authorization`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Prepended to the token, e.g. `"Bearer "`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// How often the token is re-read, e.g. `5m` (defaults to 5 minutes)
+    #[serde(rename = "refreshInterval", skip_serializing_if = "Option::is_none")]
+    pub refresh_interval: Option<String>,
+}
+
+impl AuthTokenConfig {
+    /// Refresh interval used when `refreshInterval` is unset
+    pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+    /// Header the token is sent in
+    pub fn header_name(&self) -> &str {
+        self.header.as_deref().unwrap_or("authorization")
+    }
+
+    /// Interval the token is re-read at, failing on malformed or zero durations
+    pub fn refresh_interval(&self) -> Result<Duration, XatuError> {
+        let interval = self
+            .refresh_interval
+            .as_deref()
+            .map(parse_duration)
+            .transpose()?
+            .unwrap_or(Self::DEFAULT_REFRESH_INTERVAL);
+        if interval.is_zero() {
+            return Err(XatuError::InvalidConfig(
+                "authToken refreshInterval must be above zero".to_string(),
+            ));
+        }
+        Ok(interval)
+    }
+
+    /// Check exactly one token source is set and the interval is valid
+    pub fn validate(&self, output_name: &str) -> Result<(), XatuError> {
+        if self.file.is_some() == self.command.is_some() {
+            return Err(XatuError::InvalidConfig(format!(
+                "Output '{}' authToken needs exactly one of file or command",
+                output_name
+            )));
+        }
+        self.refresh_interval().map(|_| ())
+    }
+}
+
+/// gRPC connection settings of a `xatu` output
+/// Large blob and column batches can need a higher `maxMessageSize` than the sidecar default
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct GrpcConfig {
+    /// Interval of keepalive pings on an idle connection, e.g. `30s`
+    #[serde(rename = "keepaliveInterval", skip_serializing_if = "Option::is_none")]
+    pub keepalive_interval: Option<String>,
+    /// How long a keepalive ping may go unanswered before the connection is dropped
+    #[serde(rename = "keepaliveTimeout", skip_serializing_if = "Option::is_none")]
+    pub keepalive_timeout: Option<String>,
+    /// Timeout for establishing the connection, e.g. `10s`
+    #[serde(rename = "connectTimeout", skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<String>,
+    /// Largest message in bytes the output sends
+    #[serde(rename = "maxMessageSize", skip_serializing_if = "Option::is_none")]
+    pub max_message_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<GrpcRetryConfig>,
+}
+
+/// Retry policy for failed gRPC exports
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct GrpcRetryConfig {
+    /// Attempts per export including the first, 1 disables retries
+    #[serde(rename = "maxAttempts", skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    /// Backoff before the first retry, e.g. `500ms`
+    #[serde(rename = "initialBackoff", skip_serializing_if = "Option::is_none")]
+    pub initial_backoff: Option<String>,
+    /// Upper bound of the exponential backoff
+    #[serde(rename = "maxBackoff", skip_serializing_if = "Option::is_none")]
+    pub max_backoff: Option<String>,
+}
+
+impl GrpcConfig {
+    /// Check durations parse and limits are above zero
+    pub fn validate(&self, output_name: &str) -> Result<(), XatuError> {
+        let invalid = |message: String| {
+            XatuError::InvalidConfig(format!("Output '{}' grpc {}", output_name, message))
+        };
+        let duration = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(parse_duration)
+                .transpose()
+                .map_err(|e| invalid(format!("{}: {}", field, e)))
+        };
+
+        duration("keepaliveInterval", &self.keepalive_interval)?;
+        duration("keepaliveTimeout", &self.keepalive_timeout)?;
+        duration("connectTimeout", &self.connect_timeout)?;
+        if self.max_message_size == Some(0) {
+            return Err(invalid("maxMessageSize must be above zero".to_string()));
+        }
+
+        if let Some(retry) = &self.retry {
+            if retry.max_attempts == Some(0) {
+                return Err(invalid("retry maxAttempts must be at least 1".to_string()));
+            }
+            let initial = duration("retry initialBackoff", &retry.initial_backoff)?;
+            let max = duration("retry maxBackoff", &retry.max_backoff)?;
+            if let (Some(initial), Some(max)) = (initial, max) {
+                if initial > max {
+                    return Err(invalid(format!(
+                        "retry initialBackoff {:?} is above maxBackoff {:?}",
+                        initial, max
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl XatuOutput {
+    /// Check every output and the fallbacks between them
+    pub fn validate_all(outputs: &[XatuOutput]) -> Result<(), XatuError> {
+        for output in outputs {
+            output.validate()?;
+            // The sidecar writes every batch to every output at its one address, it would
+            // silently ignore a fallback or further addresses
+            if cfg!(not(feature = "no-sidecar")) {
+                let native_only = if output.config.fallback_for.is_some() {
+                    Some("fallbackFor")
+                } else if !output.config.addresses.is_empty() {
+                    Some("addresses")
+                } else {
+                    None
+                };
+                if let Some(setting) = native_only {
+                    return Err(XatuError::InvalidConfig(format!(
+                        "Output '{}' sets {}, which needs a build with the no-sidecar feature",
+                        output.name, setting
+                    )));
+                }
+            }
+            let Some(primary) = &output.config.fallback_for else {
+                continue;
+            };
+            let Some(primary_output) = outputs.iter().find(|other| &other.name == primary) else {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Output '{}' is a fallback for '{}', which doesn't exist",
+                    output.name, primary
+                )));
+            };
+            if primary_output.config.fallback_for.is_some() {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Output '{}' is a fallback for '{}', which is a fallback itself",
+                    output.name, primary
+                )));
+            }
+            let fallbacks = outputs
+                .iter()
+                .filter(|other| other.config.fallback_for.as_ref() == Some(primary))
+                .count();
+            if fallbacks > 1 {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Output '{}' has {} fallbacks, at most one is supported",
+                    primary, fallbacks
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the output config before it is handed to the sidecar
+    pub fn validate(&self) -> Result<(), XatuError> {
+        if let Some(auth_token) = &self.config.auth_token {
+            auth_token.validate(&self.name)?;
+        }
+
+        if let Some(grpc) = &self.config.grpc {
+            if self.output_type != "xatu" {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Output '{}' has grpc settings but is a {} output",
+                    self.name, self.output_type
+                )));
+            }
+            grpc.validate(&self.name)?;
+        }
+
+        if !self.config.addresses.is_empty() && self.output_type != "http" {
+            return Err(XatuError::InvalidConfig(format!(
+                "Output '{}' lists addresses but is a {} output, only http outputs balance",
+                self.name, self.output_type
+            )));
+        }
+        if self.config.load_balancing.is_some() && self.config.addresses.is_empty() {
+            return Err(XatuError::InvalidConfig(format!(
+                "Output '{}' sets loadBalancing without any addresses to balance over",
+                self.name
+            )));
+        }
+
+        let Some(tls_config) = &self.config.tls_config else {
+            return Ok(());
+        };
+        if !self.config.tls {
+            return Err(XatuError::InvalidConfig(format!(
+                "Output '{}' has a tlsConfig but tls is false",
+                self.name
+            )));
+        }
+        tls_config.validate(&self.name)
+    }
 }
 
 /// Client information for Xatu
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ClientInfo {
     pub name: String,
     pub version: String,
 }
 
 /// Network information for Ethereum
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Network {
     pub name: String,
     pub id: u64,
 }
 
 /// Ethereum configuration for Xatu
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct XatuEthereum {
     pub implementation: String,
     pub genesis_time: u64,
@@ -106,8 +694,21 @@ pub struct XatuEthereum {
     pub network: Network,
 }
 
+impl XatuEthereum {
+    /// Point the sidecar at another network, e.g. a relaunched devnet
+    pub(crate) fn set_network(&mut self, network_info: &NetworkInfo) {
+        self.genesis_time = network_info.genesis_time;
+        self.seconds_per_slot = network_info.seconds_per_slot;
+        self.slots_per_epoch = network_info.slots_per_epoch;
+        self.network = Network {
+            name: network_info.network_name.clone(),
+            id: network_info.network_id,
+        };
+    }
+}
+
 /// Xatu processor configuration
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct XatuProcessorConfig {
     pub name: String,
     pub outputs: Vec<XatuOutput>,
@@ -118,11 +719,273 @@ pub struct XatuProcessorConfig {
 }
 
 /// Combined configuration to pass to FFI
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FullConfigWithRuntime {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub log_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingConfig>,
     pub processor: XatuProcessorConfig,
+    /// Event schema version the batches are written in, as negotiated with the sidecar
+    pub schema_version: u32,
+}
+
+/// Fully commented example config with one output of each supported type
+pub const EXAMPLE_CONFIG_YAML: &str = r#"# Xatu configuration
+#
+# Pass this file to lighthouse with `--xatu-config /path/to/xatu-config.yaml`.
+# YAML, JSON (.json) and TOML (.toml) files are all accepted.
+
+# Enable or disable event export entirely
+enabled: true
+
+# Name of this node as reported to the sink.
+# `{hostname}`, `{network}` and `{pid}` are expanded at startup, here and in output
+# header values, so one config file can be shared by a fleet.
+name: "dimhouse-node"
+# name: "{network}-{hostname}"
+
+# Exporter implementation to use (defaults to the sidecar based "ffi" exporter)
+# exporter: ffi
+
+# Optional NTP server used for clock correction, both by the sidecar and for event
+# timestamps (the applied offset is exported as clock_offset_ms)
+# ntpServer: "pool.ntp.org"
+
+# Optional ethereum configuration
+ethereum:
+  # Override the network name detected from the genesis validators root or chain spec,
+  # a disagreeing override is reported in a NETWORK_MISMATCH event
+  # overrideNetworkName: "mainnet"
+
+# Optional peer filtering applied before events are queued.
+# If `allow` is set only events from those peers are exported; `deny` always wins.
+# peerFilter:
+#   allow: []
+#   deny: []
+
+# Optional slot/epoch window (inclusive). Events outside it are skipped.
+# slotRange:
+#   startSlot: 1000
+#   endSlot: 2000
+#   startEpoch: 30
+#   endEpoch: 60
+
+# Optional per-event-type sample rates (0.0 - 1.0), keyed on the gossip message id
+# sampling:
+#   block: 1.0
+#   attestation: 0.1
+#   aggregate: 1.0
+#   blobSidecar: 1.0
+#   dataColumnSidecar: 0.5
+
+# Optional per-slot aggregation. `aggregate` exports one SLOT_AGGREGATE event per slot and
+# gossip event type (count, unique peers, min/median arrival) instead of the raw events,
+# `both` exports the aggregates alongside them. Filters apply, sampling doesn't.
+# attestationData groups attestations with identical data within each batch into one
+# ATTESTATION_GROUP event with a count and the sending peers.
+# aggregation:
+#   mode: aggregate
+#   attestationData: true
+
+# Optional duplicate suppression. With firstSeenAttestations only the first attestation
+# per (slot, committee, attester index) is exported, keeping its first-arrival timing.
+# The seen keys are bounded by maxEntries, the oldest are forgotten first.
+# dedup:
+#   firstSeenAttestations: true
+#   maxEntries: 262144
+
+# Optional privacy settings. Without a salt a random one is generated each run.
+# privacy:
+#   anonymizePeerIds: true
+#   salt: "change-me"
+
+# Optional sidecar logging, independent of the lighthouse log settings.
+# Without a file, sidecar lines show up in the lighthouse log under the xatu_sidecar target.
+# logging:
+#   level: info
+#   format: text
+#   file: /var/log/xatu-sidecar.log
+
+# Seconds between the INFO pipeline summary log lines (0 disables, defaults to 60)
+# summaryInterval: 60
+
+# Drop gossip events more than this many slots behind the wallclock when they are dequeued,
+# so after a sink outage the exporter catches up with fresh data (unset keeps every event)
+# maxEventAgeSlots: 32
+
+# Skew in milliseconds, from NTP or implied by gossip block arrivals, above which
+# CLOCK_SKEW events are emitted (defaults to 500)
+# clockSkewThresholdMs: 500
+
+# Limit what is sent toward the sink, so catching up after a stall doesn't hit shared
+# ingestion in one burst. Batches over the limit wait, up to 16 of them, and further
+# batches are dropped meanwhile. burstSeconds of the rate may go out at once (defaults to 1)
+# rateLimit:
+#   eventsPerSecond: 5000
+#   bytesPerSecond: 10485760
+#   burstSeconds: 2
+
+# Serialized batch size in bytes at which a batch is sent early, in addition to the 10000
+# event and 1 second limits (defaults to 4 MiB, 0 disables). Counted per event, before the
+# dictionary of schema 3 batches, so those come out smaller
+# maxBatchBytes: 4194304
+
+# Approximate memory the event queue may hold during a sink outage. Attestations are
+# dropped once half of it is used, other gossip events at three quarters, blocks and the
+# exporter's own events only when it is full (unset only bounds the queue to 10000 events)
+# maxQueueBytes: 268435456
+
+# Initialize the sidecar in the background instead of blocking node startup on it, retrying
+# every retryInterval. Up to bufferEvents events are held until it succeeds
+# lazyInit:
+#   bufferEvents: 10000
+#   retryInterval: 5s
+
+# Gossip events received before genesis are held back until genesis and exported after a
+# GENESIS marker event, or dropped with mode: drop (bufferEvents defaults to 10000)
+# preGenesis:
+#   mode: buffer
+#   bufferEvents: 10000
+
+outputs:
+# gRPC output to a xatu server
+- name: xatu
+  type: xatu
+  config:
+    address: localhost:8080
+    # Extra headers sent with every request (e.g. authorization), placeholders allowed
+    headers: {}
+    tls: false
+    maxQueueSize: 500000
+    batchTimeout: 1s
+    exportTimeout: 15s
+    maxExportBatchSize: 1000
+    workers: 5
+    # Optional TLS settings, only used with `tls: true`
+    # tlsConfig:
+    #   caFile: /etc/xatu/ca.pem
+    #   # Client certificate and key for mTLS, both or neither
+    #   certFile: /etc/xatu/client.pem
+    #   keyFile: /etc/xatu/client-key.pem
+    #   insecureSkipVerify: false
+    #   serverName: xatu.example.com
+    # Optional auth token from a file or a command, re-read every refreshInterval
+    # and sent in `header`, so rotated credentials don't need a restart
+    # authToken:
+    #   file: /var/run/secrets/xatu/token
+    #   # command: "vault kv get -field=token secret/xatu"
+    #   header: authorization
+    #   prefix: "Bearer "
+    #   refreshInterval: 5m
+    # Optional gRPC tuning, sidecar defaults where unset
+    # grpc:
+    #   keepaliveInterval: 30s
+    #   keepaliveTimeout: 10s
+    #   connectTimeout: 10s
+    #   # Raise for large blob and data column batches
+    #   maxMessageSize: 67108864
+    #   retry:
+    #     maxAttempts: 3
+    #     initialBackoff: 500ms
+    #     maxBackoff: 10s
+
+# HTTP output posting batches of JSON events
+- name: http
+  type: http
+  config:
+    address: http://localhost:8081
+    headers: {}
+    tls: false
+    maxQueueSize: 500000
+    batchTimeout: 1s
+    exportTimeout: 15s
+    maxExportBatchSize: 1000
+    workers: 1
+    # Spread batches over further ingest addresses (no-sidecar builds only), skipping
+    # addresses that keep failing. loadBalancing: roundRobin (default) or leastOutstanding
+    # addresses:
+    #   - http://ingest-2:8081
+    #   - http://ingest-3:8081
+    # loadBalancing: leastOutstanding
+
+# Stdout output, useful for local testing.
+# The address is ignored by the sidecar but required by the config parser.
+- name: log
+  type: stdout
+  config:
+    address: stdout
+
+# File output appending one JSON batch per line.
+# Only available in builds with the `no-sidecar` feature, like fallbackFor, which makes an
+# output receive batches only while the named output is failing.
+# - name: file
+#   type: file
+#   config:
+#     address: /var/lib/xatu/events.jsonl
+#     fallbackFor: http
+"#;
+
+/// Environment variable that disables Xatu when no config file is given
+pub const DISABLE_XATU_ENV: &str = "DISABLE_XATU";
+
+/// Resolved enable/disable state and where it came from
+/// Precedence is config file, then the `DISABLE_XATU` env var, then enabled by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnableMode {
+    /// Set explicitly by the config file
+    Config(bool),
+    /// Disabled by the `DISABLE_XATU` env var
+    EnvDisabled,
+    /// Nothing configured, enabled by default
+    Default,
+}
+
+impl EnableMode {
+    /// Resolve the mode from the config file value and the env var
+    pub fn resolve(config_enabled: Option<bool>, env_disabled: bool) -> Self {
+        match (config_enabled, env_disabled) {
+            (Some(enabled), _) => EnableMode::Config(enabled),
+            (None, true) => EnableMode::EnvDisabled,
+            (None, false) => EnableMode::Default,
+        }
+    }
+
+    /// Check if Xatu should run
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            EnableMode::Config(enabled) => *enabled,
+            EnableMode::EnvDisabled => false,
+            EnableMode::Default => true,
+        }
+    }
+}
+
+/// Supported config file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Detect the format from the file extension, or from the contents if the extension is unknown
+    pub fn detect(path: &str, contents: &str) -> Self {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("json") => ConfigFormat::Json,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            Some("toml") => ConfigFormat::Toml,
+            // JSON documents always start with an object or array
+            _ if contents.trim_start().starts_with(['{', '[']) => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
 }
 
 impl XatuConfig {
@@ -130,23 +993,179 @@ impl XatuConfig {
     pub fn enabled() -> Self {
         Self {
             enabled: true,
-            name: None,
-            outputs: None,
-            ntp_server: None,
-            ethereum: None,
+            ..Self::default()
         }
     }
 
+    /// Add a transform run on the batch thread before events are serialized
+    /// Transforms run in the order they were added, see [`crate::transform`]
+    pub fn with_transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&mut EventData) -> Option<EventData> + Send + Sync + 'static,
+    {
+        self.settings.transforms.push(transform);
+        self
+    }
+
+    /// Example configuration with one output of each supported type
+    pub fn example() -> Self {
+        serde_yaml::from_str(EXAMPLE_CONFIG_YAML).expect("example config should always parse")
+    }
+
+    /// Write the fully commented example configuration to a file
+    pub fn write_example(path: &str) -> Result<(), XatuError> {
+        std::fs::write(path, EXAMPLE_CONFIG_YAML)
+            .map_err(|e| XatuError::ConfigIo(format!("Failed to write example config file: {}", e)))
+    }
+
     /// Check if Xatu is enabled
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
 
+    /// Resolve whether Xatu should run given an optional loaded config file
+    pub fn resolve_enabled(config: Option<&Self>) -> EnableMode {
+        EnableMode::resolve(
+            config.map(|c| c.enabled),
+            std::env::var(DISABLE_XATU_ENV).is_ok(),
+        )
+    }
+
     /// Load configuration from file
-    pub fn from_file(path: &str) -> Result<Self, String> {
+    /// The format is chosen from the file extension, falling back to content sniffing
+    pub fn from_file(path: &str) -> Result<Self, XatuError> {
         let contents = std::fs::read_to_string(path)
-            .map_err(|e| format!("Failed to read config file: {}", e))?;
-        serde_yaml::from_str(&contents).map_err(|e| format!("Failed to parse config file: {}", e))
+            .map_err(|e| XatuError::ConfigIo(format!("Failed to read config file: {}", e)))?;
+        match ConfigFormat::detect(path, &contents) {
+            ConfigFormat::Json => serde_json::from_str(&contents)
+                .map_err(|e| XatuError::ConfigParse(format!("JSON: {}", e))),
+            ConfigFormat::Yaml => serde_yaml::from_str(&contents)
+                .map_err(|e| XatuError::ConfigParse(format!("YAML: {}", e))),
+            ConfigFormat::Toml => toml::from_str(&contents)
+                .map_err(|e| XatuError::ConfigParse(format!("TOML: {}", e))),
+        }
+    }
+
+    /// Save configuration to file, using the format implied by the file extension
+    pub fn to_file(&self, path: &str) -> Result<(), XatuError> {
+        let contents =
+            match ConfigFormat::detect(path, "") {
+                ConfigFormat::Json => serde_json::to_string_pretty(self)
+                    .map_err(|e| XatuError::Serialization(e.to_string()))?,
+                ConfigFormat::Yaml => serde_yaml::to_string(self)
+                    .map_err(|e| XatuError::Serialization(e.to_string()))?,
+                ConfigFormat::Toml => toml::to_string_pretty(self)
+                    .map_err(|e| XatuError::Serialization(e.to_string()))?,
+            };
+        std::fs::write(path, contents)
+            .map_err(|e| XatuError::ConfigIo(format!("Failed to write config file: {}", e)))
+    }
+
+    /// Apply `XATU_*` environment variable overrides on top of the file config
+    ///
+    /// Supported variables:
+    /// - `XATU_NAME`: node name
+    /// - `XATU_OUTPUT_ADDRESS`: address of the first output (a `xatu` output is added if none exist)
+    /// - `XATU_OUTPUT_HEADERS`: comma separated `key=value` headers merged into the first output,
+    ///   which must exist or be added by `XATU_OUTPUT_ADDRESS`
+    /// - `XATU_SAMPLING_{BLOCK,ATTESTATION,AGGREGATE,BLOB_SIDECAR,DATA_COLUMN_SIDECAR}`: sample rates
+    pub fn apply_env_overrides(&mut self) -> Result<(), XatuError> {
+        self.apply_overrides(|key| std::env::var(key).ok())
+    }
+
+    /// Apply overrides from an arbitrary key lookup, see `apply_env_overrides`
+    /// The config is left unchanged if any override is invalid
+    pub fn apply_overrides<F>(&mut self, lookup: F) -> Result<(), XatuError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut config = self.clone();
+
+        if let Some(name) = lookup("XATU_NAME") {
+            config.name = Some(name);
+        }
+
+        if let Some(address) = lookup("XATU_OUTPUT_ADDRESS") {
+            config.first_output_mut().config.address = address;
+        }
+
+        if let Some(headers) = lookup("XATU_OUTPUT_HEADERS") {
+            // Headers alone would add an output without an address
+            if config.outputs.as_deref().unwrap_or_default().is_empty() {
+                return Err(XatuError::InvalidConfig(
+                    "XATU_OUTPUT_HEADERS needs an output, configure one or set XATU_OUTPUT_ADDRESS"
+                        .to_string(),
+                ));
+            }
+            let output = config.first_output_mut();
+            for pair in headers.split(',').filter(|pair| !pair.trim().is_empty()) {
+                let (key, value) = pair.split_once('=').ok_or_else(|| {
+                    XatuError::InvalidConfig(format!(
+                        "XATU_OUTPUT_HEADERS entry '{}' is not key=value",
+                        pair
+                    ))
+                })?;
+                output
+                    .config
+                    .headers
+                    .insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+
+        let sampling_overrides: [(&str, fn(&mut SamplingConfig) -> &mut Option<f64>); 5] = [
+            ("XATU_SAMPLING_BLOCK", |s| &mut s.block),
+            ("XATU_SAMPLING_ATTESTATION", |s| &mut s.attestation),
+            ("XATU_SAMPLING_AGGREGATE", |s| &mut s.aggregate),
+            ("XATU_SAMPLING_BLOB_SIDECAR", |s| &mut s.blob_sidecar),
+            ("XATU_SAMPLING_DATA_COLUMN_SIDECAR", |s| {
+                &mut s.data_column_sidecar
+            }),
+        ];
+        for (key, field) in sampling_overrides {
+            let Some(value) = lookup(key) else {
+                continue;
+            };
+            let rate = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| XatuError::InvalidConfig(format!("{} '{}': {}", key, value, e)))?;
+            let sampling = config
+                .settings
+                .sampling
+                .get_or_insert_with(SamplingConfig::default);
+            *field(sampling) = Some(rate);
+        }
+
+        *self = config;
+        Ok(())
+    }
+
+    /// Get the first output, adding a default `xatu` output if there are none
+    fn first_output_mut(&mut self) -> &mut XatuOutput {
+        let outputs = self.outputs.get_or_insert_with(Vec::new);
+        if outputs.is_empty() {
+            outputs.push(XatuOutput {
+                name: "xatu".to_string(),
+                output_type: "xatu".to_string(),
+                config: OutputConfig {
+                    address: String::new(),
+                    headers: HashMap::new(),
+                    tls: false,
+                    tls_config: None,
+                    auth_token: None,
+                    grpc: None,
+                    max_queue_size: None,
+                    batch_timeout: None,
+                    export_timeout: None,
+                    max_export_batch_size: None,
+                    workers: None,
+                    fallback_for: None,
+                    addresses: Vec::new(),
+                    load_balancing: None,
+                },
+            });
+        }
+        &mut outputs[0]
     }
 
     /// Get a config structure that includes all outputs
@@ -160,8 +1179,81 @@ impl XatuConfig {
         FullConfig {
             node,
             outputs: self.outputs.clone().unwrap_or_default(),
-            ntp_server: self.ntp_server.clone(),
-            ethereum: self.ethereum.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+}
+
+/// Parse a Go style duration such as `500ms`, `15s` or `1m30s`
+///
+/// Like Go's `time.ParseDuration`, which the sidecar uses, a duration is a sequence of
+/// amounts with a unit of `ns`, `us`, `ms`, `s`, `m` or `h`, each amount optionally with a
+/// fraction. A bare number is taken as seconds.
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, XatuError> {
+    let value = value.trim();
+    let invalid = || XatuError::InvalidConfig(format!("Invalid duration '{}'", value));
+    let too_long = || XatuError::InvalidConfig(format!("Duration '{}' is too long", value));
+
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return value
+            .parse()
+            .map(Duration::from_secs)
+            .map_err(|_| too_long());
+    }
+
+    let mut rest = value;
+    let mut nanos: u64 = 0;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (amount, tail) = rest.split_at(split);
+        let split = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(split);
+        rest = tail;
+
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !digits(fraction) {
+            return Err(invalid());
+        }
+        let unit_nanos: u64 = match unit {
+            "ns" => 1,
+            "us" | "\u{b5}s" | "\u{3bc}s" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 3600 * 1_000_000_000,
+            _ => {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Invalid duration unit in '{}', expected ns, us, ms, s, m or h",
+                    value
+                )))
+            }
+        };
+
+        let whole: u64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| too_long())?
+        };
+        // Digits beyond nanosecond precision add nothing, as in Go
+        let mut scale = unit_nanos;
+        let mut fraction_nanos = 0;
+        for digit in fraction.bytes() {
+            scale /= 10;
+            fraction_nanos += u64::from(digit - b'0') * scale;
         }
+        nanos = whole
+            .checked_mul(unit_nanos)
+            .and_then(|n| n.checked_add(fraction_nanos))
+            .and_then(|n| n.checked_add(nanos))
+            .ok_or_else(too_long)?;
     }
+    Ok(Duration::from_nanos(nanos))
 }
@@ -0,0 +1,69 @@
+//! State shared by one set of exporters
+//!
+//! What the exporters know about the chain they export, and what they measure across
+//! events, is kept here rather than in process globals. `init` builds one per chain and the
+//! observer and its middleware share it, so a second observer in the process, or one
+//! started again after a network change, doesn't overwrite another's timing, fork schedule
+//! or handle. Lighthouse registers the pubkey lookup and fork digests on the chain's
+//! context once the beacon chain is built.
+
+use crate::events::SlotTiming;
+use crate::forks::ForkSchedule;
+use crate::handle::XatuHandle;
+use crate::latency::LatencyTracker;
+use crate::networks::NetworkState;
+use crate::skew::BlockArrivals;
+use crate::slot_aggregate::SlotAggregator;
+use crate::validators::ValidatorPubkeys;
+use std::sync::{Arc, RwLock};
+use types::Hash256;
+
+#[derive(Default)]
+pub struct XatuContext {
+    /// Fork names and digests resolved for event slots and topics
+    pub forks: ForkSchedule,
+    /// Genesis validators root and network id the exporters were started for
+    pub networks: NetworkState,
+    /// Validator pubkeys events are enriched with
+    pub validators: ValidatorPubkeys,
+    /// Timing latencies are measured against, also used for the slot aggregates
+    timing: RwLock<Option<SlotTiming>>,
+    /// Handle of the sidecar exporter started with this context, if any
+    handle: RwLock<Option<XatuHandle>>,
+    pub(crate) latency: LatencyTracker,
+    pub(crate) skew: BlockArrivals,
+    pub(crate) slot_aggregates: SlotAggregator,
+}
+
+impl XatuContext {
+    /// Create a context for exporters following the network with this genesis validators root
+    pub fn new(genesis_validators_root: Option<Hash256>) -> Arc<Self> {
+        let context = Self::default();
+        if let Some(root) = genesis_validators_root {
+            context.networks.set_genesis_validators_root(root);
+        }
+        Arc::new(context)
+    }
+
+    /// Timing events are measured against, once an exporter started with network info
+    pub fn timing(&self) -> Option<SlotTiming> {
+        *self.timing.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the timing events are measured against, replacing any previous one
+    pub(crate) fn set_timing(&self, timing: SlotTiming) {
+        *self.timing.write().unwrap_or_else(|e| e.into_inner()) = Some(timing);
+    }
+
+    /// Handle of the sidecar exporter started with this context, if any
+    pub fn handle(&self) -> Option<XatuHandle> {
+        self.handle
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn set_handle(&self, handle: XatuHandle) {
+        *self.handle.write().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+    }
+}
@@ -0,0 +1,102 @@
+//! Error types for the Xatu exporter
+
+use std::fmt;
+
+/// Errors produced by the Xatu exporter
+#[derive(Debug, Clone, PartialEq)]
+pub enum XatuError {
+    /// The config file could not be read or written
+    ConfigIo(String),
+    /// The config file could not be parsed
+    ConfigParse(String),
+    /// The config parsed but contains invalid values
+    InvalidConfig(String),
+    /// A value could not be serialized for the sidecar
+    Serialization(String),
+    /// Network info is required but was not provided
+    NetworkInfoMissing,
+    /// The sidecar `Init` call failed with the given error code
+    FfiInit(i32),
+    /// A batch submitted to the sidecar failed with the given error code
+    FfiSend(i32),
+    /// The sidecar forwarder is not initialized
+    SinkUnavailable,
+    /// The event queue is full
+    QueueFull,
+    /// The event queue has been closed
+    QueueDisconnected,
+    /// The dedicated FFI thread failed
+    Thread(String),
+    /// The batch thread did not respond in time
+    Timeout(String),
+    /// The loaded sidecar can't parse the events this crate produces
+    IncompatibleSidecar(String),
+}
+
+impl fmt::Display for XatuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XatuError::ConfigIo(e) => write!(f, "Config file I/O error: {}", e),
+            XatuError::ConfigParse(e) => write!(f, "Failed to parse config: {}", e),
+            XatuError::InvalidConfig(e) => write!(f, "Invalid config: {}", e),
+            XatuError::Serialization(e) => write!(f, "Serialization error: {}", e),
+            XatuError::NetworkInfoMissing => write!(f, "Network info not provided"),
+            XatuError::FfiInit(code) => match code {
+                -1 => write!(f, "Failed to parse configuration"),
+                -2 => write!(f, "Failed to create sink"),
+                -3 => write!(f, "Failed to start sink"),
+                -4 => write!(f, "Network info not provided"),
+                _ => write!(f, "Failed to initialize: error code {}", code),
+            },
+            XatuError::FfiSend(code) => match code {
+                -2 => write!(f, "Failed to parse event data"),
+                -3 => write!(f, "Failed to send event"),
+                -4 => write!(f, "Server returned error"),
+                _ => write!(f, "Unknown error code: {}", code),
+            },
+            XatuError::SinkUnavailable => write!(f, "Forwarder not initialized"),
+            XatuError::QueueFull => write!(f, "Event queue is full"),
+            XatuError::QueueDisconnected => write!(f, "Event queue disconnected"),
+            XatuError::Thread(e) => write!(f, "FFI thread error: {}", e),
+            XatuError::Timeout(e) => write!(f, "Timed out: {}", e),
+            XatuError::IncompatibleSidecar(e) => write!(f, "Incompatible sidecar: {}", e),
+        }
+    }
+}
+
+impl XatuError {
+    /// Short stable name of the failure, used as a metric label
+    pub fn reason(&self) -> &'static str {
+        match self {
+            XatuError::ConfigIo(_) => "config_io",
+            XatuError::ConfigParse(_) => "config_parse",
+            XatuError::InvalidConfig(_) => "invalid_config",
+            XatuError::Serialization(_) => "serialization",
+            XatuError::NetworkInfoMissing => "network_info_missing",
+            XatuError::FfiInit(_) => "init_failed",
+            XatuError::FfiSend(code) => match code {
+                -2 => "parse_failed",
+                -3 => "send_failed",
+                -4 => "server_error",
+                _ => "unknown",
+            },
+            XatuError::SinkUnavailable => "sink_unavailable",
+            XatuError::QueueFull => "queue_full",
+            XatuError::QueueDisconnected => "queue_disconnected",
+            XatuError::Thread(_) => "thread",
+            XatuError::Timeout(_) => "timeout",
+            XatuError::IncompatibleSidecar(_) => "incompatible_sidecar",
+        }
+    }
+
+    /// Error code returned by the sidecar, if the failure came from an FFI call
+    pub fn ffi_code(&self) -> Option<i32> {
+        match self {
+            XatuError::FfiInit(code) | XatuError::FfiSend(code) => Some(*code),
+            XatuError::SinkUnavailable => Some(-1),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for XatuError {}
@@ -0,0 +1,1433 @@
+//! Exported event format
+//!
+//! External tools (replayers, generators, custom exporters) can use the constructors here
+//! to build and serialize events exactly like the sidecar exporter does.
+
+use crate::bandwidth::BandwidthSummary;
+use crate::client::ClientFingerprint;
+use crate::config::NetworkInfo;
+use crate::filter::EventKind;
+use crate::forks::ForkSchedule;
+use crate::handle::XatuStats;
+use crate::latency::EpochLatency;
+use crate::networks::NetworkMismatch;
+use crate::peers::{PeerSummary, SubnetCoverage};
+use crate::skew::ClockSkew;
+use crate::slot_aggregate::SlotAggregate;
+use lighthouse_network::MessageId;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ssz::Encode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use types::{
+    Attestation, AttestationData, BlobSidecar, DataColumnSidecar, DataColumnSubnetId, EthSpec,
+    SignedAggregateAndProof, SignedBeaconBlock, SingleAttestation, SubnetId,
+};
+use uuid::Uuid;
+
+pub use crate::schema::{EventData, EVENT_SCHEMA_VERSION};
+
+/// Sequence number of the next event created in this process
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+fn next_sequence() -> u64 {
+    NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Gossip message details shared by every event
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventMeta {
+    /// Peer id as it should appear in the export (possibly anonymized)
+    pub peer_id: String,
+    /// Hex-encoded gossip message id
+    pub message_id: String,
+    pub topic: String,
+    /// Compressed size of the message on the gossip wire
+    pub message_size: u32,
+    pub timestamp_ms: i64,
+    /// Ids assigned when the event is created
+    pub header: EventHeader,
+    /// Details of the sending peer, when it is in the peer cache
+    pub peer: Option<PeerSummary>,
+    /// Client agent string, exported raw only for event types that carry it
+    /// and as a normalized fingerprint on every event
+    pub client: Option<String>,
+}
+
+impl EventMeta {
+    pub fn new(
+        peer_id: String,
+        message_id: &MessageId,
+        topic: String,
+        message_size: usize,
+        timestamp_millis: u64,
+        client: Option<String>,
+    ) -> Self {
+        Self {
+            peer_id,
+            message_id: hex::encode(&message_id.0),
+            topic,
+            message_size: message_size as u32,
+            timestamp_ms: timestamp_millis as i64,
+            header: EventHeader::new(None),
+            peer: None,
+            client,
+        }
+    }
+
+    /// Attach details of the sending peer
+    pub fn with_peer(mut self, peer: Option<PeerSummary>) -> Self {
+        self.peer = peer;
+        self
+    }
+
+    /// Correct the timestamp by a measured clock offset
+    pub fn with_clock_offset(mut self, offset_ms: Option<i64>) -> Self {
+        if let Some(offset_ms) = offset_ms {
+            self.timestamp_ms = self.timestamp_ms.saturating_add(offset_ms);
+        }
+        self.header.clock_offset_ms = offset_ms;
+        self
+    }
+}
+
+/// Fields identifying an event, shared by every event type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EventHeader {
+    /// Id of the node run that exported the event
+    pub session_id: String,
+    /// Random v4 UUID identifying this event
+    pub event_id: String,
+    /// Per-process event counter, gaps indicate dropped events
+    pub sequence: u64,
+    /// NTP clock offset already applied to `timestamp_ms`, if clock sync is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_offset_ms: Option<i64>,
+}
+
+impl EventHeader {
+    /// Header of a new event in this session, taking the next sequence number
+    pub fn new(clock_offset_ms: Option<i64>) -> Self {
+        Self {
+            session_id: crate::session::id().to_string(),
+            event_id: Uuid::new_v4().to_string(),
+            sequence: next_sequence(),
+            clock_offset_ms,
+        }
+    }
+}
+
+/// Fields shared by the events built from gossip messages, derived from the message's
+/// `EventMeta` and slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GossipHeader {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    /// Arrival time minus the start of the event's slot
+    pub propagation_slot_start_diff_ms: i64,
+    /// Slot at the moment the event was observed
+    pub wallclock_slot: u64,
+    pub wallclock_epoch: u64,
+    /// Fork the event's slot falls in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_digest: Option<String>,
+    /// Fork the gossip topic's digest belongs to, which differs from `fork_name` when a
+    /// message crosses a fork boundary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic_fork_name: Option<String>,
+    /// Digest the gossip topic is scoped to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic_fork_digest: Option<String>,
+    /// Normalized client of the sending peer, when its agent string is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_fingerprint: Option<ClientFingerprint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer: Option<PeerSummary>,
+}
+
+impl GossipHeader {
+    /// Header of an event built from a gossip message for `slot`
+    pub fn new(meta: &EventMeta, slot: u64, timing: &SlotTiming, forks: &ForkSchedule) -> Self {
+        let (fork_name, fork_digest) = forks.name_and_digest(timing.epoch(slot));
+        let (topic_fork_name, topic_fork_digest) = forks.topic_name_and_digest(&meta.topic);
+        let wallclock_slot = timing.wallclock_slot(meta.timestamp_ms);
+
+        Self {
+            header: meta.header.clone(),
+            propagation_slot_start_diff_ms: timing.propagation_ms(slot, meta.timestamp_ms),
+            wallclock_slot,
+            wallclock_epoch: timing.epoch(wallclock_slot),
+            fork_name,
+            fork_digest,
+            topic_fork_name,
+            topic_fork_digest,
+            client_fingerprint: meta.client.as_deref().map(ClientFingerprint::parse),
+            peer: meta.peer.clone(),
+        }
+    }
+}
+
+/// `SESSION_START` event, exported once per node run with the node's metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SessionStartEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub node_name: String,
+    pub implementation: String,
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub network_name: String,
+    pub network_id: u64,
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+    pub slots_per_epoch: u64,
+}
+
+impl SessionStartEvent {
+    pub fn new(
+        node_name: String,
+        network_info: &NetworkInfo,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            node_name,
+            implementation: "lighthouse".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            network_name: network_info.network_name.clone(),
+            network_id: network_info.network_id,
+            genesis_time: network_info.genesis_time,
+            seconds_per_slot: network_info.seconds_per_slot,
+            slots_per_epoch: network_info.slots_per_epoch,
+        }
+    }
+}
+
+/// `NODE_INFO` event, a self-describing header emitted once the exporter is initialized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NodeInfoEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub node_name: String,
+    pub lighthouse_version: String,
+    /// Version of this crate
+    pub xatu_version: String,
+    /// As reported by the loaded sidecar, or the version the crate was built against
+    pub sidecar_version: String,
+    /// Schema version batches are written in, below the crate's if the sink is older
+    pub event_schema_version: u32,
+    /// Chain spec config name, e.g. `mainnet`
+    pub network_name: String,
+    pub genesis_time: u64,
+    /// Registered by lighthouse, absent if it didn't report one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custody_group_count: Option<u64>,
+    /// Event types that aren't sampled away entirely
+    pub enabled_event_types: Vec<String>,
+}
+
+impl NodeInfoEvent {
+    pub fn new(
+        node_name: String,
+        network_info: &NetworkInfo,
+        enabled_event_types: Vec<String>,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            node_name,
+            lighthouse_version: lighthouse_version::VERSION.to_string(),
+            xatu_version: env!("CARGO_PKG_VERSION").to_string(),
+            sidecar_version: crate::ffi::sidecar_version()
+                .map(|version| version.version.clone())
+                .unwrap_or_else(|| env!("XATU_SIDECAR_VERSION").to_string()),
+            event_schema_version: crate::schema::negotiated_version(),
+            network_name: network_info.network_name.clone(),
+            genesis_time: network_info.genesis_time,
+            custody_group_count: crate::node::custody_group_count(),
+            enabled_event_types,
+        }
+    }
+}
+
+/// `EXPORTER_STATS` event, emitted periodically so the exporter's health can be monitored
+/// from the export stream itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ExporterStatsEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub events_queued: u64,
+    pub events_dropped: u64,
+    pub events_sent: u64,
+    pub batches_sent: u64,
+    pub send_errors: u64,
+    /// Batch sends that failed in a row up to now
+    pub consecutive_send_failures: u64,
+    pub batches_in_flight: u64,
+    pub batches_retried: u64,
+    pub queue_len: u64,
+}
+
+impl ExporterStatsEvent {
+    pub fn new(
+        stats: &XatuStats,
+        consecutive_send_failures: u64,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            events_queued: stats.events_queued,
+            events_dropped: stats.events_dropped,
+            events_sent: stats.events_sent,
+            batches_sent: stats.batches_sent,
+            send_errors: stats.send_errors,
+            consecutive_send_failures,
+            batches_in_flight: stats.batches_in_flight,
+            batches_retried: stats.batches_retried,
+            queue_len: stats.queue_len as u64,
+        }
+    }
+}
+
+/// `NETWORK_MISMATCH` event, emitted at startup when `overrideNetworkName` disagrees with the
+/// network the genesis validators root belongs to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NetworkMismatchEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub node_name: String,
+    /// Network name from the override, which the other events are exported with
+    pub configured_network_name: String,
+    /// Public network the genesis validators root belongs to
+    pub detected_network_name: String,
+    pub genesis_validators_root: String,
+}
+
+impl NetworkMismatchEvent {
+    pub fn new(
+        node_name: String,
+        mismatch: &NetworkMismatch,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            node_name,
+            configured_network_name: mismatch.configured.clone(),
+            detected_network_name: mismatch.detected.clone(),
+            genesis_validators_root: mismatch.genesis_validators_root.clone(),
+        }
+    }
+}
+
+/// `CUSTODY` event, emitted when the local node's PeerDAS custody assignment is reported or
+/// changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CustodyEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub node_name: String,
+    pub custody_group_count: u64,
+    /// Count before this change, absent the first time custody is reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_custody_group_count: Option<u64>,
+    /// Data column indices the node custodies, sorted
+    pub custody_columns: Vec<u64>,
+}
+
+impl CustodyEvent {
+    pub fn new(
+        node_name: String,
+        custody_group_count: u64,
+        previous_custody_group_count: Option<u64>,
+        custody_columns: &[u64],
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        let mut custody_columns = custody_columns.to_vec();
+        custody_columns.sort_unstable();
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            node_name,
+            custody_group_count,
+            previous_custody_group_count,
+            custody_columns,
+        }
+    }
+}
+
+/// `PEER_CUSTODY` event, emitted when a connected peer advertises a new PeerDAS custody
+/// group count in its metadata or ENR
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PeerCustodyEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    /// Peer id as it should appear in the export (possibly anonymized)
+    pub peer_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer: Option<PeerSummary>,
+    pub custody_group_count: u64,
+    /// Count the peer advertised before, absent the first time it is seen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_custody_group_count: Option<u64>,
+    /// Data column indices derived from the peer's node id, sorted, empty if not derived
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custody_columns: Vec<u64>,
+}
+
+impl PeerCustodyEvent {
+    pub fn new(
+        peer_id: String,
+        peer: Option<PeerSummary>,
+        custody_group_count: u64,
+        previous_custody_group_count: Option<u64>,
+        custody_columns: &[u64],
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        let mut custody_columns = custody_columns.to_vec();
+        custody_columns.sort_unstable();
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            peer_id,
+            peer,
+            custody_group_count,
+            previous_custody_group_count,
+            custody_columns,
+        }
+    }
+}
+
+/// `SUBNET_COVERAGE` event, emitted periodically with how many connected peers advertise
+/// each gossip subnet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SubnetCoverageEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    /// Connected peers that advertised their subnets
+    pub peer_count: u64,
+    /// Peers per attestation subnet, indexed by subnet id
+    pub attestation_subnet_peers: Vec<u64>,
+    /// Peers per sync committee subnet, indexed by subnet id
+    pub sync_committee_subnet_peers: Vec<u64>,
+    /// Peers per data column subnet, indexed by subnet id
+    pub data_column_subnet_peers: Vec<u64>,
+}
+
+impl SubnetCoverageEvent {
+    pub fn new(coverage: SubnetCoverage, clock_offset_ms: Option<i64>) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            peer_count: coverage.peers,
+            attestation_subnet_peers: coverage.attestation,
+            sync_committee_subnet_peers: coverage.sync_committee,
+            data_column_subnet_peers: coverage.data_column,
+        }
+    }
+}
+
+/// Gossip traffic on one topic during a `BANDWIDTH` interval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TopicBandwidth {
+    pub topic: String,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub messages_in: u64,
+    pub messages_out: u64,
+}
+
+/// Gossip traffic with one peer during a `BANDWIDTH` interval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PeerBandwidth {
+    /// Peer id as it should appear in the export (possibly anonymized)
+    pub peer_id: String,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub messages_in: u64,
+    pub messages_out: u64,
+}
+
+/// `BANDWIDTH` event, emitted periodically with the gossip bytes exchanged since the last one,
+/// per topic and per peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BandwidthEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    /// Length of the interval the totals cover
+    pub interval_ms: u64,
+    /// Sorted by topic
+    pub topics: Vec<TopicBandwidth>,
+    /// Sorted by total bytes, heaviest first; messages published to the whole mesh are only
+    /// counted per topic
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peers: Vec<PeerBandwidth>,
+}
+
+impl BandwidthEvent {
+    pub(crate) fn new(summary: BandwidthSummary, clock_offset_ms: Option<i64>) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            interval_ms: summary.interval.as_millis() as u64,
+            topics: summary.topics,
+            peers: summary.peers,
+        }
+    }
+}
+
+/// Propagation latency distribution of one event type during a `PROPAGATION_LATENCY` epoch
+/// Percentiles are accurate to the 10ms histogram buckets, minimum and maximum are exact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LatencySummary {
+    pub event_type: String,
+    pub count: u64,
+    pub min_ms: i64,
+    pub p50_ms: i64,
+    pub p90_ms: i64,
+    pub p99_ms: i64,
+    pub max_ms: i64,
+}
+
+/// `PROPAGATION_LATENCY` event, emitted once per epoch with percentiles of
+/// `propagation_slot_start_diff_ms` per event type, taken before sampling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PropagationLatencyEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub epoch: u64,
+    pub summaries: Vec<LatencySummary>,
+}
+
+impl PropagationLatencyEvent {
+    pub(crate) fn new(latency: EpochLatency, clock_offset_ms: Option<i64>) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            epoch: latency.epoch,
+            summaries: latency.summaries,
+        }
+    }
+}
+
+/// `SLOT_AGGREGATE` event, emitted in aggregation mode once per slot and gossip event type in
+/// place of (or alongside) the raw events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SlotAggregateEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub slot: u64,
+    pub epoch: u64,
+    /// Gossip event type the aggregate covers, e.g. `ATTESTATION`
+    pub aggregated_event_type: String,
+    pub count: u64,
+    pub unique_peers: u64,
+    /// Earliest and median arrival relative to the slot start
+    pub min_arrival_ms: i64,
+    pub median_arrival_ms: i64,
+}
+
+impl SlotAggregateEvent {
+    pub(crate) fn new(
+        aggregate: SlotAggregate,
+        timing: &SlotTiming,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            slot: aggregate.slot,
+            epoch: timing.epoch(aggregate.slot),
+            aggregated_event_type: aggregate.kind.event_type().to_string(),
+            count: aggregate.count,
+            unique_peers: aggregate.unique_peers,
+            min_arrival_ms: aggregate.min_arrival_ms,
+            median_arrival_ms: aggregate.median_arrival_ms,
+        }
+    }
+}
+
+/// `CLOCK_SKEW` event, emitted periodically while the local clock looks skewed
+/// The NTP skew is the `clock_offset_ms` the event carries; skews are the offset to add to
+/// the clock, positive when it runs behind
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ClockSkewEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    /// Skew above which the event is emitted
+    pub threshold_ms: u64,
+    /// Recent gossip blocks the peer-implied skew is estimated from
+    pub block_samples: u64,
+    /// Median block arrival relative to the slot start, on the corrected clock
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub median_block_arrival_ms: Option<i64>,
+    /// Skew left after NTP correction, as implied by the block arrivals
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peer_implied_skew_ms: Option<i64>,
+}
+
+impl ClockSkewEvent {
+    pub(crate) fn new(skew: ClockSkew) -> Self {
+        Self {
+            header: EventHeader::new(skew.ntp_offset_ms),
+            timestamp_ms: now_ms().saturating_add(skew.ntp_offset_ms.unwrap_or(0)),
+            threshold_ms: skew.threshold_ms,
+            block_samples: skew.block_samples,
+            median_block_arrival_ms: skew.median_block_arrival_ms,
+            peer_implied_skew_ms: skew.peer_implied_skew_ms,
+        }
+    }
+}
+
+/// `OUTPUT_FAILOVER` event, emitted when batches switch from an output to its fallback or back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct OutputFailoverEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub primary_output: String,
+    pub fallback_output: String,
+    /// Output receiving batches from now on
+    pub active_output: String,
+    /// Failed writes to the primary in a row, zero when switching back
+    pub consecutive_failures: u32,
+    /// Last error of the primary, when switching to the fallback
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl OutputFailoverEvent {
+    /// `failed_over` is true when switching to the fallback, false when switching back
+    pub fn new(
+        primary_output: &str,
+        fallback_output: &str,
+        failed_over: bool,
+        consecutive_failures: u32,
+        error: Option<String>,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        let active_output = if failed_over {
+            fallback_output
+        } else {
+            primary_output
+        };
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            primary_output: primary_output.to_string(),
+            fallback_output: fallback_output.to_string(),
+            active_output: active_output.to_string(),
+            consecutive_failures,
+            error,
+        }
+    }
+}
+
+/// `GENESIS` marker event, emitted once genesis passes when the exporter started before it
+/// Gossip events held back before genesis follow it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GenesisEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    /// Genesis time in seconds since the unix epoch
+    pub genesis_time: u64,
+    /// Gossip events received before genesis and exported after this event
+    pub events_buffered: u64,
+    /// Gossip events received before genesis and dropped
+    pub events_dropped: u64,
+}
+
+impl GenesisEvent {
+    pub(crate) fn new(
+        genesis_time: u64,
+        events_buffered: u64,
+        events_dropped: u64,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            genesis_time,
+            events_buffered,
+            events_dropped,
+        }
+    }
+}
+
+/// `NETWORK_CHANGED` event, emitted when the node moved to another network, e.g. a devnet
+/// relaunched under it, and the exporter restarted for the new one
+/// The other events from this session that follow it belong to the new network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NetworkChangedEvent {
+    #[serde(flatten)]
+    pub header: EventHeader,
+    pub timestamp_ms: i64,
+    pub node_name: String,
+    pub network_name: String,
+    pub network_id: u64,
+    /// Genesis time in seconds since the unix epoch
+    pub genesis_time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genesis_validators_root: Option<String>,
+    pub previous_network_name: String,
+    pub previous_network_id: u64,
+    pub previous_genesis_time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_genesis_validators_root: Option<String>,
+}
+
+impl NetworkChangedEvent {
+    pub fn new(
+        node_name: String,
+        previous: &NetworkInfo,
+        previous_genesis_validators_root: Option<String>,
+        current: &NetworkInfo,
+        genesis_validators_root: Option<String>,
+        clock_offset_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            header: EventHeader::new(clock_offset_ms),
+            timestamp_ms: now_ms().saturating_add(clock_offset_ms.unwrap_or(0)),
+            node_name,
+            network_name: current.network_name.clone(),
+            network_id: current.network_id,
+            genesis_time: current.genesis_time,
+            genesis_validators_root,
+            previous_network_name: previous.network_name.clone(),
+            previous_network_id: previous.network_id,
+            previous_genesis_time: previous.genesis_time,
+            previous_genesis_validators_root,
+        }
+    }
+}
+
+/// Chain timing used to derive slot-relative event fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotTiming {
+    /// Genesis time in seconds since the unix epoch
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+    pub slots_per_epoch: u64,
+}
+
+impl SlotTiming {
+    pub fn from_network_info(network_info: &NetworkInfo) -> Self {
+        Self {
+            genesis_time: network_info.genesis_time,
+            seconds_per_slot: network_info.seconds_per_slot,
+            slots_per_epoch: network_info.slots_per_epoch,
+        }
+    }
+
+    /// Epoch containing the slot
+    pub fn epoch(&self, slot: u64) -> u64 {
+        slot / self.slots_per_epoch.max(1)
+    }
+
+    /// Start of the slot in milliseconds since the unix epoch
+    pub fn slot_start_ms(&self, slot: u64) -> i64 {
+        let seconds = self
+            .genesis_time
+            .saturating_add(slot.saturating_mul(self.seconds_per_slot));
+        i64::try_from(seconds.saturating_mul(1000)).unwrap_or(i64::MAX)
+    }
+
+    /// Slot containing the timestamp, 0 before genesis
+    pub fn wallclock_slot(&self, timestamp_ms: i64) -> u64 {
+        let since_genesis_ms = timestamp_ms.saturating_sub(self.slot_start_ms(0));
+        let slot_ms = self.seconds_per_slot.saturating_mul(1000).max(1);
+        u64::try_from(since_genesis_ms).unwrap_or(0) / slot_ms
+    }
+
+    /// Milliseconds between the start of the slot and the arrival time (negative if early)
+    pub fn propagation_ms(&self, slot: u64, timestamp_ms: i64) -> i64 {
+        timestamp_ms.saturating_sub(self.slot_start_ms(slot))
+    }
+}
+
+/// Common behavior of every exported event struct
+pub trait ExportEvent: Serialize {
+    /// The kind of gossip message this event is built from
+    const KIND: EventKind;
+
+    /// The `event_type` tag this event is serialized with
+    fn event_type(&self) -> &'static str {
+        Self::KIND.event_type()
+    }
+
+    /// Serialize the event as a tagged JSON object into a writer
+    fn serialize_into<W: std::io::Write>(&self, writer: W) -> serde_json::Result<()>
+    where
+        Self: Sized,
+    {
+        serde_json::to_writer(
+            writer,
+            &Tagged {
+                event_type: self.event_type(),
+                event: self,
+            },
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct Tagged<'a, T> {
+    event_type: &'static str,
+    #[serde(flatten)]
+    event: &'a T,
+}
+
+/// `BEACON_BLOCK` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BeaconBlockEvent {
+    pub peer_id: String,
+    pub message_id: String,
+    pub topic: String,
+    pub message_size: u32,
+    /// Compressed gossip wire size, same as `message_size`
+    pub wire_size: u32,
+    /// SSZ-encoded size after snappy decompression
+    pub decoded_size: u32,
+    pub timestamp_ms: i64,
+    #[serde(flatten)]
+    pub header: GossipHeader,
+    pub slot: u64,
+    pub epoch: u64,
+    pub block_root: String,
+    pub proposer_index: u64,
+    /// Resolved when a validator pubkey lookup is registered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposer_pubkey: Option<String>,
+    pub parent_root: String,
+    pub state_root: String,
+    /// Graffiti as UTF-8 with trailing zero bytes removed
+    pub graffiti: String,
+    pub attestation_count: u32,
+    pub deposit_count: u32,
+    pub voluntary_exit_count: u32,
+    /// Number of sync committee members that signed, absent before Altair
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_aggregate_participation: Option<u32>,
+    /// Number of blob KZG commitments, absent before Deneb
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_kzg_commitments_count: Option<u32>,
+    /// Blob limit at the block's epoch, absent before Deneb
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_blobs_per_block: Option<u32>,
+    /// Execution payload fields, absent before Bellatrix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_block_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<String>,
+    /// Decimal string, the value does not fit a JSON number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<String>,
+    /// Absent before Deneb
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<u64>,
+    /// Execution layer requests in the block, absent before Electra
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deposit_request_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdrawal_request_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consolidation_request_count: Option<u32>,
+    /// Limit of the blob KZG commitments list, absent before Fulu
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_blob_commitments_per_block: Option<u32>,
+}
+
+impl BeaconBlockEvent {
+    pub fn new<E: EthSpec>(
+        meta: EventMeta,
+        block: &SignedBeaconBlock<E>,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        let slot = block.slot().as_u64();
+        let header = GossipHeader::new(&meta, slot, timing, forks);
+        let block_root = block.signed_block_header().message.canonical_root();
+        let message = block.message();
+        let body = message.body();
+        let payload = body
+            .execution_payload()
+            .ok()
+            .map(|payload| payload.execution_payload_ref());
+        let requests = body.execution_requests().ok();
+
+        Self {
+            peer_id: meta.peer_id,
+            message_id: meta.message_id,
+            topic: meta.topic,
+            message_size: meta.message_size,
+            wire_size: meta.message_size,
+            decoded_size: block.ssz_bytes_len() as u32,
+            timestamp_ms: meta.timestamp_ms,
+            header,
+            slot,
+            epoch: timing.epoch(slot),
+            block_root: hex_root(block_root.0),
+            proposer_index: message.proposer_index(),
+            proposer_pubkey: None,
+            parent_root: hex_root(message.parent_root().0),
+            state_root: hex_root(message.state_root().0),
+            graffiti: String::from_utf8_lossy(&body.graffiti().0)
+                .trim_end_matches('\0')
+                .to_string(),
+            attestation_count: body.attestations_len() as u32,
+            deposit_count: body.deposits().len() as u32,
+            voluntary_exit_count: body.voluntary_exits().len() as u32,
+            sync_aggregate_participation: body
+                .sync_aggregate()
+                .ok()
+                .map(|sync_aggregate| sync_aggregate.num_set_bits() as u32),
+            blob_kzg_commitments_count: body
+                .blob_kzg_commitments()
+                .ok()
+                .map(|commitments| commitments.len() as u32),
+            max_blobs_per_block: forks
+                .params_at_epoch(timing.epoch(slot))
+                .and_then(|params| params.max_blobs_per_block)
+                .map(|max| max as u32),
+            execution_block_hash: payload.map(|p| hex_root(p.block_hash().into_root().0)),
+            fee_recipient: payload.map(|p| format!("0x{}", hex::encode(p.fee_recipient()))),
+            base_fee_per_gas: payload.map(|p| p.base_fee_per_gas().to_string()),
+            excess_blob_gas: payload.and_then(|p| p.excess_blob_gas().ok()),
+            deposit_request_count: requests.map(|r| r.deposits.len() as u32),
+            withdrawal_request_count: requests.map(|r| r.withdrawals.len() as u32),
+            consolidation_request_count: requests.map(|r| r.consolidations.len() as u32),
+            max_blob_commitments_per_block: block
+                .fork_name_unchecked()
+                .fulu_enabled()
+                .then(|| E::max_blob_commitments_per_block() as u32),
+        }
+    }
+}
+
+/// `ATTESTATION` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AttestationEvent {
+    pub peer_id: String,
+    pub slot: u64,
+    pub epoch: u64,
+    pub attestation_data_root: String,
+    pub subnet_id: u64,
+    pub timestamp_ms: i64,
+    #[serde(flatten)]
+    pub header: GossipHeader,
+    pub message_id: String,
+    pub should_process: bool,
+    pub topic: String,
+    pub message_size: u32,
+    // Additional attestation data fields
+    pub source_epoch: u64,
+    pub source_root: String,
+    pub target_epoch: u64,
+    pub target_root: String,
+    pub committee_index: u64,
+    // Aggregation and signature fields
+    pub aggregation_bits: String,
+    pub signature: String,
+    // Validator specific fields
+    /// Absent for pre-Electra attestations, whose attester is only known from the
+    /// aggregation bits and the committee
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attester_index: Option<u64>,
+    /// Resolved when a validator pubkey lookup is registered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attester_pubkey: Option<String>,
+}
+
+impl AttestationEvent {
+    pub fn new(
+        meta: EventMeta,
+        attestation: &SingleAttestation,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        Self {
+            committee_index: attestation.committee_index,
+            // Single attestations don't carry aggregation bits
+            aggregation_bits: String::from("0x"),
+            signature: format!("0x{}", hex::encode(attestation.signature.serialize())),
+            attester_index: Some(attestation.attester_index),
+            ..Self::from_data(
+                meta,
+                &attestation.data,
+                subnet_id,
+                should_process,
+                timing,
+                forks,
+            )
+        }
+    }
+
+    /// Build the event from a pre-Electra `Attestation`, which identifies its attester by
+    /// the aggregation bits rather than an index
+    pub fn from_attestation<E: EthSpec>(
+        meta: EventMeta,
+        attestation: &Attestation<E>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        let data = attestation.data();
+        let aggregation_bits = match attestation {
+            Attestation::Base(att) => att.aggregation_bits.as_slice(),
+            Attestation::Electra(att) => att.aggregation_bits.as_slice(),
+        };
+
+        Self {
+            committee_index: attestation.committee_index().unwrap_or(data.index),
+            aggregation_bits: format!("0x{}", hex::encode(aggregation_bits)),
+            signature: format!("0x{}", hex::encode(attestation.signature().serialize())),
+            attester_index: None,
+            ..Self::from_data(meta, data, subnet_id, should_process, timing, forks)
+        }
+    }
+
+    /// Fields derived from the attestation data, shared by both attestation formats
+    fn from_data(
+        meta: EventMeta,
+        data: &AttestationData,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        let slot = data.slot.as_u64();
+        let header = GossipHeader::new(&meta, slot, timing, forks);
+
+        Self {
+            peer_id: meta.peer_id,
+            slot,
+            epoch: timing.epoch(slot),
+            attestation_data_root: hex_root(data.beacon_block_root.0),
+            subnet_id: u64::from(subnet_id),
+            timestamp_ms: meta.timestamp_ms,
+            header,
+            message_id: meta.message_id,
+            should_process,
+            topic: meta.topic,
+            message_size: meta.message_size,
+            source_epoch: data.source.epoch.as_u64(),
+            source_root: hex_root(data.source.root.0),
+            target_epoch: data.target.epoch.as_u64(),
+            target_root: hex_root(data.target.root.0),
+            committee_index: data.index,
+            aggregation_bits: String::from("0x"),
+            signature: String::new(),
+            attester_index: None,
+            attester_pubkey: None,
+        }
+    }
+}
+
+/// `ATTESTATION_GROUP` event, replacing the attestations with identical data in a batch when
+/// `aggregation.attestationData` is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AttestationGroupEvent {
+    /// Id of the first attestation in the group
+    pub event_id: String,
+    /// Sequence of every attestation in the group, so gaps still indicate dropped events
+    pub sequences: Vec<u64>,
+    pub session_id: String,
+    /// Arrival of the first attestation in the group
+    pub timestamp_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_offset_ms: Option<i64>,
+    pub slot: u64,
+    pub epoch: u64,
+    pub committee_index: u64,
+    pub attestation_data_root: String,
+    pub source_epoch: u64,
+    pub source_root: String,
+    pub target_epoch: u64,
+    pub target_root: String,
+    /// Attestations in the group
+    pub count: u64,
+    /// Peers that sent them, in order of first arrival
+    pub peers: Vec<String>,
+    /// Attester indices, where the attestations carry them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attester_indices: Vec<u64>,
+    /// Arrival of the first attestation minus the start of the slot
+    pub propagation_slot_start_diff_ms: i64,
+}
+
+impl AttestationGroupEvent {
+    /// Start a group from its first attestation
+    pub fn new(first: AttestationEvent) -> Self {
+        let header = first.header.header;
+        Self {
+            event_id: header.event_id,
+            sequences: vec![header.sequence],
+            session_id: header.session_id,
+            timestamp_ms: first.timestamp_ms,
+            clock_offset_ms: header.clock_offset_ms,
+            slot: first.slot,
+            epoch: first.epoch,
+            committee_index: first.committee_index,
+            attestation_data_root: first.attestation_data_root,
+            source_epoch: first.source_epoch,
+            source_root: first.source_root,
+            target_epoch: first.target_epoch,
+            target_root: first.target_root,
+            count: 1,
+            peers: vec![first.peer_id],
+            attester_indices: first.attester_index.into_iter().collect(),
+            propagation_slot_start_diff_ms: first.header.propagation_slot_start_diff_ms,
+        }
+    }
+
+    /// Add a later attestation with the same data
+    pub fn add(&mut self, attestation: AttestationEvent) {
+        self.sequences.push(attestation.header.header.sequence);
+        self.count += 1;
+        if !self.peers.contains(&attestation.peer_id) {
+            self.peers.push(attestation.peer_id);
+        }
+        if let Some(index) = attestation.attester_index {
+            self.attester_indices.push(index);
+        }
+    }
+}
+
+/// `AGGREGATE_AND_PROOF` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AggregateAndProofEvent {
+    pub peer_id: String,
+    pub slot: u64,
+    pub epoch: u64,
+    pub attestation_data_root: String,
+    pub aggregator_index: u64,
+    pub timestamp_ms: i64,
+    #[serde(flatten)]
+    pub header: GossipHeader,
+    pub message_id: String,
+    pub topic: String,
+    pub message_size: u32,
+    // Additional attestation data fields
+    pub source_epoch: u64,
+    pub source_root: String,
+    pub target_epoch: u64,
+    pub target_root: String,
+    /// First committee of the aggregate, see `committee_bits` for all of them from Electra on
+    pub committee_index: u64,
+    /// Hex-encoded bitfield of the committees the aggregate covers, absent before Electra
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub committee_bits: Option<String>,
+    // Aggregation and signature fields
+    pub aggregation_bits: String, // Hex-encoded aggregation bits
+    /// Number of participating validators in the aggregate
+    pub aggregation_bits_set_count: u32,
+    pub signature: String, // Hex-encoded signature
+    /// Resolved when a validator pubkey lookup is registered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aggregator_pubkey: Option<String>,
+}
+
+impl AggregateAndProofEvent {
+    pub fn new<E: EthSpec>(
+        meta: EventMeta,
+        aggregate: &SignedAggregateAndProof<E>,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        let attestation = aggregate.message().aggregate();
+        let data = attestation.data();
+        let slot = data.slot.as_u64();
+        let header = GossipHeader::new(&meta, slot, timing, forks);
+        let (aggregation_bits, aggregation_bits_set_count, committee_bits) = match attestation {
+            types::AttestationRef::Base(att) => (
+                att.aggregation_bits.as_slice(),
+                att.aggregation_bits.num_set_bits(),
+                None,
+            ),
+            types::AttestationRef::Electra(att) => (
+                att.aggregation_bits.as_slice(),
+                att.aggregation_bits.num_set_bits(),
+                Some(format!("0x{}", hex::encode(att.committee_bits.as_slice()))),
+            ),
+        };
+
+        Self {
+            peer_id: meta.peer_id,
+            slot,
+            epoch: timing.epoch(slot),
+            attestation_data_root: hex_root(data.beacon_block_root.0),
+            aggregator_index: aggregate.message().aggregator_index(),
+            timestamp_ms: meta.timestamp_ms,
+            header,
+            message_id: meta.message_id,
+            topic: meta.topic,
+            message_size: meta.message_size,
+            source_epoch: data.source.epoch.as_u64(),
+            source_root: hex_root(data.source.root.0),
+            target_epoch: data.target.epoch.as_u64(),
+            target_root: hex_root(data.target.root.0),
+            // For Electra, get committee index from committee_bits; for pre-Electra use data.index
+            committee_index: attestation.committee_index().unwrap_or(data.index),
+            committee_bits,
+            aggregation_bits: format!("0x{}", hex::encode(aggregation_bits)),
+            aggregation_bits_set_count: aggregation_bits_set_count as u32,
+            signature: format!("0x{}", hex::encode(aggregate.signature().serialize())),
+            aggregator_pubkey: None,
+        }
+    }
+}
+
+/// `BLOB_SIDECAR` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BlobSidecarEvent {
+    pub peer_id: String,
+    pub slot: u64,
+    pub epoch: u64,
+    pub block_root: String,
+    pub parent_root: String,
+    pub state_root: String,
+    pub proposer_index: u64,
+    pub blob_index: u64,
+    pub kzg_commitment: String,
+    /// Versioned hash of the commitment as referenced by EL blob transactions
+    pub versioned_hash: String,
+    pub timestamp_ms: i64,
+    #[serde(flatten)]
+    pub header: GossipHeader,
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    pub topic: String,
+    pub message_size: u32,
+    /// Compressed gossip wire size, same as `message_size`
+    pub wire_size: u32,
+    /// SSZ-encoded size after snappy decompression
+    pub decoded_size: u32,
+}
+
+impl BlobSidecarEvent {
+    pub fn new<E: EthSpec>(
+        meta: EventMeta,
+        blob_index: u64,
+        blob_sidecar: &BlobSidecar<E>,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        let slot = blob_sidecar.slot().as_u64();
+        let header = GossipHeader::new(&meta, slot, timing, forks);
+        let block_header = &blob_sidecar.signed_block_header.message;
+
+        Self {
+            peer_id: meta.peer_id,
+            slot,
+            epoch: timing.epoch(slot),
+            block_root: hex_root(blob_sidecar.block_root().0),
+            parent_root: hex_root(block_header.parent_root.0),
+            state_root: hex_root(block_header.state_root.0),
+            proposer_index: blob_sidecar.block_proposer_index(),
+            blob_index,
+            kzg_commitment: format!("0x{}", hex::encode(blob_sidecar.kzg_commitment.0)),
+            versioned_hash: hex_root(blob_sidecar.kzg_commitment.calculate_versioned_hash().0),
+            timestamp_ms: meta.timestamp_ms,
+            header,
+            message_id: meta.message_id,
+            client: meta.client,
+            topic: meta.topic,
+            message_size: meta.message_size,
+            wire_size: meta.message_size,
+            decoded_size: blob_sidecar.ssz_bytes_len() as u32,
+        }
+    }
+}
+
+/// `DATA_COLUMN_SIDECAR` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DataColumnSidecarEvent {
+    pub peer_id: String,
+    pub slot: u64,
+    pub epoch: u64,
+    pub block_root: String,
+    pub parent_root: String,
+    pub state_root: String,
+    pub proposer_index: u64,
+    pub column_index: u64,
+    /// Gossip subnet the column was received on
+    pub subnet_id: u64,
+    pub kzg_commitments_count: u32,
+    pub cell_count: u32,
+    pub kzg_proofs_count: u32,
+    pub timestamp_ms: i64,
+    #[serde(flatten)]
+    pub header: GossipHeader,
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    pub topic: String,
+    pub message_size: u32,
+    /// Compressed gossip wire size, same as `message_size`
+    pub wire_size: u32,
+    /// SSZ-encoded size after snappy decompression
+    pub decoded_size: u32,
+}
+
+impl DataColumnSidecarEvent {
+    pub fn new<E: EthSpec>(
+        meta: EventMeta,
+        column_sidecar: &DataColumnSidecar<E>,
+        subnet_id: DataColumnSubnetId,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        let slot = column_sidecar.slot().as_u64();
+        let header = GossipHeader::new(&meta, slot, timing, forks);
+
+        // Extract variant-specific fields (only available on Fulu)
+        let (parent_root, state_root, proposer_index) =
+            if let Ok(block_header) = column_sidecar.signed_block_header() {
+                (
+                    hex_root(block_header.message.parent_root.0),
+                    hex_root(block_header.message.state_root.0),
+                    block_header.message.proposer_index,
+                )
+            } else {
+                // Gloas variant: these fields are not available
+                (String::new(), String::new(), 0)
+            };
+
+        Self {
+            peer_id: meta.peer_id,
+            slot,
+            epoch: timing.epoch(slot),
+            block_root: hex_root(column_sidecar.block_root().0),
+            parent_root,
+            state_root,
+            proposer_index,
+            column_index: *column_sidecar.index(),
+            subnet_id: u64::from(subnet_id),
+            kzg_commitments_count: column_sidecar
+                .kzg_commitments()
+                .map(|c| c.len() as u32)
+                .unwrap_or(0),
+            cell_count: column_sidecar.column().len() as u32,
+            kzg_proofs_count: column_sidecar.kzg_proofs().len() as u32,
+            timestamp_ms: meta.timestamp_ms,
+            header,
+            message_id: meta.message_id,
+            client: meta.client,
+            topic: meta.topic,
+            message_size: meta.message_size,
+            wire_size: meta.message_size,
+            decoded_size: column_sidecar.ssz_bytes_len() as u32,
+        }
+    }
+}
+
+impl EventData {
+    /// Build a `BEACON_BLOCK` event
+    pub fn beacon_block<E: EthSpec>(
+        meta: EventMeta,
+        block: &SignedBeaconBlock<E>,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        BeaconBlockEvent::new(meta, block, timing, forks).into()
+    }
+
+    /// Build an `ATTESTATION` event
+    pub fn attestation(
+        meta: EventMeta,
+        attestation: &SingleAttestation,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        AttestationEvent::new(meta, attestation, subnet_id, should_process, timing, forks).into()
+    }
+
+    /// Build an `ATTESTATION` event from a pre-Electra `Attestation`
+    pub fn base_attestation<E: EthSpec>(
+        meta: EventMeta,
+        attestation: &Attestation<E>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        AttestationEvent::from_attestation(
+            meta,
+            attestation,
+            subnet_id,
+            should_process,
+            timing,
+            forks,
+        )
+        .into()
+    }
+
+    /// Build an `AGGREGATE_AND_PROOF` event
+    pub fn aggregate_and_proof<E: EthSpec>(
+        meta: EventMeta,
+        aggregate: &SignedAggregateAndProof<E>,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        AggregateAndProofEvent::new(meta, aggregate, timing, forks).into()
+    }
+
+    /// Build a `BLOB_SIDECAR` event
+    pub fn blob_sidecar<E: EthSpec>(
+        meta: EventMeta,
+        blob_index: u64,
+        blob_sidecar: &BlobSidecar<E>,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        BlobSidecarEvent::new(meta, blob_index, blob_sidecar, timing, forks).into()
+    }
+
+    /// Build a `DATA_COLUMN_SIDECAR` event
+    pub fn data_column_sidecar<E: EthSpec>(
+        meta: EventMeta,
+        column_sidecar: &DataColumnSidecar<E>,
+        subnet_id: DataColumnSubnetId,
+        timing: &SlotTiming,
+        forks: &ForkSchedule,
+    ) -> Self {
+        DataColumnSidecarEvent::new(meta, column_sidecar, subnet_id, timing, forks).into()
+    }
+}
+
+fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn hex_root(root: [u8; 32]) -> String {
+    format!("0x{}", hex::encode(root))
+}
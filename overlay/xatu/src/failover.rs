@@ -0,0 +1,128 @@
+//! Primary/fallback routing between outputs
+//!
+//! An output with `fallbackFor` only receives batches while its primary is failing. The
+//! primary's circuit opens after `FAILURE_THRESHOLD` failed writes in a row; while it is
+//! open batches go to the fallback, and every `PROBE_INTERVAL` one of them is also sent to
+//! the primary. The first probe that succeeds closes the circuit and traffic switches back.
+//! Each switch is recorded as an `OUTPUT_FAILOVER` event, exported by the batch thread.
+
+use crate::error::XatuError;
+use crate::events::OutputFailoverEvent;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Failed writes in a row that open the circuit
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// How often a batch is sent to a primary whose circuit is open, to detect its recovery
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+static TRANSITIONS: Mutex<Vec<OutputFailoverEvent>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    /// Set while the circuit is open, with the time the next probe is due
+    next_probe: Option<Instant>,
+}
+
+/// Health of a primary output with a fallback
+#[derive(Debug)]
+pub(crate) struct Circuit {
+    primary: String,
+    fallback: String,
+    state: Mutex<CircuitState>,
+}
+
+impl Circuit {
+    pub(crate) fn new(primary: &str, fallback: &str) -> Self {
+        Self {
+            primary: primary.to_string(),
+            fallback: fallback.to_string(),
+            state: Mutex::new(CircuitState::default()),
+        }
+    }
+
+    /// Name of the output the circuit protects
+    pub(crate) fn primary(&self) -> &str {
+        &self.primary
+    }
+
+    /// Whether batches currently go to the fallback
+    pub(crate) fn is_open(&self) -> bool {
+        self.lock().next_probe.is_some()
+    }
+
+    /// Whether the primary should get the next batch, claiming the probe if one is due
+    pub(crate) fn primary_receives(&self) -> bool {
+        let mut state = self.lock();
+        match state.next_probe {
+            None => true,
+            Some(due) if Instant::now() >= due => {
+                state.next_probe = Some(Instant::now() + PROBE_INTERVAL);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Record the outcome of a write to the primary, opening or closing the circuit
+    pub(crate) fn record(&self, result: &Result<(), XatuError>) {
+        let mut state = self.lock();
+        let transition = match result {
+            Ok(()) => {
+                state.consecutive_failures = 0;
+                state.next_probe.take().map(|_| {
+                    info!(
+                        "Output '{}' recovered, switching back from '{}'",
+                        self.primary, self.fallback
+                    );
+                    OutputFailoverEvent::new(
+                        &self.primary,
+                        &self.fallback,
+                        false,
+                        0,
+                        None,
+                        crate::clock::offset_ms(),
+                    )
+                })
+            }
+            Err(e) => {
+                state.consecutive_failures += 1;
+                let opens =
+                    state.next_probe.is_none() && state.consecutive_failures >= FAILURE_THRESHOLD;
+                opens.then(|| {
+                    state.next_probe = Some(Instant::now() + PROBE_INTERVAL);
+                    warn!(
+                        "Output '{}' failed {} times in a row, failing over to '{}': {}",
+                        self.primary, state.consecutive_failures, self.fallback, e
+                    );
+                    OutputFailoverEvent::new(
+                        &self.primary,
+                        &self.fallback,
+                        true,
+                        state.consecutive_failures,
+                        Some(e.to_string()),
+                        crate::clock::offset_ms(),
+                    )
+                })
+            }
+        };
+        if let Some(transition) = transition {
+            TRANSITIONS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(transition);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CircuitState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Take the transitions recorded since the last call, oldest first
+pub(crate) fn take_transitions() -> Vec<OutputFailoverEvent> {
+    std::mem::take(&mut *TRANSITIONS.lock().unwrap_or_else(|e| e.into_inner()))
+}
@@ -0,0 +1,293 @@
+//! In-process stand-in for the Go sidecar, enabled with the `fake-sidecar` feature
+//!
+//! Exports the same C symbols as libxatu, so the `ffi` module calls into it across the real
+//! FFI boundary while nothing is downloaded or linked. Every submitted batch is recorded and
+//! acknowledged from another thread like the sidecar does, which lets CI run end-to-end
+//! tests of `XatuObserver` without the Go library.
+
+// The exported symbols keep the sidecar's Go names
+#![allow(non_snake_case)]
+
+use crate::filter::EventKind;
+use crate::schema::EVENT_SCHEMA_VERSION;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Version the fake reports from `GetVersion`
+pub const FAKE_SIDECAR_VERSION: &str = "fake";
+
+type LogCallback = extern "C" fn(level: c_int, message: *const c_char);
+type BatchCallback = extern "C" fn(batch_id: u64, status: c_int);
+
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+static SCHEMA_VERSION: AtomicU32 = AtomicU32::new(EVENT_SCHEMA_VERSION);
+static CONFIG: Mutex<Option<String>> = Mutex::new(None);
+static BATCHES: Mutex<Vec<RecordedBatch>> = Mutex::new(Vec::new());
+static STATUSES: Mutex<VecDeque<c_int>> = Mutex::new(VecDeque::new());
+static INIT_FAILURES: AtomicUsize = AtomicUsize::new(0);
+static LOG_CALLBACK: Mutex<Option<LogCallback>> = Mutex::new(None);
+static BATCH_CALLBACK: Mutex<Option<BatchCallback>> = Mutex::new(None);
+static OUTPUT_HEADERS: Mutex<Option<HashMap<(String, String), String>>> = Mutex::new(None);
+static GENESIS_TIME: Mutex<Option<u64>> = Mutex::new(None);
+
+/// A batch received through `SubmitEventBatch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedBatch {
+    pub batch_id: u64,
+    /// Schema version the batch was written in, 1 for bare event arrays
+    pub schema_version: u32,
+    /// Parsed JSON array of events
+    pub events: Vec<Value>,
+    /// Status the batch was acknowledged with, 0 for delivered
+    pub status: i32,
+}
+
+impl RecordedBatch {
+    /// `event_type` of every event in the batch
+    pub fn event_types(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .filter_map(|event| event.get("event_type")?.as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+/// Check if `Init` was called and `Shutdown` wasn't since
+pub fn is_initialized() -> bool {
+    INITIALIZED.load(Ordering::Relaxed)
+}
+
+/// Config YAML passed to the last `Init` call
+pub fn config() -> Option<String> {
+    lock(&CONFIG).clone()
+}
+
+/// Header value last set on an output through `SetOutputHeader`
+pub fn output_header(output_name: &str, key: &str) -> Option<String> {
+    lock(&OUTPUT_HEADERS)
+        .as_ref()?
+        .get(&(output_name.to_string(), key.to_string()))
+        .cloned()
+}
+
+/// Genesis time last set through `SetGenesisTime`
+pub fn genesis_time() -> Option<u64> {
+    *lock(&GENESIS_TIME)
+}
+
+/// Every batch received so far, delivered or not
+pub fn batches() -> Vec<RecordedBatch> {
+    lock(&BATCHES).clone()
+}
+
+/// Every event of the delivered batches, in submission order
+pub fn delivered_events() -> Vec<Value> {
+    lock(&BATCHES)
+        .iter()
+        .filter(|batch| batch.status == 0)
+        .flat_map(|batch| batch.events.clone())
+        .collect()
+}
+
+/// Acknowledge the next `count` batches with `status` instead of delivering them
+/// Statuses follow the sidecar's codes, e.g. -3 for a failed send
+pub fn fail_next(count: usize, status: i32) {
+    lock(&STATUSES).extend(std::iter::repeat(status).take(count));
+}
+
+/// Fail the next `count` calls to `Init`, as a sidecar that can't reach its server would
+pub fn fail_next_inits(count: usize) {
+    INIT_FAILURES.store(count, Ordering::Relaxed);
+}
+
+/// Wait until at least `count` events were delivered, returning false on timeout
+pub fn wait_for_delivered(count: usize, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while delivered_events().len() < count {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
+
+/// Report `version` as the newest event schema the fake parses, for downgrade tests
+/// Only affects observers created afterwards
+pub fn set_schema_version(version: u32) {
+    SCHEMA_VERSION.store(version, Ordering::Relaxed);
+}
+
+/// Forget recorded batches and pending failures
+pub fn reset() {
+    lock(&BATCHES).clear();
+    lock(&STATUSES).clear();
+    INIT_FAILURES.store(0, Ordering::Relaxed);
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Send a line through the registered log callback, if any
+fn log(level: c_int, message: &str) {
+    let Some(callback) = *lock(&LOG_CALLBACK) else {
+        return;
+    };
+    if let Ok(message) = CString::new(message) {
+        callback(level, message.as_ptr());
+    }
+}
+
+/// Write `response` NUL-terminated into the caller's buffer like the sidecar does
+unsafe fn write_response(response: &Value, buf: *mut c_char, len: c_int) -> c_int {
+    let bytes = response.to_string().into_bytes();
+    if buf.is_null() || bytes.len() >= len.max(0) as usize {
+        return -1;
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf as *mut u8, bytes.len());
+    *buf.add(bytes.len()) = 0;
+    bytes.len() as c_int
+}
+
+/// Split a batch into its schema version and events, accepting every schema version
+/// Dictionary encoded fields are expanded, so events compare the same in every version
+fn parse_batch(json: &[u8]) -> Option<(u32, Vec<Value>)> {
+    match serde_json::from_slice(json).ok()? {
+        Value::Array(events) => Some((1, events)),
+        Value::Object(mut batch) => {
+            let schema_version = batch.get("schema_version")?.as_u64()? as u32;
+            let dictionary = match batch.remove("dictionary") {
+                Some(Value::Array(dictionary)) => dictionary,
+                _ => Vec::new(),
+            };
+            match batch.remove("events")? {
+                Value::Array(mut events) => {
+                    for event in &mut events {
+                        crate::schema::expand_dictionary(&dictionary, event);
+                    }
+                    Some((schema_version, events))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn Init(config_json: *const c_char) -> c_int {
+    if config_json.is_null() {
+        return -1;
+    }
+    let failing = INIT_FAILURES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+        count.checked_sub(1)
+    });
+    if failing.is_ok() {
+        log(2, "Fake xatu sidecar failed to initialize");
+        return -1;
+    }
+    let config = CStr::from_ptr(config_json).to_string_lossy().into_owned();
+    *lock(&CONFIG) = Some(config);
+    INITIALIZED.store(true, Ordering::Relaxed);
+    log(4, "Fake xatu sidecar initialized");
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn GetVersion(buf: *mut c_char, len: c_int) -> c_int {
+    let response = json!({
+        "version": FAKE_SIDECAR_VERSION,
+        "schemaVersion": SCHEMA_VERSION.load(Ordering::Relaxed),
+    });
+    write_response(&response, buf, len)
+}
+
+#[no_mangle]
+unsafe extern "C" fn GetCapabilities(buf: *mut c_char, len: c_int) -> c_int {
+    let event_types: Vec<&str> = EventKind::ALL
+        .iter()
+        .map(|kind| kind.event_type())
+        .collect();
+    let response = json!({
+        "eventTypes": event_types,
+        "encodings": ["json"],
+    });
+    write_response(&response, buf, len)
+}
+
+#[no_mangle]
+extern "C" fn SetLogCallback(callback: LogCallback) {
+    *lock(&LOG_CALLBACK) = Some(callback);
+}
+
+#[no_mangle]
+extern "C" fn SetBatchCallback(callback: BatchCallback) {
+    *lock(&BATCH_CALLBACK) = Some(callback);
+}
+
+#[no_mangle]
+unsafe extern "C" fn SubmitEventBatch(batch_id: u64, events_json: *const c_char) -> c_int {
+    if !is_initialized() {
+        return -1;
+    }
+    if events_json.is_null() {
+        return -2;
+    }
+    let Some((schema_version, events)) = parse_batch(CStr::from_ptr(events_json).to_bytes()) else {
+        return -2;
+    };
+
+    let status = lock(&STATUSES).pop_front().unwrap_or(0);
+    lock(&BATCHES).push(RecordedBatch {
+        batch_id,
+        schema_version,
+        events,
+        status,
+    });
+
+    // The sidecar acknowledges from its own goroutines, never from inside the submit call
+    if let Some(callback) = *lock(&BATCH_CALLBACK) {
+        std::thread::spawn(move || callback(batch_id, status));
+    }
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn SetOutputHeader(
+    output_name: *const c_char,
+    key: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    if !is_initialized() {
+        return -1;
+    }
+    if output_name.is_null() || key.is_null() || value.is_null() {
+        return -2;
+    }
+    let to_string = |ptr: *const c_char| CStr::from_ptr(ptr).to_string_lossy().into_owned();
+    lock(&OUTPUT_HEADERS)
+        .get_or_insert_with(HashMap::new)
+        .insert((to_string(output_name), to_string(key)), to_string(value));
+    0
+}
+
+#[no_mangle]
+extern "C" fn SetGenesisTime(genesis_time: u64) -> c_int {
+    if !is_initialized() {
+        return -1;
+    }
+    *lock(&GENESIS_TIME) = Some(genesis_time);
+    0
+}
+
+#[no_mangle]
+extern "C" fn Shutdown() {
+    INITIALIZED.store(false, Ordering::Relaxed);
+    log(4, "Fake xatu sidecar shut down");
+}
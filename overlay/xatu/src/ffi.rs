@@ -1,180 +1,305 @@
-use serde::{Deserialize, Serialize};
-use std::ffi::CString;
+use crate::error::XatuError;
+use crate::events::OutputFailoverEvent;
+use crate::filter::EventKind;
+use crate::schema::EVENT_SCHEMA_VERSION;
+use serde::Deserialize;
+use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
-use std::sync::Mutex;
-use tracing::debug;
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, debug_span, error, info, trace, warn};
 
 // Global mutex to ensure thread-safe FFI calls
 static FFI_MUTEX: Mutex<()> = Mutex::new(());
 
-#[link(name = "xatu")]
+// Version reported by the loaded sidecar, set once at init
+static SIDECAR_VERSION: OnceLock<SidecarVersion> = OnceLock::new();
+
+// Capabilities reported by the loaded sidecar, set once at init
+static CAPABILITIES: OnceLock<SidecarCapabilities> = OnceLock::new();
+
+/// Size of the buffer `GetVersion` and `GetCapabilities` write their JSON response into
+const RESPONSE_BUFFER_LEN: usize = 4096;
+
+/// Encoding the batches are sent in
+const BATCH_ENCODING: &str = "json";
+
+/// Tracing target sidecar log lines are emitted under
+const SIDECAR_LOG_TARGET: &str = "xatu_sidecar";
+
+/// Version details reported by the sidecar's `GetVersion` call
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SidecarVersion {
+    pub version: String,
+    /// Newest event schema version the sidecar can parse
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: u32,
+}
+
+/// What the sidecar reports it can parse, from its `GetCapabilities` call
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SidecarCapabilities {
+    /// Supported `event_type` tags
+    #[serde(rename = "eventTypes")]
+    pub event_types: Vec<String>,
+    /// Supported batch encodings, e.g. `json`
+    pub encodings: Vec<String>,
+}
+
+/// Check if the sidecar can parse events of this type
+/// Everything is assumed supported until capabilities have been negotiated
+pub(crate) fn supports_event_type(event_type: &str) -> bool {
+    CAPABILITIES.get().is_none_or(|capabilities| {
+        capabilities
+            .event_types
+            .iter()
+            .any(|supported| supported == event_type)
+    })
+}
+
+/// The sidecar writes every batch to every output, there are no failovers to report
+pub(crate) fn take_failovers() -> Vec<OutputFailoverEvent> {
+    Vec::new()
+}
+
+/// Version of the loaded sidecar, once it has been checked at init
+pub(crate) fn sidecar_version() -> Option<&'static SidecarVersion> {
+    SIDECAR_VERSION.get()
+}
+
+#[cfg_attr(feature = "static-sidecar", link(name = "xatu", kind = "static"))]
+#[cfg_attr(
+    not(any(feature = "static-sidecar", feature = "fake-sidecar")),
+    link(name = "xatu")
+)]
 extern "C" {
     fn Init(config_json: *const c_char) -> c_int;
-    fn SendEventBatch(events_json: *const c_char) -> c_int;
+    /// Writes a NUL-terminated JSON version object into `buf`
+    /// Returns the length written, or a negative code if it doesn't fit
+    fn GetVersion(buf: *mut c_char, len: c_int) -> c_int;
+    /// Like `GetVersion`, with a JSON capabilities object
+    fn GetCapabilities(buf: *mut c_char, len: c_int) -> c_int;
+    /// Queue a batch for sending, its outcome is reported through the batch callback
+    /// Returns non-zero if the batch was rejected outright, no callback follows then
+    fn SubmitEventBatch(batch_id: u64, events_json: *const c_char) -> c_int;
+    /// Report the outcome of every submitted batch to `callback`, 0 meaning delivered
+    fn SetBatchCallback(callback: extern "C" fn(batch_id: u64, status: c_int));
+    /// Route sidecar log lines to `callback` instead of the sidecar's stderr
+    fn SetLogCallback(callback: extern "C" fn(level: c_int, message: *const c_char));
+    /// Replace a header sent by an initialized output, returns non-zero if there is no such output
+    fn SetOutputHeader(
+        output_name: *const c_char,
+        key: *const c_char,
+        value: *const c_char,
+    ) -> c_int;
+    /// Replace the genesis time the sidecar derives slots from, returns non-zero if it isn't
+    /// initialized
+    fn SetGenesisTime(genesis_time: u64) -> c_int;
     fn Shutdown();
 }
 
-// Removed thread ID tracking - not needed
+/// Receives sidecar log lines, called from Go threads
+/// Levels follow logrus: 0 panic, 1 fatal, 2 error, 3 warn, 4 info, 5 debug, 6 trace
+extern "C" fn log_callback(level: c_int, message: *const c_char) {
+    if message.is_null() {
+        return;
+    }
+    // The sidecar owns the string, it is only valid for the duration of the call
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    let message = message.trim_end();
+    match level {
+        ..=2 => error!(target: SIDECAR_LOG_TARGET, "{}", message),
+        3 => warn!(target: SIDECAR_LOG_TARGET, "{}", message),
+        4 => info!(target: SIDECAR_LOG_TARGET, "{}", message),
+        5 => debug!(target: SIDECAR_LOG_TARGET, "{}", message),
+        _ => trace!(target: SIDECAR_LOG_TARGET, "{}", message),
+    }
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "event_type")]
-pub enum EventData {
-    #[serde(rename = "BEACON_BLOCK")]
-    BeaconBlock {
-        peer_id: String,
-        message_id: String,
-        topic: String,
-        message_size: u32,
-        timestamp_ms: i64,
-        slot: u64,
-        epoch: u64,
-        block_root: String,
-        proposer_index: u64,
-    },
-    #[serde(rename = "ATTESTATION")]
-    Attestation {
-        peer_id: String,
-        slot: u64,
-        epoch: u64,
-        attestation_data_root: String,
-        subnet_id: u64,
-        timestamp_ms: i64,
-        message_id: String,
-        should_process: bool,
-        topic: String,
-        message_size: u32,
-        // Additional attestation data fields
-        source_epoch: u64,
-        source_root: String,
-        target_epoch: u64,
-        target_root: String,
-        committee_index: u64,
-        // Aggregation and signature fields
-        aggregation_bits: String,
-        signature: String,
-        // Validator specific fields
-        attester_index: u64,
-    },
-    #[serde(rename = "AGGREGATE_AND_PROOF")]
-    AggregateAndProof {
-        peer_id: String,
-        slot: u64,
-        epoch: u64,
-        attestation_data_root: String,
-        aggregator_index: u64,
-        timestamp_ms: i64,
-        message_id: String,
-        topic: String,
-        message_size: u32,
-        // Additional attestation data fields
-        source_epoch: u64,
-        source_root: String,
-        target_epoch: u64,
-        target_root: String,
-        committee_index: u64,
-        // Aggregation and signature fields
-        aggregation_bits: String, // Hex-encoded aggregation bits
-        signature: String,        // Hex-encoded signature
-    },
-    #[serde(rename = "BLOB_SIDECAR")]
-    BlobSidecar {
-        peer_id: String,
-        slot: u64,
-        epoch: u64,
-        block_root: String,
-        parent_root: String,
-        state_root: String,
-        proposer_index: u64,
-        blob_index: u64,
-        timestamp_ms: i64,
-        message_id: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        client: Option<String>,
-        topic: String,
-        message_size: u32,
-    },
-    #[serde(rename = "DATA_COLUMN_SIDECAR")]
-    DataColumnSidecar {
-        peer_id: String,
-        slot: u64,
-        epoch: u64,
-        block_root: String,
-        parent_root: String,
-        state_root: String,
-        proposer_index: u64,
-        column_index: u64,
-        kzg_commitments_count: u32,
-        timestamp_ms: i64,
-        message_id: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        client: Option<String>,
-        topic: String,
-        message_size: u32,
-    },
+/// Receives batch outcomes, called from Go threads
+extern "C" fn batch_callback(batch_id: u64, status: c_int) {
+    crate::acks::complete(batch_id, status_result(status));
+}
+
+/// Map a sidecar batch status code to a result
+fn status_result(status: c_int) -> Result<(), XatuError> {
+    match status {
+        0 => Ok(()),
+        -1 => Err(XatuError::SinkUnavailable),
+        code => Err(XatuError::FfiSend(code)),
+    }
 }
 
+// Removed thread ID tracking - not needed
+
 pub struct XatuFFI;
 
 impl XatuFFI {
-    pub fn init_with_runtime(config: &crate::config::FullConfigWithRuntime) -> Result<(), String> {
+    pub fn init_with_runtime(
+        config: &crate::config::FullConfigWithRuntime,
+    ) -> Result<(), XatuError> {
         let config_yaml = serde_yaml::to_string(config)
-            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+            .map_err(|e| XatuError::Serialization(format!("Failed to serialize config: {}", e)))?;
 
         // Lock mutex to ensure thread-safe FFI call
         let _guard = FFI_MUTEX
             .lock()
-            .map_err(|e| format!("Failed to lock mutex: {}", e))?;
+            .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
 
-        let c_config =
-            CString::new(config_yaml).map_err(|e| format!("Failed to create CString: {}", e))?;
+        let c_config = CString::new(config_yaml)
+            .map_err(|e| XatuError::Serialization(format!("Failed to create CString: {}", e)))?;
 
         unsafe {
             let result = Init(c_config.as_ptr());
             match result {
                 0 => Ok(()),
-                -1 => Err("Failed to parse configuration".to_string()),
-                -2 => Err("Failed to create sink".to_string()),
-                -3 => Err("Failed to start sink".to_string()),
-                -4 => Err("Network info not provided".to_string()),
-                _ => Err(format!("Failed to initialize: error code {}", result)),
+                code => Err(XatuError::FfiInit(code)),
             }
         }
     }
 
-    pub fn send_event_batch(events: Vec<EventData>) -> Result<(), String> {
-        if events.is_empty() {
-            return Ok(());
+    /// Forward sidecar log lines into the `tracing` subscriber under the `xatu_sidecar` target
+    pub fn set_log_callback() -> Result<(), XatuError> {
+        let _guard = FFI_MUTEX
+            .lock()
+            .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
+        unsafe { SetLogCallback(log_callback) };
+        Ok(())
+    }
+
+    /// Receive batch outcomes on the `acks` channel, must be set before any batch is submitted
+    pub fn set_batch_callback() -> Result<(), XatuError> {
+        let _guard = FFI_MUTEX
+            .lock()
+            .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
+        unsafe { SetBatchCallback(batch_callback) };
+        Ok(())
+    }
+
+    /// Query the sidecar version and negotiate the event schema batches are written in
+    /// Older sidecars get downgraded batches, ones older than any schema we can write are refused
+    pub fn check_version() -> Result<SidecarVersion, XatuError> {
+        let version: SidecarVersion = Self::query_json("GetVersion", GetVersion)?;
+        info!(
+            "Xatu sidecar version {} (event schema {}, crate produces {})",
+            version.version, version.schema_version, EVENT_SCHEMA_VERSION
+        );
+
+        crate::schema::negotiate_version(version.schema_version)?;
+
+        let _ = SIDECAR_VERSION.set(version.clone());
+        Ok(version)
+    }
+
+    /// Query what the sidecar can parse, so unsupported event types are never sent
+    /// Sending them would get the whole batch rejected
+    pub fn negotiate_capabilities() -> Result<SidecarCapabilities, XatuError> {
+        let capabilities: SidecarCapabilities =
+            Self::query_json("GetCapabilities", GetCapabilities)?;
+
+        if !capabilities.encodings.iter().any(|e| e == BATCH_ENCODING) {
+            return Err(XatuError::IncompatibleSidecar(format!(
+                "sidecar supports encodings {:?}, but batches are sent as {}",
+                capabilities.encodings, BATCH_ENCODING
+            )));
+        }
+
+        let unsupported: Vec<&str> = EventKind::ALL
+            .iter()
+            .map(|kind| kind.event_type())
+            .filter(|event_type| !capabilities.event_types.iter().any(|t| t == event_type))
+            .collect();
+        if !unsupported.is_empty() {
+            warn!(
+                "Xatu sidecar can't parse {:?} events, they will not be exported",
+                unsupported
+            );
+        }
+
+        let _ = CAPABILITIES.set(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Call a sidecar function that writes a JSON response into a caller-owned buffer
+    fn query_json<T: serde::de::DeserializeOwned>(
+        name: &str,
+        call: unsafe extern "C" fn(*mut c_char, c_int) -> c_int,
+    ) -> Result<T, XatuError> {
+        let mut buf = vec![0u8; RESPONSE_BUFFER_LEN];
+
+        let written = {
+            let _guard = FFI_MUTEX
+                .lock()
+                .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
+            unsafe { call(buf.as_mut_ptr() as *mut c_char, buf.len() as c_int) }
+        };
+        if written < 0 || written as usize > buf.len() {
+            return Err(XatuError::IncompatibleSidecar(format!(
+                "{} failed with code {}",
+                name, written
+            )));
         }
 
-        // Thread verification removed - not needed for this issue
+        serde_json::from_slice(&buf[..written as usize]).map_err(|e| {
+            XatuError::IncompatibleSidecar(format!("Unreadable {} response: {}", name, e))
+        })
+    }
 
-        let event_count = events.len();
-        // Serialize outside of unsafe block
-        let json_data = serde_json::to_string(&events)
-            .map_err(|e| format!("Failed to serialize events: {}", e))?;
+    /// Submit a batch of `events` events serialized by `schema::serialize_batch`, its outcome
+    /// arrives later on the `acks` channel under `batch_id`
+    /// An error here means the sidecar rejected the batch without queueing it
+    pub fn submit_event_batch(
+        batch_id: u64,
+        events: usize,
+        json_data: String,
+    ) -> Result<(), XatuError> {
+        let _span =
+            debug_span!("xatu_ffi_send", batch_id, events, bytes = json_data.len()).entered();
 
         // Lock mutex to ensure thread-safe FFI call
         let _guard = FFI_MUTEX
             .lock()
-            .map_err(|e| format!("Failed to lock mutex: {}", e))?;
+            .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
 
         // Create CString and keep it alive for the FFI call
-        let c_json =
-            CString::new(json_data).map_err(|e| format!("Failed to create CString: {}", e))?;
+        let c_json = CString::new(json_data)
+            .map_err(|e| XatuError::Serialization(format!("Failed to create CString: {}", e)))?;
 
-        unsafe {
-            let result = SendEventBatch(c_json.as_ptr());
-            match result {
-                0 => {
-                    debug!("Successfully sent batch of {} events", event_count);
-                    Ok(())
-                }
-                -1 => Err("Forwarder not initialized".to_string()),
-                -2 => Err("Failed to parse event data".to_string()),
-                -3 => Err("Failed to send event".to_string()),
-                -4 => Err("Server returned error".to_string()),
-                _ => Err(format!("Unknown error code: {}", result)),
-            }
+        let result = unsafe { SubmitEventBatch(batch_id, c_json.as_ptr()) };
+        status_result(result)?;
+        debug!("Submitted batch {} of {} events", batch_id, events);
+        Ok(())
+    }
+
+    /// Replace a header of an output, e.g. after its auth token was refreshed
+    pub fn set_output_header(output_name: &str, key: &str, value: &str) -> Result<(), XatuError> {
+        let to_c = |value: &str| {
+            CString::new(value)
+                .map_err(|e| XatuError::Serialization(format!("Failed to create CString: {}", e)))
+        };
+        let (c_output_name, c_key, c_value) = (to_c(output_name)?, to_c(key)?, to_c(value)?);
+
+        let _guard = FFI_MUTEX
+            .lock()
+            .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
+        let result =
+            unsafe { SetOutputHeader(c_output_name.as_ptr(), c_key.as_ptr(), c_value.as_ptr()) };
+        match result {
+            0 => Ok(()),
+            code => Err(XatuError::InvalidConfig(format!(
+                "Sidecar rejected header {} for output '{}' with code {}",
+                key, output_name, code
+            ))),
+        }
+    }
+
+    /// Correct the genesis time the sidecar was initialized with
+    pub fn set_genesis_time(genesis_time: u64) -> Result<(), XatuError> {
+        let _guard = FFI_MUTEX
+            .lock()
+            .map_err(|e| XatuError::Thread(format!("Failed to lock mutex: {}", e)))?;
+        match unsafe { SetGenesisTime(genesis_time) } {
+            0 => Ok(()),
+            _ => Err(XatuError::SinkUnavailable),
         }
     }
 
@@ -0,0 +1,269 @@
+//! Event filtering applied before events are queued for export
+
+use crate::config::FullConfig;
+use crate::error::XatuError;
+use libp2p::PeerId;
+use lighthouse_network::MessageId;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Event types that can be filtered individually
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Block,
+    Attestation,
+    Aggregate,
+    BlobSidecar,
+    DataColumnSidecar,
+    /// Emitted once per node run, never sampled
+    SessionStart,
+    /// Emitted once per node run, never sampled
+    NodeInfo,
+    /// Emitted periodically by the exporter, never sampled
+    ExporterStats,
+    /// Emitted at startup if the network override looks wrong, never sampled
+    NetworkMismatch,
+    /// Emitted when the local custody assignment changes, never sampled
+    Custody,
+    /// Emitted when a peer advertises a new custody group count, never sampled
+    PeerCustody,
+    /// Emitted periodically by the exporter, never sampled
+    SubnetCoverage,
+    /// Emitted periodically by the exporter, never sampled
+    Bandwidth,
+    /// Emitted once per epoch by the exporter, never sampled
+    PropagationLatency,
+    /// Emitted per slot and gossip event type in aggregation mode, never sampled
+    SlotAggregate,
+    /// Built from already sampled attestations, never sampled again
+    AttestationGroup,
+    /// Emitted periodically while the local clock looks skewed, never sampled
+    ClockSkew,
+    /// Emitted when batches switch between an output and its fallback, never sampled
+    OutputFailover,
+    /// Emitted once genesis passes when the exporter started before it, never sampled
+    Genesis,
+    /// Emitted when the node moved to another network and the exporter restarted, never
+    /// sampled
+    NetworkChanged,
+}
+
+impl EventKind {
+    /// Every kind of exported event
+    pub const ALL: [EventKind; 20] = [
+        EventKind::Block,
+        EventKind::Attestation,
+        EventKind::Aggregate,
+        EventKind::BlobSidecar,
+        EventKind::DataColumnSidecar,
+        EventKind::SessionStart,
+        EventKind::NodeInfo,
+        EventKind::ExporterStats,
+        EventKind::NetworkMismatch,
+        EventKind::Custody,
+        EventKind::PeerCustody,
+        EventKind::SubnetCoverage,
+        EventKind::Bandwidth,
+        EventKind::PropagationLatency,
+        EventKind::SlotAggregate,
+        EventKind::AttestationGroup,
+        EventKind::ClockSkew,
+        EventKind::OutputFailover,
+        EventKind::Genesis,
+        EventKind::NetworkChanged,
+    ];
+
+    /// Kinds built from gossip messages
+    pub const GOSSIP: [EventKind; 5] = [
+        EventKind::Block,
+        EventKind::Attestation,
+        EventKind::Aggregate,
+        EventKind::BlobSidecar,
+        EventKind::DataColumnSidecar,
+    ];
+
+    /// The `event_type` tag used for events of this kind
+    pub fn event_type(&self) -> &'static str {
+        crate::schema::event_type(*self)
+    }
+}
+
+/// Filter deciding which gossip events are exported
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    allowed_peers: HashSet<PeerId>,
+    denied_peers: HashSet<PeerId>,
+    start_slot: Option<u64>,
+    end_slot: Option<u64>,
+    sample_thresholds: SampleThresholds,
+}
+
+/// Sampling thresholds compared against the first 8 bytes of the message id
+#[derive(Debug, Clone, Default)]
+struct SampleThresholds {
+    block: Option<u64>,
+    attestation: Option<u64>,
+    aggregate: Option<u64>,
+    blob_sidecar: Option<u64>,
+    data_column_sidecar: Option<u64>,
+}
+
+impl EventFilter {
+    /// Build the filter from the full config, failing on malformed peer ids
+    pub fn from_config(config: &FullConfig, slots_per_epoch: u64) -> Result<Self, XatuError> {
+        let mut filter = Self::default();
+
+        if let Some(peer_filter) = &config.settings.peer_filter {
+            filter.allowed_peers = parse_peer_ids(&peer_filter.allow)?;
+            filter.denied_peers = parse_peer_ids(&peer_filter.deny)?;
+        }
+
+        if let Some(range) = &config.settings.slot_range {
+            // Epoch bounds are converted to the first/last slot of the epoch
+            let start_from_epoch = range.start_epoch.map(|e| e.saturating_mul(slots_per_epoch));
+            let end_from_epoch = range.end_epoch.map(|e| {
+                e.saturating_add(1)
+                    .saturating_mul(slots_per_epoch)
+                    .saturating_sub(1)
+            });
+
+            filter.start_slot = max_bound(range.start_slot, start_from_epoch);
+            filter.end_slot = min_bound(range.end_slot, end_from_epoch);
+
+            if let (Some(start), Some(end)) = (filter.start_slot, filter.end_slot) {
+                if start > end {
+                    return Err(XatuError::InvalidConfig(format!(
+                        "slotRange start slot {} is after end slot {}",
+                        start, end
+                    )));
+                }
+            }
+        }
+
+        if let Some(sampling) = &config.settings.sampling {
+            filter.sample_thresholds = SampleThresholds {
+                block: sample_threshold("block", sampling.block)?,
+                attestation: sample_threshold("attestation", sampling.attestation)?,
+                aggregate: sample_threshold("aggregate", sampling.aggregate)?,
+                blob_sidecar: sample_threshold("blobSidecar", sampling.blob_sidecar)?,
+                data_column_sidecar: sample_threshold(
+                    "dataColumnSidecar",
+                    sampling.data_column_sidecar,
+                )?,
+            };
+        }
+
+        Ok(filter)
+    }
+
+    /// Check if events from this peer should be exported
+    pub fn allows_peer(&self, peer_id: &PeerId) -> bool {
+        if self.denied_peers.contains(peer_id) {
+            return false;
+        }
+        self.allowed_peers.is_empty() || self.allowed_peers.contains(peer_id)
+    }
+
+    /// Check if events for this slot fall within the configured range
+    pub fn allows_slot(&self, slot: u64) -> bool {
+        !self.start_slot.is_some_and(|start| slot < start)
+            && !self.end_slot.is_some_and(|end| slot > end)
+    }
+
+    /// Check if the message falls within the sample for its event type
+    /// Message ids are already hashes, so their leading bytes are uniformly distributed
+    pub fn is_sampled(&self, kind: EventKind, message_id: &MessageId) -> bool {
+        match self.sample_threshold(kind) {
+            Some(threshold) => message_id_hash(message_id) < threshold,
+            None => true,
+        }
+    }
+
+    fn sample_threshold(&self, kind: EventKind) -> Option<u64> {
+        match kind {
+            EventKind::Block => self.sample_thresholds.block,
+            EventKind::Attestation => self.sample_thresholds.attestation,
+            EventKind::Aggregate => self.sample_thresholds.aggregate,
+            EventKind::BlobSidecar => self.sample_thresholds.blob_sidecar,
+            EventKind::DataColumnSidecar => self.sample_thresholds.data_column_sidecar,
+            EventKind::SessionStart
+            | EventKind::NodeInfo
+            | EventKind::ExporterStats
+            | EventKind::NetworkMismatch
+            | EventKind::Custody
+            | EventKind::PeerCustody
+            | EventKind::SubnetCoverage
+            | EventKind::Bandwidth
+            | EventKind::PropagationLatency
+            | EventKind::SlotAggregate
+            | EventKind::AttestationGroup
+            | EventKind::ClockSkew
+            | EventKind::OutputFailover
+            | EventKind::Genesis
+            | EventKind::NetworkChanged => None,
+        }
+    }
+
+    /// Gossip kinds that aren't sampled away entirely
+    pub fn enabled_kinds(&self) -> Vec<EventKind> {
+        EventKind::GOSSIP
+            .into_iter()
+            .filter(|kind| self.sample_threshold(*kind) != Some(0))
+            .collect()
+    }
+
+    /// Check the slot, peer and sampling filters
+    pub fn allows(
+        &self,
+        kind: EventKind,
+        message_id: &MessageId,
+        peer_id: &PeerId,
+        slot: u64,
+    ) -> bool {
+        self.allows_slot(slot) && self.allows_peer(peer_id) && self.is_sampled(kind, message_id)
+    }
+}
+
+fn sample_threshold(name: &str, rate: Option<f64>) -> Result<Option<u64>, XatuError> {
+    match rate {
+        None => Ok(None),
+        Some(rate) if !(0.0..=1.0).contains(&rate) => Err(XatuError::InvalidConfig(format!(
+            "sampling rate for {} is {} (must be between 0.0 and 1.0)",
+            name, rate
+        ))),
+        Some(rate) if rate >= 1.0 => Ok(None),
+        Some(rate) => Ok(Some((rate * u64::MAX as f64) as u64)),
+    }
+}
+
+fn message_id_hash(message_id: &MessageId) -> u64 {
+    let mut bytes = [0u8; 8];
+    let len = message_id.0.len().min(8);
+    bytes[..len].copy_from_slice(&message_id.0[..len]);
+    u64::from_be_bytes(bytes)
+}
+
+fn max_bound(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn min_bound(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn parse_peer_ids(peer_ids: &[String]) -> Result<HashSet<PeerId>, XatuError> {
+    peer_ids
+        .iter()
+        .map(|peer_id| {
+            PeerId::from_str(peer_id).map_err(|e| {
+                XatuError::InvalidConfig(format!("peerFilter peer id '{}': {}", peer_id, e))
+            })
+        })
+        .collect()
+}
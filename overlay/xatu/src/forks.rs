@@ -0,0 +1,166 @@
+//! Fork name and digest resolution for event slots
+//!
+//! The schedule is derived from the `ChainSpec` at init, so every event can carry the fork
+//! its slot falls in. Digests also need the genesis validators root, which lighthouse
+//! passes to init once the beacon chain is built.
+//!
+//! The spec is kept too, so parameters that change at fork boundaries are resolved for each
+//! event's epoch rather than frozen at init, which matters for nodes running across forks.
+
+use std::sync::{Arc, RwLock};
+use types::consts::altair::SYNC_COMMITTEE_SUBNET_COUNT;
+use types::{ChainSpec, Epoch, ForkName, Hash256};
+
+/// A scheduled fork
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledFork {
+    pub epoch: u64,
+    /// Lowercase fork name, e.g. `fulu`
+    pub name: String,
+    /// Hex-encoded fork digest, `None` until the genesis validators root is known
+    pub digest: Option<String>,
+}
+
+/// Parameters in effect at an epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochParams {
+    /// Lowercase fork name, e.g. `fulu`
+    pub fork_name: String,
+    /// Hex-encoded digest gossip topics are scoped to, which also changes with blob parameter
+    /// only forks. `None` until the genesis validators root is known
+    pub fork_digest: Option<String>,
+    /// Blob limit per block, `None` before Deneb
+    pub max_blobs_per_block: Option<u64>,
+    pub max_committees_per_slot: u64,
+}
+
+/// Gossip subnet counts of the registered spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubnetCounts {
+    pub attestation: u64,
+    pub sync_committee: u64,
+    pub data_column: u64,
+}
+
+type SpecWithRoot = (Arc<ChainSpec>, Option<Hash256>);
+
+/// The fork schedule one set of exporters resolves event forks from
+#[derive(Debug, Default)]
+pub struct ForkSchedule {
+    schedule: RwLock<Vec<ScheduledFork>>,
+    spec: RwLock<Option<SpecWithRoot>>,
+}
+
+impl ForkSchedule {
+    /// Set the fork schedule from the chain spec, replacing any previous one
+    pub fn set_fork_schedule(&self, spec: &ChainSpec, genesis_validators_root: Option<Hash256>) {
+        let mut schedule: Vec<ScheduledFork> = ForkName::list_all()
+            .into_iter()
+            .filter_map(|fork| {
+                let epoch = spec.fork_epoch(fork)?;
+                Some(ScheduledFork {
+                    epoch: epoch.as_u64(),
+                    name: fork.to_string(),
+                    digest: genesis_validators_root.map(|root| {
+                        format!("0x{}", hex::encode(spec.compute_fork_digest(root, epoch)))
+                    }),
+                })
+            })
+            .collect();
+        // Stable, so forks sharing an epoch keep their order and the latest one wins
+        schedule.sort_by_key(|fork| fork.epoch);
+
+        *self.schedule.write().unwrap_or_else(|e| e.into_inner()) = schedule;
+        *self.spec.write().unwrap_or_else(|e| e.into_inner()) =
+            Some((Arc::new(spec.clone()), genesis_validators_root));
+    }
+
+    /// Parameters in effect at the epoch, if a schedule is set
+    pub fn params_at_epoch(&self, epoch: u64) -> Option<EpochParams> {
+        let (spec, genesis_validators_root) = self
+            .spec
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()?;
+        let epoch = Epoch::new(epoch);
+        let fork = spec.fork_name_at_epoch(epoch);
+        Some(EpochParams {
+            fork_name: fork.to_string(),
+            fork_digest: genesis_validators_root
+                .map(|root| format!("0x{}", hex::encode(spec.compute_fork_digest(root, epoch)))),
+            max_blobs_per_block: fork
+                .deneb_enabled()
+                .then(|| spec.max_blobs_per_block(epoch)),
+            max_committees_per_slot: spec.max_committees_per_slot as u64,
+        })
+    }
+
+    /// Subnet counts of the registered spec, if one is set
+    pub fn subnet_counts(&self) -> Option<SubnetCounts> {
+        let spec = self
+            .spec
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()?
+            .0;
+        Some(SubnetCounts {
+            attestation: spec.attestation_subnet_count,
+            sync_committee: SYNC_COMMITTEE_SUBNET_COUNT,
+            data_column: spec.data_column_sidecar_subnet_count,
+        })
+    }
+
+    /// The fork active at the epoch, if a schedule is set
+    pub fn at_epoch(&self, epoch: u64) -> Option<ScheduledFork> {
+        self.schedule
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .rev()
+            .find(|fork| fork.epoch <= epoch)
+            .cloned()
+    }
+
+    /// Fork name and digest for the epoch, as exported on events
+    pub(crate) fn name_and_digest(&self, epoch: u64) -> (Option<String>, Option<String>) {
+        match self.params_at_epoch(epoch) {
+            Some(params) => (Some(params.fork_name), params.fork_digest),
+            None => (None, None),
+        }
+    }
+
+    /// The scheduled fork with the hex-encoded digest, once digests are known
+    pub fn by_digest(&self, digest: &str) -> Option<ScheduledFork> {
+        self.schedule
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|fork| {
+                fork.digest
+                    .as_deref()
+                    .is_some_and(|known| known.eq_ignore_ascii_case(digest))
+            })
+            .cloned()
+    }
+
+    /// Fork name and digest of the topic, as exported on gossip events
+    ///
+    /// Around a fork boundary the node is subscribed under both digests, so a message's topic
+    /// can belong to a different fork than its slot.
+    pub(crate) fn topic_name_and_digest(&self, topic: &str) -> (Option<String>, Option<String>) {
+        let Some(digest) = topic_digest(topic) else {
+            return (None, None);
+        };
+        (self.by_digest(&digest).map(|fork| fork.name), Some(digest))
+    }
+}
+
+/// Hex-encoded digest a gossip topic is scoped to, e.g. `0x6a95a1a9` for
+/// `/eth2/6a95a1a9/beacon_block/ssz_snappy`
+pub fn topic_digest(topic: &str) -> Option<String> {
+    let digest = topic.strip_prefix("/eth2/")?.split('/').next()?;
+    if digest.len() != 8 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("0x{}", digest.to_ascii_lowercase()))
+}
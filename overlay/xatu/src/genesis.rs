@@ -0,0 +1,82 @@
+//! Holding gossip events back until genesis
+//!
+//! A devnet node is often started ahead of genesis, and gossip received meanwhile would be
+//! exported with slot and propagation fields relative to a chain that hasn't started. While
+//! the wallclock is before genesis such events are buffered, up to `bufferEvents`, or dropped
+//! with `mode: drop`. Once genesis passes a `GENESIS` marker event goes out with the counts,
+//! followed by the buffered events. The exporter's own events are never held back.
+
+use crate::config::PreGenesisConfig;
+use crate::events::{GenesisEvent, SlotTiming};
+use crate::schema::EventData;
+
+/// What happened to an event received before genesis
+pub(crate) enum Hold {
+    /// Not held back, export it as usual
+    Pass(EventData),
+    Buffered,
+    Dropped,
+}
+
+#[derive(Debug)]
+pub(crate) struct PreGenesis {
+    buffer_events: usize,
+    buffered: Vec<EventData>,
+    dropped: u64,
+}
+
+impl PreGenesis {
+    /// Start holding events back, `None` if genesis has already passed or isn't known
+    pub(crate) fn start(config: &PreGenesisConfig, timing: Option<SlotTiming>) -> Option<Self> {
+        genesis_ms(timing).filter(|genesis_ms| now_ms() < *genesis_ms)?;
+        Some(Self {
+            buffer_events: config.buffer_events(),
+            buffered: Vec::new(),
+            dropped: 0,
+        })
+    }
+
+    /// Hold back an event built from a gossip message
+    pub(crate) fn hold(&mut self, event: EventData, timing: Option<SlotTiming>) -> Hold {
+        if event.topic().is_none() || self.is_over(timing) {
+            return Hold::Pass(event);
+        }
+        if self.buffered.len() < self.buffer_events {
+            self.buffered.push(event);
+            Hold::Buffered
+        } else {
+            self.dropped += 1;
+            Hold::Dropped
+        }
+    }
+
+    /// Events currently held back
+    pub(crate) fn buffered_len(&self) -> usize {
+        self.buffered.len()
+    }
+
+    /// Whether genesis has passed, reevaluated as the genesis time may be corrected
+    pub(crate) fn is_over(&self, timing: Option<SlotTiming>) -> bool {
+        genesis_ms(timing).is_some_and(|genesis_ms| now_ms() >= genesis_ms)
+    }
+
+    /// The `GENESIS` marker and the events held back, in the order they go out
+    pub(crate) fn finish(self, timing: Option<SlotTiming>) -> (GenesisEvent, Vec<EventData>) {
+        let genesis_time = timing.map_or(0, |timing| timing.genesis_time);
+        let marker = GenesisEvent::new(
+            genesis_time,
+            self.buffered.len() as u64,
+            self.dropped,
+            crate::clock::offset_ms(),
+        );
+        (marker, self.buffered)
+    }
+}
+
+fn genesis_ms(timing: Option<SlotTiming>) -> Option<i64> {
+    timing.map(|timing| timing.slot_start_ms(0))
+}
+
+fn now_ms() -> i64 {
+    crate::clock::corrected_now_ms()
+}
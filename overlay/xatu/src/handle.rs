@@ -0,0 +1,341 @@
+//! Explicit control over the sidecar exporter
+//!
+//! Lighthouse's shutdown sequence and admin tooling use the handle to flush queued events
+//! and close the sidecar deterministically instead of relying on `Drop`.
+//!
+//! Shutdown has two phases: `drain` stops accepting new events and flushes the queue, then
+//! `close` closes the sidecar and stops the batch thread. `shutdown` runs both, and
+//! `shutdown_async` runs them off the calling task so a shutdown coordinator can await it.
+//!
+//! The exporter moves through the `ExporterPhase`s in order, apart from going back and forth
+//! between `Running` and `Degraded` as sends fail and recover, and back to `Initializing`
+//! while the sidecar restarts for another network. `Initializing`, `Running` and `Degraded`
+//! accept events, those arriving during a lazy init are buffered until the sidecar is up,
+//! and the phase is what `is_healthy` reports on.
+
+use crate::config::NetworkInfo;
+use crate::error::XatuError;
+use crate::events::EventData;
+use crate::queue_budget::{QueueBudget, QueuedEvent};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Requests sent from a handle to the batch thread, each acknowledged with the send result
+pub(crate) enum Control {
+    /// Send everything queued so far
+    Flush(Sender<Result<(), XatuError>>),
+    /// Close the sidecar and stop the batch thread, dropping anything still queued
+    Close(Sender<Result<(), XatuError>>),
+    /// Send everything queued so far, then close the sidecar and start it again for another
+    /// network, sending the queued `NETWORK_CHANGED` event through the new one
+    Restart(NetworkInfo, Box<QueuedEvent>),
+}
+
+/// Where the exporter is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExporterPhase {
+    /// Created, the batch thread has not started yet
+    Uninitialized = 0,
+    /// The batch thread is starting the sidecar, or starting it again for another network,
+    /// events are buffered meanwhile
+    Initializing = 1,
+    /// Accepting events and the last send succeeded
+    Running = 2,
+    /// Accepting events, but the last send failed
+    Degraded = 3,
+    /// Shutdown started, new events are dropped while the queue drains
+    ShuttingDown = 4,
+    /// The sidecar is closed, or failed to start, and the batch thread stopped
+    Closed = 5,
+}
+
+impl ExporterPhase {
+    const ALL: [ExporterPhase; 6] = [
+        ExporterPhase::Uninitialized,
+        ExporterPhase::Initializing,
+        ExporterPhase::Running,
+        ExporterPhase::Degraded,
+        ExporterPhase::ShuttingDown,
+        ExporterPhase::Closed,
+    ];
+
+    /// Whether new events are queued
+    pub fn accepts_events(self) -> bool {
+        matches!(
+            self,
+            ExporterPhase::Initializing | ExporterPhase::Running | ExporterPhase::Degraded
+        )
+    }
+
+    /// Whether the batch thread started and has not stopped yet
+    pub fn is_active(self) -> bool {
+        !matches!(self, ExporterPhase::Uninitialized | ExporterPhase::Closed)
+    }
+
+    /// Whether the sidecar is initialized and not closed yet
+    pub fn is_open(self) -> bool {
+        matches!(
+            self,
+            ExporterPhase::Running | ExporterPhase::Degraded | ExporterPhase::ShuttingDown
+        )
+    }
+
+    /// Whether the exporter may move from this phase to `next`
+    fn leads_to(self, next: ExporterPhase) -> bool {
+        use ExporterPhase::*;
+        matches!(
+            (self, next),
+            (Uninitialized | Running | Degraded, Initializing)
+                | (Initializing, Running)
+                | (Running, Degraded)
+                | (Degraded, Running)
+                | (Running | Degraded, ShuttingDown)
+                | (Initializing | Running | Degraded | ShuttingDown, Closed)
+        )
+    }
+}
+
+impl fmt::Display for ExporterPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExporterPhase::Uninitialized => "uninitialized",
+            ExporterPhase::Initializing => "initializing",
+            ExporterPhase::Running => "running",
+            ExporterPhase::Degraded => "degraded",
+            ExporterPhase::ShuttingDown => "shutting down",
+            ExporterPhase::Closed => "closed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The exporter's current phase, moved along by the observer, batch thread and handles
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle(AtomicU8);
+
+impl Lifecycle {
+    pub(crate) fn get(&self) -> ExporterPhase {
+        ExporterPhase::ALL[self.0.load(Ordering::Acquire) as usize]
+    }
+
+    /// Move to `next` if the current phase leads to it, returns whether it did
+    pub(crate) fn advance(&self, next: ExporterPhase) -> bool {
+        let moved = self
+            .0
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                ExporterPhase::ALL[current as usize]
+                    .leads_to(next)
+                    .then_some(next as u8)
+            })
+            .is_ok();
+        if moved {
+            crate::metrics::set_exporter_phase(next);
+        }
+        moved
+    }
+}
+
+/// Counters shared between the observer, the batch thread and every handle
+#[derive(Debug, Default)]
+pub(crate) struct ExporterState {
+    pub(crate) events_queued: AtomicU64,
+    pub(crate) events_dropped: AtomicU64,
+    pub(crate) events_sent: AtomicU64,
+    pub(crate) batches_sent: AtomicU64,
+    pub(crate) send_errors: AtomicU64,
+    pub(crate) batches_in_flight: AtomicU64,
+    pub(crate) batches_retried: AtomicU64,
+    pub(crate) phase: Lifecycle,
+    /// Most recent send error, reported in the pipeline summary
+    pub(crate) last_error: Mutex<Option<String>>,
+    /// Bytes held by queued events, `None` without `maxQueueBytes`
+    pub(crate) queue_budget: Option<QueueBudget>,
+}
+
+impl ExporterState {
+    /// Snapshot of the counters, with the current queue length
+    pub(crate) fn snapshot(&self, queue_len: usize) -> XatuStats {
+        XatuStats {
+            events_queued: self.events_queued.load(Ordering::Relaxed),
+            events_dropped: self.events_dropped.load(Ordering::Relaxed),
+            events_sent: self.events_sent.load(Ordering::Relaxed),
+            batches_sent: self.batches_sent.load(Ordering::Relaxed),
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+            batches_in_flight: self.batches_in_flight.load(Ordering::Relaxed),
+            batches_retried: self.batches_retried.load(Ordering::Relaxed),
+            queue_len,
+            queue_bytes: self
+                .queue_budget
+                .as_ref()
+                .map_or(0, |budget| budget.queued_bytes()),
+        }
+    }
+}
+
+/// Snapshot of the exporter counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct XatuStats {
+    /// Events accepted onto the queue
+    pub events_queued: u64,
+    /// Events that could not be queued, or whose batch failed every attempt
+    pub events_dropped: u64,
+    /// Events the sidecar confirmed as delivered
+    pub events_sent: u64,
+    /// Batches the sidecar confirmed as delivered
+    pub batches_sent: u64,
+    /// Batch attempts that failed
+    pub send_errors: u64,
+    /// Batches submitted and waiting for their outcome, or for a retry
+    pub batches_in_flight: u64,
+    /// Failed batches that were submitted again
+    pub batches_retried: u64,
+    /// Events currently waiting on the queue
+    pub queue_len: usize,
+    /// Approximate bytes of the events waiting on the queue, 0 without `maxQueueBytes`
+    pub queue_bytes: u64,
+}
+
+/// Handle to the sidecar exporter, cheap to clone
+#[derive(Clone)]
+pub struct XatuHandle {
+    state: Arc<ExporterState>,
+    control: Sender<Control>,
+    queue: Receiver<QueuedEvent>,
+}
+
+impl XatuHandle {
+    pub(crate) fn new(
+        state: Arc<ExporterState>,
+        control: Sender<Control>,
+        queue: Receiver<QueuedEvent>,
+    ) -> Self {
+        Self {
+            state,
+            control,
+            queue,
+        }
+    }
+
+    pub(crate) fn state(&self) -> &ExporterState {
+        &self.state
+    }
+
+    /// Send every queued event now, waiting up to `timeout` for the sidecar to deliver them
+    pub fn flush(&self, timeout: Duration) -> Result<(), XatuError> {
+        self.request(Control::Flush, timeout)
+    }
+
+    /// Restart the sidecar for another network once everything queued so far is sent, then
+    /// send `changed` through it
+    /// Returns as soon as the batch thread has the request, the restart runs there
+    pub(crate) fn restart(
+        &self,
+        network_info: NetworkInfo,
+        changed: EventData,
+    ) -> Result<(), XatuError> {
+        self.control
+            .send(Control::Restart(network_info, Box::new((changed, 0))))
+            .map_err(|_| XatuError::QueueDisconnected)?;
+        self.state.events_queued.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Stop accepting new events, they are dropped from now on
+    pub fn stop_intake(&self) {
+        self.state.phase.advance(ExporterPhase::ShuttingDown);
+    }
+
+    /// First shutdown phase: stop accepting new events and send everything queued so far,
+    /// waiting up to `timeout` for the sidecar to deliver it
+    pub fn drain(&self, timeout: Duration) -> Result<(), XatuError> {
+        self.stop_intake();
+        self.flush(timeout)
+    }
+
+    /// Second shutdown phase: close the sidecar and stop the batch thread
+    /// Events not yet sent are dropped, so `drain` first. No-op once the thread has stopped
+    pub fn close(&self, timeout: Duration) -> Result<(), XatuError> {
+        if !self.phase().is_active() {
+            return Ok(());
+        }
+        self.request(Control::Close, timeout)
+    }
+
+    /// Drain then close, each phase waiting up to `timeout`
+    /// The sidecar is closed even if draining fails, the drain error is returned
+    /// Calling this again after a successful shutdown is a no-op
+    pub fn shutdown(&self, timeout: Duration) -> Result<(), XatuError> {
+        if !self.phase().is_active() {
+            return Ok(());
+        }
+        let drained = self.drain(timeout);
+        let closed = self.close(timeout);
+        drained.and(closed)
+    }
+
+    /// `shutdown` on a separate thread, resolving once both phases are done
+    /// Shutdown starts when this is called, not when the future is first polled, so the
+    /// caller's async runtime is never blocked by it
+    pub fn shutdown_async(
+        &self,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(), XatuError>> + Send + 'static {
+        let handle = self.clone();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let spawned = std::thread::Builder::new()
+            .name("xatu-shutdown".to_string())
+            .spawn(move || {
+                let _ = sender.send(handle.shutdown(timeout));
+            });
+        async move {
+            spawned.map_err(|e| XatuError::Thread(format!("Failed to spawn shutdown: {}", e)))?;
+            receiver.await.unwrap_or_else(|_| {
+                Err(XatuError::Thread(
+                    "shutdown thread exited before responding".to_string(),
+                ))
+            })
+        }
+    }
+
+    /// Current exporter counters
+    pub fn stats(&self) -> XatuStats {
+        self.state.snapshot(self.queue.len())
+    }
+
+    /// Current lifecycle phase of the exporter
+    pub fn phase(&self) -> ExporterPhase {
+        self.state.phase.get()
+    }
+
+    /// Check the exporter is running and the last send succeeded
+    pub fn is_healthy(&self) -> bool {
+        self.phase() == ExporterPhase::Running
+    }
+
+    fn request(
+        &self,
+        control: impl FnOnce(Sender<Result<(), XatuError>>) -> Control,
+        timeout: Duration,
+    ) -> Result<(), XatuError> {
+        let (ack_sender, ack_receiver) = bounded(1);
+        self.control
+            .send(control(ack_sender))
+            .map_err(|_| XatuError::QueueDisconnected)?;
+
+        match ack_receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(XatuError::Timeout(format!(
+                "batch thread did not respond within {:?}",
+                timeout
+            ))),
+            Err(RecvTimeoutError::Disconnected) => Err(XatuError::Thread(
+                "batch thread exited before responding".to_string(),
+            )),
+        }
+    }
+}
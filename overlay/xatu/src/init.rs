@@ -2,47 +2,65 @@
 //! This module consolidates all the initialization logic to minimize upstream code
 
 use crate::chain::XatuChain as XatuChainNew;
-use crate::config::NetworkInfo;
-use crate::shim::create_exporter_with_network_info;
-use crate::{XatuChain, XatuConfig};
-use std::sync::Arc;
-use tracing::{error, info};
-use types::{ChainSpec, EthSpec};
+use crate::config::{EnableMode, NetworkInfo};
+use crate::context::XatuContext;
+use crate::error::XatuError;
+use crate::registry::ExporterRegistry;
+use crate::{ObserverResult, XatuChain, XatuConfig};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+use types::{ChainSpec, EthSpec, Hash256};
 
-/// Initialize xatu observer chain with minimal configuration
-/// This handles all environment variable checking, config loading, and error handling
-pub fn init<E: EthSpec>() -> Option<Arc<XatuChain<E>>> {
-    info!("XATU FEATURE IS ENABLED - Initializing observer");
+// The chain built by the last init, kept for a network service built again in this process
+static RUNNING: Mutex<Option<Arc<dyn Any + Send + Sync>>> = Mutex::new(None);
 
-    // Check for XATU_CONFIG environment variable
-    let config = if let Ok(config_path) = std::env::var("XATU_CONFIG") {
-        info!("XATU_CONFIG env var found: {}", config_path);
+/// Load the config from `XATU_CONFIG` and resolve whether xatu should run
+/// Returns `None` if xatu is disabled
+fn load_config() -> Option<XatuConfig> {
+    let config_path = std::env::var("XATU_CONFIG").ok();
+    let file_config = config_path.as_ref().and_then(|config_path| {
         info!("Loading Xatu config from: {}", config_path);
-        match XatuConfig::from_file(&config_path) {
-            Ok(cfg) => cfg,
+        match XatuConfig::from_file(config_path) {
+            Ok(cfg) => Some(cfg),
             Err(e) => {
-                error!(
-                    "Failed to load Xatu config: {}. Using default enabled config.",
-                    e
-                );
-                XatuConfig::enabled()
+                error!("Failed to load Xatu config: {}", e);
+                None
             }
         }
-    } else {
-        // No config specified, check if we should still enable with defaults
-        if std::env::var("DISABLE_XATU").is_ok() {
-            info!("DISABLE_XATU set, xatu observer disabled");
-            return None;
+    });
+
+    // What happens without a usable config is only known once DISABLE_XATU is checked
+    let mode = XatuConfig::resolve_enabled(file_config.as_ref());
+    match mode {
+        EnableMode::Config(false) => info!("Xatu is disabled in config"),
+        EnableMode::EnvDisabled => info!("DISABLE_XATU set, xatu observer disabled"),
+        EnableMode::Default if config_path.is_some() => {
+            info!("Xatu config could not be loaded, using default enabled config")
         }
-        info!("No Xatu config specified, using default enabled config");
-        XatuConfig::enabled()
-    };
+        EnableMode::Default => info!("No Xatu config specified, using default enabled config"),
+        EnableMode::Config(true) => {}
+    }
 
-    if !config.is_enabled() {
-        info!("Xatu is disabled in config");
+    if !mode.is_enabled() {
         return None;
     }
 
+    let mut config = file_config.unwrap_or_else(XatuConfig::enabled);
+    if let Err(e) = config.apply_env_overrides() {
+        error!("Failed to apply XATU_* env overrides: {}", e);
+    }
+
+    Some(config)
+}
+
+/// Initialize xatu observer chain with minimal configuration
+/// This handles all environment variable checking, config loading, and error handling
+pub fn init<E: EthSpec>() -> Option<Arc<XatuChain<E>>> {
+    info!("XATU FEATURE IS ENABLED - Initializing observer");
+
+    let config = load_config()?;
+
     let exporter = crate::shim::create_exporter_from_config::<E>(&config)?;
     Some(Arc::new(XatuChainNew::with_exporter(exporter)))
 }
@@ -50,80 +68,142 @@ pub fn init<E: EthSpec>() -> Option<Arc<XatuChain<E>>> {
 /// Initialize xatu with chain spec
 pub fn init_with_chain_spec<E: EthSpec>(
     spec: &ChainSpec,
-) -> Result<Option<Arc<XatuChain<E>>>, String> {
-    init_with_chain_spec_and_genesis::<E>(spec, spec.min_genesis_time)
+) -> Result<Option<Arc<XatuChain<E>>>, XatuError> {
+    init_with_chain_spec_and_genesis::<E>(spec, spec.min_genesis_time, None)
 }
 
-/// Initialize xatu with chain spec and explicit genesis time
+/// Initialize xatu with chain spec, explicit genesis time and the genesis validators root,
+/// if the beacon chain already knows it
 pub fn init_with_chain_spec_and_genesis<E: EthSpec>(
     spec: &ChainSpec,
     genesis_time: u64,
-) -> Result<Option<Arc<XatuChain<E>>>, String> {
+    genesis_validators_root: Option<Hash256>,
+) -> Result<Option<Arc<XatuChain<E>>>, XatuError> {
+    init_with_registry(
+        spec,
+        genesis_time,
+        genesis_validators_root,
+        &ExporterRegistry::with_builtins(),
+    )
+}
+
+/// Initialize xatu with chain spec, explicit genesis time and a custom exporter registry
+/// The returned chain owns the context its exporters share, nothing is kept process-wide
+/// apart from the chain itself, for `running`
+pub fn init_with_registry<E: EthSpec>(
+    spec: &ChainSpec,
+    genesis_time: u64,
+    genesis_validators_root: Option<Hash256>,
+    registry: &ExporterRegistry<E>,
+) -> Result<Option<Arc<XatuChain<E>>>, XatuError> {
     info!("XATU FEATURE IS ENABLED - Initializing observer with chain spec");
 
-    // Get config from environment or use defaults
-    let config = if let Ok(config_path) = std::env::var("XATU_CONFIG") {
-        info!("XATU_CONFIG env var found: {}", config_path);
-        match XatuConfig::from_file(&config_path) {
-            Ok(cfg) => cfg,
-            Err(e) => {
-                error!(
-                    "Failed to load Xatu config: {}. Using default enabled config.",
-                    e
-                );
-                XatuConfig::enabled()
-            }
-        }
-    } else {
-        if std::env::var("DISABLE_XATU").is_ok() {
-            info!("DISABLE_XATU set, xatu observer disabled");
-            return Ok(None);
-        }
-        info!("No Xatu config specified, using default enabled config");
-        XatuConfig::enabled()
+    let Some(config) = load_config() else {
+        return Ok(None);
     };
 
-    if !config.is_enabled() {
-        info!("Xatu is disabled in config");
-        return Ok(None);
+    // The root tells which network the node exports before the configured name is trusted
+    let context = XatuContext::new(genesis_validators_root);
+    // Fork names are known from the spec alone, digests only with the genesis validators root
+    context
+        .forks
+        .set_fork_schedule(spec, genesis_validators_root);
+
+    let network_info = network_info::<E>(&config, &context, spec, genesis_time);
+    context.networks.set_network_id(network_info.network_id);
+
+    info!(
+        "Creating Xatu with network: {}, genesis_time: {} (actual), session: {}",
+        network_info.network_name,
+        network_info.genesis_time,
+        crate::session::id()
+    );
+
+    // Create the configured exporter with network info
+    let exporter = registry.create(&config, network_info.clone(), context.clone())?;
+    let chain = XatuChainNew::with_exporter(exporter)
+        .with_context(context)
+        .with_config(config);
+    if let ObserverResult::Error(e) = chain.on_start(&network_info) {
+        warn!("Xatu exporter failed to start cleanly: {}", e);
     }
+    let chain = Arc::new(chain);
+    *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) = Some(chain.clone());
+    Ok(Some(chain))
+}
 
-    // Determine network name - use override if provided, otherwise use chain spec
-    let network_name = if let Some(ref ethereum_config) = config.ethereum {
-        if let Some(ref override_name) = ethereum_config.override_network_name {
-            info!("Using override network name from config: {}", override_name);
-            override_name.clone()
-        } else {
-            spec.config_name
-                .clone()
-                .unwrap_or_else(|| "unknown".to_string())
-        }
-    } else {
-        spec.config_name
-            .clone()
-            .unwrap_or_else(|| "unknown".to_string())
-    };
+/// The chain an earlier init in this process built, for lighthouse code that isn't handed
+/// it, and to reuse when the network service is built again instead of starting a second set
+/// of exporters
+pub fn running<E: EthSpec>() -> Option<Arc<XatuChain<E>>> {
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()?
+        .downcast::<XatuChain<E>>()
+        .ok()
+}
 
-    // Create network info from chain spec with explicit genesis time
-    let network_info = NetworkInfo {
-        genesis_time: genesis_time,
-        network_name: network_name.clone(),
-        network_id: spec.deposit_network_id,
-        slots_per_epoch: E::slots_per_epoch(),
-        seconds_per_slot: spec.seconds_per_slot,
+/// Tell the exporters which network the node follows after its beacon chain was rebuilt,
+/// e.g. for a devnet torn down and relaunched under the same node
+/// If the genesis validators root or network id differs from the one they were started for,
+/// they restart for the new network and emit a `NETWORK_CHANGED` event
+pub fn refresh_network<E: EthSpec>(
+    chain: &XatuChain<E>,
+    spec: &ChainSpec,
+    genesis_time: u64,
+    genesis_validators_root: Hash256,
+) -> ObserverResult {
+    let context = chain.context();
+    let same_root = context.networks.genesis_validators_root() == Some(genesis_validators_root);
+    let same_id = context.networks.network_id() == Some(spec.deposit_network_id);
+    if same_root && same_id {
+        return ObserverResult::Ok;
+    }
+    // The config loaded at start, not whatever the file holds by now
+    let Some(config) = chain.config() else {
+        return ObserverResult::Ok;
     };
 
-    info!(
-        "Creating Xatu with network: {}, genesis_time: {} (actual)",
-        network_info.network_name, network_info.genesis_time
+    context
+        .networks
+        .set_genesis_validators_root(genesis_validators_root);
+    context
+        .forks
+        .set_fork_schedule(spec, Some(genesis_validators_root));
+    let network_info = network_info::<E>(config, context, spec, genesis_time);
+    context.networks.set_network_id(network_info.network_id);
+
+    warn!(
+        "Xatu detected a network change to {} (id {}), restarting the exporters",
+        network_info.network_name, network_info.network_id
     );
+    chain.on_network_changed(&network_info)
+}
 
-    // Create exporter with network info
-    match create_exporter_with_network_info(&config, network_info) {
-        Some(exporter) => Ok(Some(Arc::new(XatuChainNew::with_exporter(exporter)))),
-        None => {
-            // This should only happen if network info is missing or invalid
-            Err("Failed to create Xatu exporter - network info may be invalid".to_string())
-        }
+/// Network info from the chain spec with an explicit genesis time
+fn network_info<E: EthSpec>(
+    config: &XatuConfig,
+    context: &XatuContext,
+    spec: &ChainSpec,
+    genesis_time: u64,
+) -> NetworkInfo {
+    // Determine network name - the override, else the network the genesis validators root
+    // belongs to, else the chain spec's name
+    let override_name = config
+        .settings
+        .ethereum
+        .as_ref()
+        .and_then(|ethereum_config| ethereum_config.override_network_name.as_deref());
+    let network_name = context
+        .networks
+        .resolve_name(override_name, spec.config_name.as_deref());
+
+    NetworkInfo {
+        genesis_time,
+        network_name,
+        network_id: spec.deposit_network_id,
+        slots_per_epoch: E::slots_per_epoch(),
+        seconds_per_slot: spec.seconds_per_slot,
     }
 }
@@ -0,0 +1,160 @@
+//! Propagation latency percentiles per epoch
+//!
+//! Every gossip event that passes the peer and slot filters is recorded here before
+//! sampling, so the summaries stay complete even when raw events are sampled away. An
+//! epoch is summarized once events from two epochs later arrive, which leaves time for late
+//! messages, and the batch thread exports the summaries as `PROPAGATION_LATENCY` events.
+
+use crate::events::{LatencySummary, SlotTiming};
+use crate::filter::EventKind;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Width of a histogram bucket, which bounds the error of the reported percentiles
+const BUCKET_MS: i64 = 10;
+
+/// Range covered by the buckets, values outside it land in the edge buckets
+/// Minimum and maximum are still tracked exactly
+const MIN_MS: i64 = -12_000;
+const MAX_MS: i64 = 60_000;
+
+const BUCKETS: usize = ((MAX_MS - MIN_MS) / BUCKET_MS) as usize;
+
+/// Histograms of one exporter's epochs, open and completed
+#[derive(Default)]
+pub(crate) struct LatencyTracker {
+    open: Mutex<OpenEpochs>,
+    completed: Mutex<Vec<EpochLatency>>,
+}
+
+/// Histograms of the epochs still receiving events, by event kind
+#[derive(Default)]
+struct OpenEpochs {
+    histograms: BTreeMap<u64, Vec<(EventKind, Histogram)>>,
+    /// Epochs before this one were summarized, late events for them are dropped
+    closed_before: u64,
+}
+
+/// Latency summaries of one completed epoch
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EpochLatency {
+    pub(crate) epoch: u64,
+    pub(crate) summaries: Vec<LatencySummary>,
+}
+
+struct Histogram {
+    buckets: Vec<u32>,
+    count: u64,
+    min: i64,
+    max: i64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            min: i64::MAX,
+            max: i64::MIN,
+        }
+    }
+
+    fn record(&mut self, value_ms: i64) {
+        let index = (value_ms.clamp(MIN_MS, MAX_MS - 1) - MIN_MS) / BUCKET_MS;
+        self.buckets[index as usize] = self.buckets[index as usize].saturating_add(1);
+        self.count += 1;
+        self.min = self.min.min(value_ms);
+        self.max = self.max.max(value_ms);
+    }
+
+    /// Highest value of the bucket holding the quantile, kept within the observed range
+    fn quantile(&self, quantile: f64) -> i64 {
+        let rank = ((quantile * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0u64;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += *count as u64;
+            if seen >= rank {
+                let upper = MIN_MS + (index as i64 + 1) * BUCKET_MS - 1;
+                return upper.clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+
+    fn summary(&self, kind: EventKind) -> LatencySummary {
+        LatencySummary {
+            event_type: kind.event_type().to_string(),
+            count: self.count,
+            min_ms: self.min,
+            p50_ms: self.quantile(0.5),
+            p90_ms: self.quantile(0.9),
+            p99_ms: self.quantile(0.99),
+            max_ms: self.max,
+        }
+    }
+}
+
+impl LatencyTracker {
+    /// Record the arrival of a gossip event for its slot
+    /// `timestamp_ms` is the local arrival time, before clock correction
+    pub(crate) fn record(
+        &self,
+        timing: &SlotTiming,
+        kind: EventKind,
+        slot: u64,
+        timestamp_ms: u64,
+    ) {
+        let arrival_ms = i64::try_from(timestamp_ms)
+            .unwrap_or(i64::MAX)
+            .saturating_add(crate::clock::offset_ms().unwrap_or(0));
+        let propagation_ms = timing.propagation_ms(slot, arrival_ms);
+        let epoch = timing.epoch(slot);
+
+        // A slot far ahead of the clock would close every open epoch early
+        if epoch > timing.epoch(timing.wallclock_slot(arrival_ms)) + 1 {
+            return;
+        }
+
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        if epoch < open.closed_before {
+            return;
+        }
+        // Events from two epochs later mean the older ones have settled
+        let settled = epoch.saturating_sub(1);
+        if settled > open.closed_before {
+            let still_open = open.histograms.split_off(&settled);
+            let completed = std::mem::replace(&mut open.histograms, still_open);
+            open.closed_before = settled;
+            self.completed
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .extend(completed.into_iter().map(|(epoch, histograms)| {
+                    EpochLatency {
+                        epoch,
+                        summaries: histograms
+                            .iter()
+                            .map(|(kind, histogram)| histogram.summary(*kind))
+                            .collect(),
+                    }
+                }));
+        }
+
+        let histograms = open.histograms.entry(epoch).or_default();
+        match histograms
+            .iter_mut()
+            .find(|(existing, _)| *existing == kind)
+        {
+            Some((_, histogram)) => histogram.record(propagation_ms),
+            None => {
+                let mut histogram = Histogram::new();
+                histogram.record(propagation_ms);
+                histograms.push((kind, histogram));
+            }
+        }
+    }
+
+    /// Take the summaries of epochs completed since the last call, oldest first
+    pub(crate) fn take_completed(&self) -> Vec<EpochLatency> {
+        std::mem::take(&mut *self.completed.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
@@ -3,24 +3,81 @@
 //! This crate provides FFI-based event export functionality for Lighthouse.
 
 // Public modules
+pub mod client;
 pub mod config;
+pub mod context;
+pub mod error;
+pub mod events;
+#[cfg(feature = "fake-sidecar")]
+pub mod fake_sidecar;
+pub mod forks;
+pub mod handle;
+pub mod middleware;
+pub mod networks;
+pub mod node;
+pub mod peers;
+pub mod registry;
+pub mod schema;
+pub mod session;
 pub mod shim;
+pub mod tap;
+pub mod testing;
+pub mod transform;
+pub mod validators;
 
 // Internal modules
+mod acks;
+mod attestation_groups;
+mod auth;
+// Spreading batches over an output's addresses, only the native outputs balance
+#[cfg(feature = "no-sidecar")]
+mod balance;
+mod bandwidth;
 mod chain;
+mod clock;
+// Primary/fallback routing, only the native outputs are routed from Rust
+#[cfg(feature = "no-sidecar")]
+mod failover;
+#[cfg(not(feature = "no-sidecar"))]
 mod ffi;
+// Native outputs with the same interface, for builds without the Go sidecar
+#[cfg(feature = "no-sidecar")]
+#[path = "native.rs"]
+mod ffi;
+mod filter;
+mod genesis;
 mod init;
+mod latency;
 mod metrics;
 mod observer_ffi;
 mod observer_trait;
+mod privacy;
+mod queue_budget;
+mod rate_limit;
+mod skew;
+mod slot_aggregate;
+mod template;
 
 use libp2p::PeerId;
-use lighthouse_network::MessageId;
+use lighthouse_network::types::GossipKind;
+use lighthouse_network::{MessageId, SyncState};
 use std::sync::Arc;
 use types::{EthSpec, SignedBeaconBlock};
 
-pub use config::{NetworkInfo, XatuConfig};
-pub use init::{init, init_with_chain_spec, init_with_chain_spec_and_genesis};
+pub use crate::metrics::set_metrics_registry;
+pub use config::{AggregationMode, NetworkInfo, XatuConfig};
+pub use context::XatuContext;
+pub use error::XatuError;
+pub use events::{EventMeta, ExportEvent, SlotTiming};
+pub use filter::{EventFilter, EventKind};
+pub use handle::{ExporterPhase, XatuHandle, XatuStats};
+pub use init::{
+    init, init_with_chain_spec, init_with_chain_spec_and_genesis, init_with_registry,
+    refresh_network, running,
+};
+pub use peers::{ConnectionDirection, PeerDetails, PeerSubnets};
+pub use registry::ExporterRegistry;
+pub use schema::EventData;
 
 // Keep these for backwards compatibility with Lighthouse integration
 pub use chain::XatuChain;
@@ -38,7 +95,7 @@ pub trait Xatu<E: EthSpec>: Send + Sync {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    );
+    ) -> ObserverResult;
 
     /// Called when an attestation is received via gossip
     fn on_gossip_attestation(
@@ -51,7 +108,23 @@ pub trait Xatu<E: EthSpec>: Send + Sync {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    );
+    ) -> ObserverResult;
+
+    /// Called when an attestation in the pre-Electra `Attestation` format is received via
+    /// gossip, identifying its attester by aggregation bits rather than an index
+    fn on_gossip_unaggregated_attestation(
+        &self,
+        _message_id: MessageId,
+        _peer_id: PeerId,
+        _attestation: Arc<types::Attestation<E>>,
+        _subnet_id: types::SubnetId,
+        _should_process: bool,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
 
     /// Called when an aggregate and proof is received via gossip
     fn on_gossip_aggregate_and_proof(
@@ -62,7 +135,7 @@ pub trait Xatu<E: EthSpec>: Send + Sync {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    );
+    ) -> ObserverResult;
 
     /// Called when a blob sidecar is received via gossip
     fn on_gossip_blob_sidecar(
@@ -75,7 +148,7 @@ pub trait Xatu<E: EthSpec>: Send + Sync {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    );
+    ) -> ObserverResult;
 
     /// Called when a data column sidecar is received via gossip
     fn on_gossip_data_column_sidecar(
@@ -88,14 +161,183 @@ pub trait Xatu<E: EthSpec>: Send + Sync {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    );
+    ) -> ObserverResult;
+
+    /// Called for every gossip message, including types without a dedicated hook
+    fn on_gossip_message(
+        &self,
+        _topic: &str,
+        _message_id: &MessageId,
+        _peer_id: &PeerId,
+        _raw_size: usize,
+        _decoded_kind: &GossipKind,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when a gossip message is sent, for bandwidth accounting
+    /// `peer_id` is `None` for messages published to the mesh as a whole
+    fn on_gossip_sent(
+        &self,
+        _topic: &str,
+        _peer_id: Option<&PeerId>,
+        _size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when a peer connects
+    fn on_peer_connected(&self, _peer_id: &PeerId, _details: &PeerDetails) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when a peer disconnects
+    fn on_peer_disconnected(&self, _peer_id: &PeerId) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called once after the exporter is created, with the network it exports for
+    fn on_start(&self, _network_info: &NetworkInfo) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when the node's sync state changes
+    fn on_sync_state_changed(&self, _old: &SyncState, _new: &SyncState) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when the local node's PeerDAS custody is first known and whenever it changes
+    fn on_custody_changed(
+        &self,
+        _custody_group_count: u64,
+        _custody_columns: &[u64],
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when a peer advertises its PeerDAS custody group count in its metadata or ENR
+    /// `custody_columns` are the columns derived from its node id, empty if not derived
+    fn on_peer_custody(
+        &self,
+        _peer_id: &PeerId,
+        _custody_group_count: u64,
+        _custody_columns: &[u64],
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when a peer advertises its gossip subnets in its metadata or ENR
+    fn on_peer_subnets(&self, _peer_id: &PeerId, _subnets: &PeerSubnets) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when the genesis time passed to `on_start` turned out to be a placeholder,
+    /// e.g. on a devnet started before genesis; later events derive their timing from it
+    fn on_genesis_time_changed(&self, _genesis_time: u64) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called when the node moved to another network than the one passed to `on_start`, e.g.
+    /// a devnet torn down and relaunched under it; the new genesis validators root is already
+    /// registered in `networks`
+    fn on_network_changed(&self, _network_info: &NetworkInfo) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Send everything queued right away and wait for it to be delivered, e.g. in tests or
+    /// before a shutdown hook; exporters that don't queue have nothing to do
+    fn flush(&self) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    /// Called once during shutdown; release resources and flush pending events here
+    fn on_shutdown(&self) -> ObserverResult {
+        ObserverResult::Ok
+    }
 }
 
 /// Result type for observer processing
 #[derive(Debug, Clone, PartialEq)]
 pub enum ObserverResult {
     Ok,
-    Error(String),
+    Error(ObserverError),
+}
+
+/// Failure reported by one or more observers for a single callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObserverError {
+    /// Event type of the failed callback, `None` for lifecycle calls
+    pub kind: Option<EventKind>,
+    /// One reason per failing observer
+    pub reasons: Vec<String>,
+}
+
+impl ObserverError {
+    pub fn new(kind: Option<EventKind>, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            reasons: vec![reason.into()],
+        }
+    }
+}
+
+impl std::fmt::Display for ObserverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{}: {}", kind.event_type(), self.reasons.join("; ")),
+            None => write!(f, "{}", self.reasons.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ObserverError {}
+
+impl ObserverResult {
+    /// Error result with a single reason and no event type
+    pub fn error(reason: impl Into<String>) -> Self {
+        ObserverResult::Error(ObserverError::new(None, reason))
+    }
+
+    /// Combine the results of several observers, keeping every failure reason
+    pub fn combine(results: impl IntoIterator<Item = ObserverResult>) -> ObserverResult {
+        let mut combined: Option<ObserverError> = None;
+        for result in results {
+            let ObserverResult::Error(error) = result else {
+                continue;
+            };
+            match &mut combined {
+                Some(combined) => {
+                    combined.kind = combined.kind.or(error.kind);
+                    combined.reasons.extend(error.reasons);
+                }
+                None => combined = Some(error),
+            }
+        }
+
+        match combined {
+            Some(error) => ObserverResult::Error(error),
+            None => ObserverResult::Ok,
+        }
+    }
+
+    /// Tag an error with the event type of the callback that produced it, if it has none
+    pub fn with_kind(self, kind: EventKind) -> Self {
+        match self {
+            ObserverResult::Error(mut error) => {
+                error.kind = error.kind.or(Some(kind));
+                ObserverResult::Error(error)
+            }
+            ObserverResult::Ok => ObserverResult::Ok,
+        }
+    }
+
+    /// Number of observers that reported a failure
+    pub fn error_count(&self) -> usize {
+        match self {
+            ObserverResult::Ok => 0,
+            ObserverResult::Error(error) => error.reasons.len(),
+        }
+    }
 }
 
 /// Re-export the concrete implementation
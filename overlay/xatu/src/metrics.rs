@@ -1,18 +1,397 @@
+use crate::events::EventData;
 pub use metrics::*;
-use std::sync::LazyLock;
+use prometheus::core::Collector;
+use prometheus::{HistogramOpts, Opts, Registry};
+use std::collections::HashMap;
+use std::sync::{LazyLock, OnceLock};
+
+// Registry xatu metrics are registered with, the default (lighthouse's) one if unset
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// Register xatu metrics with this registry instead of the default prometheus registry
+/// Lighthouse serves the default registry, so only embedders need this. It must be called
+/// before xatu is initialized; returns false if a registry was already set.
+pub fn set_metrics_registry(registry: Registry) -> bool {
+    REGISTRY.set(registry).is_ok()
+}
+
+fn register<C: Collector + Clone + 'static>(metric: Result<C>) -> Result<C> {
+    let metric = metric?;
+    match REGISTRY.get() {
+        Some(registry) => registry.register(Box::new(metric.clone()))?,
+        None => prometheus::register(Box::new(metric.clone()))?,
+    }
+    Ok(metric)
+}
+
+fn int_counter_vec(name: &str, help: &str, labels: &[&str]) -> Result<IntCounterVec> {
+    register(IntCounterVec::new(Opts::new(name, help), labels))
+}
+
+fn int_counter(name: &str, help: &str) -> Result<IntCounter> {
+    register(IntCounter::new(name, help))
+}
+
+fn int_gauge(name: &str, help: &str) -> Result<IntGauge> {
+    register(IntGauge::new(name, help))
+}
+
+#[cfg(feature = "no-sidecar")]
+fn int_gauge_vec(name: &str, help: &str, labels: &[&str]) -> Result<IntGaugeVec> {
+    register(IntGaugeVec::new(Opts::new(name, help), labels))
+}
+
+fn histogram(name: &str, help: &str, buckets: Result<Vec<f64>>) -> Result<Histogram> {
+    register(Histogram::with_opts(
+        HistogramOpts::new(name, help).buckets(buckets?),
+    ))
+}
 
 // Xatu event counter
 pub static XATU_EVENTS_SENT: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
-    try_create_int_counter_vec(
+    int_counter_vec(
         "xatu_events_sent_total",
         "Total number of events sent to Xatu sink",
         &["event_type"],
     )
 });
 
-// Helper function to increment counter for batch
-pub fn inc_events_sent_batch(count: usize) {
-    if let Some(counter) = XATU_EVENTS_SENT.as_ref().ok() {
-        counter.with_label_values(&["batch"]).inc_by(count as u64);
+// Sent events by the gossip topic they were received on
+pub static XATU_EVENTS_SENT_BY_TOPIC: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_sent_by_topic_total",
+        "Total number of events sent to Xatu sink by gossip topic",
+        &["topic"],
+    )
+});
+
+// Health of each address of outputs that balance over several, 1 while batches go to it
+#[cfg(feature = "no-sidecar")]
+pub static XATU_OUTPUT_ADDRESS_HEALTHY: LazyLock<Result<IntGaugeVec>> = LazyLock::new(|| {
+    int_gauge_vec(
+        "xatu_output_address_healthy",
+        "Whether an address of a load balanced Xatu output is receiving batches",
+        &["output", "address"],
+    )
+});
+
+// Batch submissions held back by the outbound rate limit
+pub static XATU_BATCHES_RATE_LIMITED: LazyLock<Result<IntCounter>> = LazyLock::new(|| {
+    int_counter(
+        "xatu_batches_rate_limited_total",
+        "Total number of batches that waited for the outbound rate limit",
+    )
+});
+
+// Batches dropped because too many were already waiting for the outbound rate limit
+pub static XATU_BATCHES_DROPPED_RATE_LIMITED: LazyLock<Result<IntCounter>> = LazyLock::new(|| {
+    int_counter(
+        "xatu_batches_dropped_rate_limited_total",
+        "Total number of batches dropped while the outbound rate limit held back the maximum",
+    )
+});
+
+// Batches dropped because they could not be serialized
+pub static XATU_BATCHES_UNSERIALIZABLE: LazyLock<Result<IntCounter>> = LazyLock::new(|| {
+    int_counter(
+        "xatu_batches_unserializable_total",
+        "Total number of batches dropped because they failed to serialize",
+    )
+});
+
+// Events refused because the exporter queue was full
+pub static XATU_EVENTS_QUEUE_FULL: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_queue_full_total",
+        "Total number of events dropped because the Xatu exporter queue was full",
+        &["event_type"],
+    )
+});
+
+// Approximate bytes held by queued events, only tracked with maxQueueBytes
+pub static XATU_QUEUE_BYTES: LazyLock<Result<IntGauge>> = LazyLock::new(|| {
+    int_gauge(
+        "xatu_queue_bytes",
+        "Approximate bytes of the events waiting on the Xatu exporter queue",
+    )
+});
+
+// Lifecycle phase of the exporter, see `set_exporter_phase` for the values
+pub static XATU_EXPORTER_PHASE: LazyLock<Result<IntGauge>> = LazyLock::new(|| {
+    int_gauge(
+        "xatu_exporter_phase",
+        "Lifecycle phase of the Xatu exporter: 0 uninitialized, 1 initializing, 2 running, 3 degraded, 4 shutting down, 5 closed",
+    )
+});
+
+// Events refused because the queue used up the memory budget of their type
+pub static XATU_EVENTS_OVER_QUEUE_BUDGET: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_over_queue_budget_total",
+        "Total number of events dropped because the queue reached maxQueueBytes for their type",
+        &["event_type"],
+    )
+});
+
+// Gossip events arriving at the exporter, before filtering or queueing
+pub static XATU_EVENTS_INGESTED: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_ingested_total",
+        "Total number of gossip events received by the Xatu exporter by gossip topic",
+        &["topic"],
+    )
+});
+
+// Failed batch sends by sidecar error code and mapped reason
+pub static XATU_SEND_ERRORS: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_send_errors_total",
+        "Total number of failed batch sends to the Xatu sink",
+        &["code", "reason"],
+    )
+});
+
+// Batch sends that failed in a row, reset on the next success
+pub static XATU_CONSECUTIVE_SEND_FAILURES: LazyLock<Result<IntGauge>> = LazyLock::new(|| {
+    int_gauge(
+        "xatu_consecutive_send_failures",
+        "Number of consecutive failed batch sends to the Xatu sink",
+    )
+});
+
+// Unix time of the last batch the sink accepted
+pub static XATU_LAST_SUCCESSFUL_SEND: LazyLock<Result<IntGauge>> = LazyLock::new(|| {
+    int_gauge(
+        "xatu_last_successful_send_timestamp_seconds",
+        "Unix time of the last batch successfully sent to the Xatu sink",
+    )
+});
+
+// Batches submitted to the sink and not yet acknowledged, including pending retries
+pub static XATU_BATCHES_IN_FLIGHT: LazyLock<Result<IntGauge>> = LazyLock::new(|| {
+    int_gauge(
+        "xatu_batches_in_flight",
+        "Number of batches submitted to the Xatu sink awaiting their outcome",
+    )
+});
+
+// Events seen by the metrics middleware, before they reach the exporter
+pub static XATU_EVENTS_OBSERVED: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_observed_total",
+        "Total number of gossip events observed by Xatu",
+        &["event_type"],
+    )
+});
+
+// Exporter errors returned for observed events
+pub static XATU_OBSERVER_ERRORS: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_observer_errors_total",
+        "Total number of errors returned by Xatu exporters",
+        &["event_type"],
+    )
+});
+
+// Exporter failures that reached the chain, counted per failing exporter
+pub static XATU_CHAIN_ERRORS: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_chain_exporter_errors_total",
+        "Total number of exporter failures returned to the Xatu chain",
+        &["event_type"],
+    )
+});
+
+// Every gossip message seen by the chain, by gossip kind
+pub static XATU_GOSSIP_MESSAGES: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_gossip_messages_total",
+        "Total number of gossip messages seen by Xatu",
+        &["kind"],
+    )
+});
+
+// Raw size of every gossip message seen by the chain, by gossip kind
+pub static XATU_GOSSIP_BYTES: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_gossip_bytes_total",
+        "Total size in bytes of gossip messages seen by Xatu",
+        &["kind"],
+    )
+});
+
+// Events dropped at dequeue for being too many slots behind the wallclock
+pub static XATU_EVENTS_EXPIRED: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_expired_total",
+        "Total number of events dropped for exceeding maxEventAgeSlots",
+        &["event_type"],
+    )
+});
+
+// Gossip events received before genesis that didn't fit the preGenesis buffer
+pub static XATU_EVENTS_DROPPED_PRE_GENESIS: LazyLock<Result<IntCounterVec>> = LazyLock::new(|| {
+    int_counter_vec(
+        "xatu_events_dropped_pre_genesis_total",
+        "Total number of gossip events received before genesis and dropped",
+        &["event_type"],
+    )
+});
+
+// Number of events in every batch handed to the sink
+pub static XATU_BATCH_SIZE: LazyLock<Result<Histogram>> = LazyLock::new(|| {
+    histogram(
+        "xatu_batch_size_events",
+        "Number of events per batch sent to the Xatu sink",
+        exponential_buckets(1.0, 4.0, 8),
+    )
+});
+
+// Time between consecutive batches handed to the sink
+pub static XATU_FLUSH_INTERVAL: LazyLock<Result<Histogram>> = LazyLock::new(|| {
+    histogram(
+        "xatu_flush_interval_seconds",
+        "Seconds between consecutive batches sent to the Xatu sink",
+        Ok(vec![
+            0.05, 0.1, 0.25, 0.5, 1.0, 1.5, 2.0, 5.0, 10.0, 30.0, 60.0,
+        ]),
+    )
+});
+
+pub fn observe_batch(size: usize, since_last_flush: Option<std::time::Duration>) {
+    observe(&XATU_BATCH_SIZE, size as f64);
+    if let Some(interval) = since_last_flush {
+        observe_duration(&XATU_FLUSH_INTERVAL, interval);
+    }
+}
+
+pub fn inc_send_errors(error: &crate::error::XatuError) {
+    let code = error
+        .ffi_code()
+        .map(|code| code.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    inc_counter_vec(&XATU_SEND_ERRORS, &[&code, error.reason()]);
+}
+
+pub fn set_consecutive_send_failures(count: u64) {
+    set_gauge(&XATU_CONSECUTIVE_SEND_FAILURES, count as i64);
+}
+
+pub fn set_batches_in_flight(count: usize) {
+    set_gauge(&XATU_BATCHES_IN_FLIGHT, count as i64);
+}
+
+#[cfg(feature = "no-sidecar")]
+pub fn set_output_address_healthy(output: &str, address: &str, healthy: bool) {
+    set_gauge_vec(
+        &XATU_OUTPUT_ADDRESS_HEALTHY,
+        &[output, address],
+        healthy as i64,
+    );
+}
+
+pub fn set_queue_bytes(bytes: u64) {
+    set_gauge(&XATU_QUEUE_BYTES, bytes as i64);
+}
+
+pub fn set_exporter_phase(phase: crate::handle::ExporterPhase) {
+    set_gauge(&XATU_EXPORTER_PHASE, phase as i64);
+}
+
+pub fn inc_events_over_queue_budget(kind: crate::filter::EventKind) {
+    inc_counter_vec(&XATU_EVENTS_OVER_QUEUE_BUDGET, &[kind.event_type()]);
+}
+
+pub fn inc_batches_rate_limited() {
+    inc_counter(&XATU_BATCHES_RATE_LIMITED);
+}
+
+pub fn inc_batches_dropped_rate_limited() {
+    inc_counter(&XATU_BATCHES_DROPPED_RATE_LIMITED);
+}
+
+pub fn inc_batches_unserializable() {
+    inc_counter(&XATU_BATCHES_UNSERIALIZABLE);
+}
+
+pub fn inc_events_queue_full(kind: crate::filter::EventKind) {
+    inc_counter_vec(&XATU_EVENTS_QUEUE_FULL, &[kind.event_type()]);
+}
+
+pub fn set_last_successful_send_now() {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    set_gauge(&XATU_LAST_SUCCESSFUL_SEND, now);
+}
+
+pub fn inc_events_ingested(topic: &str) {
+    inc_counter_vec(&XATU_EVENTS_INGESTED, &[topic]);
+}
+
+pub fn inc_events_observed(kind: crate::filter::EventKind) {
+    inc_counter_vec(&XATU_EVENTS_OBSERVED, &[kind.event_type()]);
+}
+
+pub fn inc_observer_errors(kind: crate::filter::EventKind) {
+    inc_counter_vec(&XATU_OBSERVER_ERRORS, &[kind.event_type()]);
+}
+
+pub fn inc_chain_errors(kind: crate::filter::EventKind, count: usize) {
+    if let Some(counter) = XATU_CHAIN_ERRORS.as_ref().ok() {
+        counter
+            .with_label_values(&[kind.event_type()])
+            .inc_by(count as u64);
+    }
+}
+
+pub fn inc_events_expired(kind: crate::filter::EventKind) {
+    inc_counter_vec(&XATU_EVENTS_EXPIRED, &[kind.event_type()]);
+}
+
+pub fn inc_events_dropped_pre_genesis(kind: crate::filter::EventKind) {
+    inc_counter_vec(&XATU_EVENTS_DROPPED_PRE_GENESIS, &[kind.event_type()]);
+}
+
+pub fn inc_gossip_messages(kind: &lighthouse_network::types::GossipKind, raw_size: usize) {
+    // Subnet ids are dropped from the label to keep cardinality bounded
+    let kind: &str = kind.as_ref();
+    inc_counter_vec(&XATU_GOSSIP_MESSAGES, &[kind]);
+    if let Some(counter) = XATU_GOSSIP_BYTES.as_ref().ok() {
+        counter.with_label_values(&[kind]).inc_by(raw_size as u64);
+    }
+}
+
+/// Event counts of a batch, recorded once the sink has accepted it
+#[derive(Debug, Default)]
+pub struct SentCounts {
+    by_type: HashMap<&'static str, u64>,
+    by_topic: HashMap<String, u64>,
+}
+
+impl SentCounts {
+    pub fn from_batch(batch: &[EventData]) -> Self {
+        let mut counts = Self::default();
+        for event in batch {
+            *counts.by_type.entry(event.event_type()).or_default() += 1;
+            if let Some(topic) = event.topic() {
+                *counts.by_topic.entry(topic.to_string()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn record(&self) {
+        if let Ok(counter) = XATU_EVENTS_SENT.as_ref() {
+            for (event_type, count) in &self.by_type {
+                counter.with_label_values(&[event_type]).inc_by(*count);
+            }
+        }
+        if let Ok(counter) = XATU_EVENTS_SENT_BY_TOPIC.as_ref() {
+            for (topic, count) in &self.by_topic {
+                counter.with_label_values(&[topic]).inc_by(*count);
+            }
+        }
     }
 }
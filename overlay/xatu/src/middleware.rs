@@ -0,0 +1,631 @@
+//! Composable middleware wrapping any `Xatu<E>` exporter
+//!
+//! Cross-cutting behavior (filtering, sampling, enrichment, metrics) lives here rather
+//! than inside a particular exporter, so every exporter gets it for free.
+
+use crate::config::{AggregationMode, FullConfig, NetworkInfo};
+use crate::context::XatuContext;
+use crate::error::XatuError;
+use crate::filter::{EventFilter, EventKind};
+use crate::peers::{PeerDetails, PeerSubnets};
+use crate::{ObserverResult, Xatu};
+use libp2p::PeerId;
+use lighthouse_network::types::GossipKind;
+use lighthouse_network::{MessageId, SyncState};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use types::{
+    Attestation, BlobSidecar, DataColumnSidecar, DataColumnSubnetId, EthSpec,
+    SignedAggregateAndProof, SignedBeaconBlock, SingleAttestation, SubnetId,
+};
+
+/// Per-call details shared by every gossip callback
+#[derive(Debug, Clone)]
+pub struct EventContext {
+    pub kind: EventKind,
+    pub message_id: MessageId,
+    pub peer_id: PeerId,
+    pub client: Option<String>,
+    pub slot: u64,
+    pub timestamp_millis: u64,
+    pub topic: String,
+    pub message_size: usize,
+    /// Identity of a single attester's vote, for unaggregated attestations
+    pub attestation_key: Option<AttestationKey>,
+}
+
+/// Identifies one attester's vote in a slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttestationKey {
+    pub slot: u64,
+    pub committee_index: u64,
+    pub attester: Attester,
+}
+
+/// How an unaggregated attestation identifies its attester
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attester {
+    /// Validator index carried by a `SingleAttestation`
+    Index(u64),
+    /// Position in the committee of the one aggregation bit set in a pre-Electra attestation
+    Position(usize),
+}
+
+impl AttestationKey {
+    /// Key of an attestation in the `Attestation` format, `None` unless exactly one
+    /// aggregation bit is set
+    pub fn from_attestation<E: EthSpec>(attestation: &Attestation<E>) -> Option<Self> {
+        let (set_bits, position) = match attestation {
+            Attestation::Base(att) => (
+                att.aggregation_bits.num_set_bits(),
+                att.aggregation_bits.iter().position(|bit| bit),
+            ),
+            Attestation::Electra(att) => (
+                att.aggregation_bits.num_set_bits(),
+                att.aggregation_bits.iter().position(|bit| bit),
+            ),
+        };
+        if set_bits != 1 {
+            return None;
+        }
+        let data = attestation.data();
+        Some(Self {
+            slot: data.slot.as_u64(),
+            committee_index: attestation.committee_index().unwrap_or(data.index),
+            attester: Attester::Position(position?),
+        })
+    }
+}
+
+/// Behavior run around the inner exporter for every gossip callback
+pub trait Middleware: Send + Sync {
+    /// Called before the inner exporter; return false to drop the event
+    fn before(&self, _ctx: &mut EventContext) -> bool {
+        true
+    }
+
+    /// Called with the inner exporter's result (not called for dropped events)
+    fn after(&self, _ctx: &EventContext, _result: &ObserverResult) {}
+}
+
+/// Wrap an exporter with a middleware
+pub fn layer<E: EthSpec, M: Middleware + 'static>(
+    inner: Arc<dyn Xatu<E>>,
+    middleware: M,
+) -> Arc<dyn Xatu<E>> {
+    Arc::new(Layered { inner, middleware })
+}
+
+/// Keys remembered by the dedup layer when `dedup.maxEntries` is unset
+const DEFAULT_DEDUP_ENTRIES: usize = 262_144;
+
+/// Wrap an exporter with the middleware stack described by the config
+/// Filtering runs first so dropped events are never sampled or counted, duplicates are
+/// dropped next, and latency is recorded before sampling
+pub fn layers_from_config<E: EthSpec>(
+    inner: Arc<dyn Xatu<E>>,
+    config: &FullConfig,
+    slots_per_epoch: u64,
+    context: Arc<XatuContext>,
+) -> Result<Arc<dyn Xatu<E>>, XatuError> {
+    let filter = Arc::new(EventFilter::from_config(config, slots_per_epoch)?);
+    Ok(layers_with_filter(inner, config, filter, context))
+}
+
+/// Wrap an exporter with the middleware stack using an already built filter
+/// Lets callers reject a bad filter or sampling config before building the exporter
+/// The latency and aggregate layers record into the context the inner exporter reports from
+pub fn layers_with_filter<E: EthSpec>(
+    inner: Arc<dyn Xatu<E>>,
+    config: &FullConfig,
+    filter: Arc<EventFilter>,
+    context: Arc<XatuContext>,
+) -> Arc<dyn Xatu<E>> {
+    let exporter = layer(inner, MetricsLayer);
+    let exporter = layer(exporter, SampleLayer::new(filter.clone()));
+    let mode = config
+        .settings
+        .aggregation
+        .as_ref()
+        .map(|aggregation| aggregation.mode)
+        .unwrap_or_default();
+    let exporter = if mode == AggregationMode::Off {
+        exporter
+    } else {
+        layer(exporter, AggregateLayer::new(mode, context.clone()))
+    };
+    let exporter = layer(exporter, LatencyLayer::new(context));
+    let exporter = match config.settings.dedup.as_ref() {
+        Some(dedup) if dedup.first_seen_attestations => layer(
+            exporter,
+            DedupLayer::new(dedup.max_entries.unwrap_or(DEFAULT_DEDUP_ENTRIES)),
+        ),
+        _ => exporter,
+    };
+    layer(exporter, FilterLayer::new(filter))
+}
+
+/// Drops events from filtered peers or outside the configured slot range
+pub struct FilterLayer {
+    filter: Arc<EventFilter>,
+}
+
+impl FilterLayer {
+    pub fn new(filter: Arc<EventFilter>) -> Self {
+        Self { filter }
+    }
+}
+
+impl Middleware for FilterLayer {
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        self.filter.allows_slot(ctx.slot) && self.filter.allows_peer(&ctx.peer_id)
+    }
+}
+
+/// Drops events outside the deterministic per-event-type sample
+pub struct SampleLayer {
+    filter: Arc<EventFilter>,
+}
+
+impl SampleLayer {
+    pub fn new(filter: Arc<EventFilter>) -> Self {
+        Self { filter }
+    }
+}
+
+impl Middleware for SampleLayer {
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        self.filter.is_sampled(ctx.kind, &ctx.message_id)
+    }
+}
+
+/// Drops attestations from an attester already seen in the slot, keeping the first arrival
+/// Only events with an `attestation_key` are checked, so aggregates pass through; the oldest
+/// keys are forgotten once `max_entries` are remembered
+pub struct DedupLayer {
+    seen: Mutex<SeenKeys>,
+}
+
+struct SeenKeys {
+    keys: HashSet<AttestationKey>,
+    order: VecDeque<AttestationKey>,
+    max_entries: usize,
+}
+
+impl DedupLayer {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            seen: Mutex::new(SeenKeys {
+                keys: HashSet::new(),
+                order: VecDeque::new(),
+                max_entries: max_entries.max(1),
+            }),
+        }
+    }
+}
+
+impl Middleware for DedupLayer {
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        let Some(key) = ctx.attestation_key else {
+            return true;
+        };
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        if !seen.keys.insert(key) {
+            return false;
+        }
+        seen.order.push_back(key);
+        if seen.order.len() > seen.max_entries {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Records the propagation latency of every event for the per-epoch percentiles
+/// Placed before sampling, so the percentiles cover events that are sampled away
+pub struct LatencyLayer {
+    context: Arc<XatuContext>,
+}
+
+impl LatencyLayer {
+    pub fn new(context: Arc<XatuContext>) -> Self {
+        Self { context }
+    }
+}
+
+impl Middleware for LatencyLayer {
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        let Some(timing) = self.context.timing() else {
+            return true;
+        };
+        self.context
+            .latency
+            .record(&timing, ctx.kind, ctx.slot, ctx.timestamp_millis);
+        self.context
+            .skew
+            .record(&timing, ctx.kind, ctx.slot, ctx.timestamp_millis);
+        true
+    }
+}
+
+/// Folds events into per-slot aggregates, dropping them in `aggregate` mode
+pub struct AggregateLayer {
+    mode: AggregationMode,
+    context: Arc<XatuContext>,
+}
+
+impl AggregateLayer {
+    pub fn new(mode: AggregationMode, context: Arc<XatuContext>) -> Self {
+        Self { mode, context }
+    }
+}
+
+impl Middleware for AggregateLayer {
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        if self.mode == AggregationMode::Off {
+            return true;
+        }
+        if let Some(timing) = self.context.timing() {
+            self.context.slot_aggregates.record(
+                &timing,
+                ctx.kind,
+                ctx.slot,
+                &ctx.peer_id,
+                ctx.timestamp_millis,
+            );
+        }
+        self.mode == AggregationMode::Both
+    }
+}
+
+/// Fills in missing event details, e.g. the client agent for event types that don't carry one
+pub struct EnrichLayer<F> {
+    client_lookup: F,
+}
+
+impl<F> EnrichLayer<F>
+where
+    F: Fn(&PeerId) -> Option<String> + Send + Sync,
+{
+    pub fn new(client_lookup: F) -> Self {
+        Self { client_lookup }
+    }
+}
+
+impl<F> Middleware for EnrichLayer<F>
+where
+    F: Fn(&PeerId) -> Option<String> + Send + Sync,
+{
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        if ctx.client.is_none() {
+            ctx.client = (self.client_lookup)(&ctx.peer_id);
+        }
+        true
+    }
+}
+
+/// Counts observed events and exporter errors per event type
+pub struct MetricsLayer;
+
+impl Middleware for MetricsLayer {
+    fn before(&self, ctx: &mut EventContext) -> bool {
+        crate::metrics::inc_events_observed(ctx.kind);
+        true
+    }
+
+    fn after(&self, ctx: &EventContext, result: &ObserverResult) {
+        if let ObserverResult::Error(_) = result {
+            crate::metrics::inc_observer_errors(ctx.kind);
+        }
+    }
+}
+
+struct Layered<E: EthSpec, M> {
+    inner: Arc<dyn Xatu<E>>,
+    middleware: M,
+}
+
+impl<E: EthSpec, M: Middleware> Layered<E, M> {
+    fn run(
+        &self,
+        mut ctx: EventContext,
+        call: impl FnOnce(&dyn Xatu<E>, &EventContext) -> ObserverResult,
+    ) -> ObserverResult {
+        if !self.middleware.before(&mut ctx) {
+            return ObserverResult::Ok;
+        }
+        let result = call(self.inner.as_ref(), &ctx);
+        self.middleware.after(&ctx, &result);
+        result
+    }
+}
+
+impl<E: EthSpec, M: Middleware> Xatu<E> for Layered<E, M> {
+    fn on_gossip_block(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        block: Arc<SignedBeaconBlock<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        let ctx = EventContext {
+            kind: EventKind::Block,
+            message_id,
+            peer_id,
+            client,
+            slot: block.slot().as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+            attestation_key: None,
+        };
+        self.run(ctx, |inner, ctx| {
+            inner.on_gossip_block(
+                ctx.message_id.clone(),
+                ctx.peer_id,
+                ctx.client.clone(),
+                block,
+                ctx.timestamp_millis,
+                ctx.topic.clone(),
+                ctx.message_size,
+            )
+        })
+    }
+
+    fn on_gossip_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<SingleAttestation>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        let ctx = EventContext {
+            kind: EventKind::Attestation,
+            message_id,
+            peer_id,
+            client: None,
+            slot: attestation.data.slot.as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+            attestation_key: Some(AttestationKey {
+                slot: attestation.data.slot.as_u64(),
+                committee_index: attestation.committee_index,
+                attester: Attester::Index(attestation.attester_index),
+            }),
+        };
+        self.run(ctx, |inner, ctx| {
+            inner.on_gossip_attestation(
+                ctx.message_id.clone(),
+                ctx.peer_id,
+                attestation,
+                subnet_id,
+                should_process,
+                ctx.timestamp_millis,
+                ctx.topic.clone(),
+                ctx.message_size,
+            )
+        })
+    }
+
+    fn on_gossip_unaggregated_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<Attestation<E>>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        let ctx = EventContext {
+            kind: EventKind::Attestation,
+            message_id,
+            peer_id,
+            client: None,
+            slot: attestation.data().slot.as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+            attestation_key: AttestationKey::from_attestation(&attestation),
+        };
+        self.run(ctx, |inner, ctx| {
+            inner.on_gossip_unaggregated_attestation(
+                ctx.message_id.clone(),
+                ctx.peer_id,
+                attestation,
+                subnet_id,
+                should_process,
+                ctx.timestamp_millis,
+                ctx.topic.clone(),
+                ctx.message_size,
+            )
+        })
+    }
+
+    fn on_gossip_aggregate_and_proof(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        aggregate: Arc<SignedAggregateAndProof<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        let ctx = EventContext {
+            kind: EventKind::Aggregate,
+            message_id,
+            peer_id,
+            client: None,
+            slot: aggregate.message().aggregate().data().slot.as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+            attestation_key: None,
+        };
+        self.run(ctx, |inner, ctx| {
+            inner.on_gossip_aggregate_and_proof(
+                ctx.message_id.clone(),
+                ctx.peer_id,
+                aggregate,
+                ctx.timestamp_millis,
+                ctx.topic.clone(),
+                ctx.message_size,
+            )
+        })
+    }
+
+    fn on_gossip_blob_sidecar(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        blob_index: u64,
+        blob_sidecar: Arc<BlobSidecar<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        let ctx = EventContext {
+            kind: EventKind::BlobSidecar,
+            message_id,
+            peer_id,
+            client,
+            slot: blob_sidecar.slot().as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+            attestation_key: None,
+        };
+        self.run(ctx, |inner, ctx| {
+            inner.on_gossip_blob_sidecar(
+                ctx.message_id.clone(),
+                ctx.peer_id,
+                ctx.client.clone(),
+                blob_index,
+                blob_sidecar,
+                ctx.timestamp_millis,
+                ctx.topic.clone(),
+                ctx.message_size,
+            )
+        })
+    }
+
+    fn on_gossip_data_column_sidecar(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        subnet_id: DataColumnSubnetId,
+        column_sidecar: Arc<DataColumnSidecar<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        let ctx = EventContext {
+            kind: EventKind::DataColumnSidecar,
+            message_id,
+            peer_id,
+            client,
+            slot: column_sidecar.slot().as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+            attestation_key: None,
+        };
+        self.run(ctx, |inner, ctx| {
+            inner.on_gossip_data_column_sidecar(
+                ctx.message_id.clone(),
+                ctx.peer_id,
+                ctx.client.clone(),
+                subnet_id,
+                column_sidecar,
+                ctx.timestamp_millis,
+                ctx.topic.clone(),
+                ctx.message_size,
+            )
+        })
+    }
+
+    // The catch-all and lifecycle hooks carry no event context, so they bypass the middleware
+    fn on_gossip_message(
+        &self,
+        topic: &str,
+        message_id: &MessageId,
+        peer_id: &PeerId,
+        raw_size: usize,
+        decoded_kind: &GossipKind,
+    ) -> ObserverResult {
+        self.inner
+            .on_gossip_message(topic, message_id, peer_id, raw_size, decoded_kind)
+    }
+
+    fn on_gossip_sent(&self, topic: &str, peer_id: Option<&PeerId>, size: usize) -> ObserverResult {
+        self.inner.on_gossip_sent(topic, peer_id, size)
+    }
+
+    fn on_peer_connected(&self, peer_id: &PeerId, details: &PeerDetails) -> ObserverResult {
+        self.inner.on_peer_connected(peer_id, details)
+    }
+
+    fn on_peer_disconnected(&self, peer_id: &PeerId) -> ObserverResult {
+        self.inner.on_peer_disconnected(peer_id)
+    }
+
+    fn on_start(&self, network_info: &NetworkInfo) -> ObserverResult {
+        self.inner.on_start(network_info)
+    }
+
+    fn on_sync_state_changed(&self, old: &SyncState, new: &SyncState) -> ObserverResult {
+        self.inner.on_sync_state_changed(old, new)
+    }
+
+    fn on_custody_changed(
+        &self,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        self.inner
+            .on_custody_changed(custody_group_count, custody_columns)
+    }
+
+    fn on_peer_custody(
+        &self,
+        peer_id: &PeerId,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        self.inner
+            .on_peer_custody(peer_id, custody_group_count, custody_columns)
+    }
+
+    fn on_peer_subnets(&self, peer_id: &PeerId, subnets: &PeerSubnets) -> ObserverResult {
+        self.inner.on_peer_subnets(peer_id, subnets)
+    }
+
+    fn on_genesis_time_changed(&self, genesis_time: u64) -> ObserverResult {
+        self.inner.on_genesis_time_changed(genesis_time)
+    }
+
+    fn on_network_changed(&self, network_info: &NetworkInfo) -> ObserverResult {
+        self.inner.on_network_changed(network_info)
+    }
+
+    fn flush(&self) -> ObserverResult {
+        self.inner.flush()
+    }
+
+    fn on_shutdown(&self) -> ObserverResult {
+        self.inner.on_shutdown()
+    }
+}
@@ -0,0 +1,514 @@
+//! Native Rust outputs used in place of the Go sidecar with the `no-sidecar` feature
+//!
+//! Mirrors the `ffi` module interface so the observer doesn't know which one it talks to.
+//! Supports `stdout`, `file` (JSON lines appended to `address`) and `http` outputs; the
+//! gRPC `xatu` output needs the sidecar and is rejected at init.
+//!
+//! Every output has its own bounded queue and writer thread, like the sidecar's outputs,
+//! so a slow or offline output can't hold up the others. A batch counts as delivered once
+//! any output has written it; an output whose queue is full drops the batch on its own.
+//! Outputs with `fallbackFor` only get batches while their primary is failing, see
+//! `failover`. An `http` output with `addresses` has a writer per address and sends each
+//! batch to one of them, see `balance`.
+
+use crate::balance::Balancer;
+use crate::config::{parse_duration, FullConfigWithRuntime, XatuOutput};
+use crate::error::XatuError;
+use crate::events::OutputFailoverEvent;
+use crate::failover::Circuit;
+use crate::schema::EVENT_SCHEMA_VERSION;
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{debug, debug_span, info, warn};
+
+// Output writers started at init, stopped on close
+static OUTPUTS: Mutex<Vec<OutputWorker>> = Mutex::new(Vec::new());
+
+// Version reported in place of a sidecar version, set once at init
+static SIDECAR_VERSION: OnceLock<SidecarVersion> = OnceLock::new();
+
+/// Export timeout for `http` outputs that don't set `exportTimeout`
+const DEFAULT_EXPORT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Events an output queues when it doesn't set `maxQueueSize`, the sidecar's default
+const DEFAULT_MAX_QUEUE_SIZE: u64 = 51_200;
+
+/// Version details, reported as the native exporter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SidecarVersion {
+    pub version: String,
+    /// Event schema version the outputs write
+    pub schema_version: u32,
+}
+
+/// Every event type can be written natively
+pub(crate) fn supports_event_type(_event_type: &str) -> bool {
+    true
+}
+
+/// Switches between primary and fallback outputs since the last call
+pub(crate) fn take_failovers() -> Vec<OutputFailoverEvent> {
+    crate::failover::take_transitions()
+}
+
+/// Native exporter version, once it has been checked at init
+pub(crate) fn sidecar_version() -> Option<&'static SidecarVersion> {
+    SIDECAR_VERSION.get()
+}
+
+enum Output {
+    Stdout,
+    File(BufWriter<File>),
+    Http {
+        agent: ureq::Agent,
+        url: String,
+        /// Shared with the worker's handle so headers can be replaced while it runs
+        headers: Arc<Mutex<Vec<(String, String)>>>,
+    },
+}
+
+impl Output {
+    /// Open a writer to `address`, one of the output's addresses
+    /// `headers` is shared by every writer of an `http` output
+    fn open(
+        output: &XatuOutput,
+        address: &str,
+        headers: &Arc<Mutex<Vec<(String, String)>>>,
+    ) -> Result<Self, XatuError> {
+        let config = &output.config;
+        match output.output_type.as_str() {
+            "stdout" => Ok(Output::Stdout),
+            "file" => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(address)
+                .map(|file| Output::File(BufWriter::new(file)))
+                .map_err(|e| {
+                    XatuError::ConfigIo(format!("Failed to open output file {}: {}", address, e))
+                }),
+            "http" if config.tls_config.is_some() => Err(XatuError::InvalidConfig(format!(
+                "Output '{}' sets tlsConfig, which needs the sidecar (native http uses system roots)",
+                output.name
+            ))),
+            "http" => {
+                let timeout = config
+                    .export_timeout
+                    .as_deref()
+                    .map(parse_duration)
+                    .transpose()?
+                    .unwrap_or(DEFAULT_EXPORT_TIMEOUT);
+                let url = if address.contains("://") {
+                    address.to_string()
+                } else if config.tls {
+                    format!("https://{}", address)
+                } else {
+                    format!("http://{}", address)
+                };
+                Ok(Output::Http {
+                    agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+                    url,
+                    headers: headers.clone(),
+                })
+            }
+            other => Err(XatuError::InvalidConfig(format!(
+                "Output '{}' has type '{}', which needs the sidecar (native: stdout, file, http)",
+                output.name, other
+            ))),
+        }
+    }
+
+    fn write(&mut self, json_data: &str) -> Result<(), XatuError> {
+        match self {
+            Output::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                writeln!(stdout, "{}", json_data).map_err(|_| XatuError::FfiSend(-3))
+            }
+            Output::File(file) => writeln!(file, "{}", json_data)
+                .and_then(|_| file.flush())
+                .map_err(|_| XatuError::FfiSend(-3)),
+            Output::Http {
+                agent,
+                url,
+                headers,
+            } => {
+                let mut request = agent.post(url).set("Content-Type", "application/json");
+                for (key, value) in headers.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+                    request = request.set(key, value);
+                }
+                match request.send_string(json_data) {
+                    Ok(_) => Ok(()),
+                    Err(ureq::Error::Status(..)) => Err(XatuError::FfiSend(-4)),
+                    Err(ureq::Error::Transport(_)) => Err(XatuError::FfiSend(-3)),
+                }
+            }
+        }
+    }
+}
+
+/// A batch queued on every output, completed by the first output that writes it
+struct PendingBatch {
+    batch_id: u64,
+    events: usize,
+    json_data: String,
+    /// Outputs that haven't written or given up on the batch yet
+    remaining: AtomicUsize,
+    completed: AtomicBool,
+}
+
+impl PendingBatch {
+    /// Record one output's outcome, reporting the batch on the first success, or once
+    /// every output has failed
+    fn finish(&self, result: Result<(), XatuError>) {
+        let last = self.remaining.fetch_sub(1, Ordering::AcqRel) == 1;
+        if (result.is_ok() || last) && !self.completed.swap(true, Ordering::AcqRel) {
+            crate::acks::complete(self.batch_id, result);
+        }
+    }
+}
+
+/// Which batches an output receives
+#[derive(Clone)]
+enum Route {
+    Always,
+    /// Every batch while the circuit is closed, probes while it is open
+    Primary(Arc<Circuit>),
+    /// Batches only while the primary's circuit is open
+    Fallback(Arc<Circuit>),
+}
+
+/// An output with its own queue and writer thread, or one per address when it balances
+struct OutputWorker {
+    name: String,
+    route: Route,
+    /// Writers in the order of the output's addresses
+    lanes: Vec<Lane>,
+    /// Picks the lane of each batch, `None` with a single address
+    balancer: Option<Arc<Balancer>>,
+    /// Events waiting on the queues, bounded by the output's `maxQueueSize`
+    queued_events: Arc<AtomicUsize>,
+    max_queue_size: usize,
+    /// Set on close so batches still queued are dropped instead of written
+    closing: Arc<AtomicBool>,
+    /// Headers of `http` outputs, `None` for other types
+    headers: Option<Arc<Mutex<Vec<(String, String)>>>>,
+}
+
+/// The queue and writer thread of one address
+struct Lane {
+    queue: Option<Sender<Arc<PendingBatch>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OutputWorker {
+    fn start(output: &XatuOutput, route: Route) -> Result<Self, XatuError> {
+        let config = &output.config;
+        let headers = Arc::new(Mutex::new(
+            config
+                .headers
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        ));
+        let max_queue_size = config
+            .max_queue_size
+            .unwrap_or(DEFAULT_MAX_QUEUE_SIZE)
+            .try_into()
+            .unwrap_or(usize::MAX);
+        let addresses: Vec<String> = std::iter::once(&config.address)
+            .chain(&config.addresses)
+            .cloned()
+            .collect();
+        let balancer = (addresses.len() > 1).then(|| {
+            Arc::new(Balancer::new(
+                &output.name,
+                config.load_balancing.unwrap_or_default(),
+                &addresses,
+            ))
+        });
+
+        let mut worker = Self {
+            name: output.name.clone(),
+            route,
+            lanes: Vec::with_capacity(addresses.len()),
+            balancer,
+            queued_events: Arc::new(AtomicUsize::new(0)),
+            max_queue_size,
+            closing: Arc::new(AtomicBool::new(false)),
+            headers: (output.output_type == "http").then(|| headers.clone()),
+        };
+        for (index, address) in addresses.iter().enumerate() {
+            let writer = Output::open(output, address, &headers)?;
+            let lane = worker.start_lane(index, writer)?;
+            worker.lanes.push(lane);
+        }
+        Ok(worker)
+    }
+
+    /// Spawn the writer thread of the address at `index`
+    fn start_lane(&self, index: usize, mut writer: Output) -> Result<Lane, XatuError> {
+        // Batches are bounded by events below, the channel only needs room for the count
+        let (queue, receiver) = bounded::<Arc<PendingBatch>>(self.max_queue_size.clamp(1, 4096));
+
+        let name = self.name.clone();
+        let route = self.route.clone();
+        let balancer = self.balancer.clone();
+        let queued_events = self.queued_events.clone();
+        let closing = self.closing.clone();
+        let thread = std::thread::Builder::new()
+            .name(format!("xatu-output-{}-{}", name, index))
+            .spawn(move || {
+                let address = balancer
+                    .as_ref()
+                    .map(|balancer| &balancer.addresses()[index]);
+                for batch in receiver {
+                    queued_events.fetch_sub(batch.events, Ordering::Relaxed);
+                    if closing.load(Ordering::Relaxed) {
+                        if let Some(address) = address {
+                            address.finish(batch.events);
+                        }
+                        batch.finish(Err(XatuError::SinkUnavailable));
+                        continue;
+                    }
+                    let span = debug_span!(
+                        "xatu_output_write",
+                        output = %name,
+                        batch_id = batch.batch_id,
+                        events = batch.events,
+                        bytes = batch.json_data.len()
+                    );
+                    let result = span.in_scope(|| writer.write(&batch.json_data));
+                    if let Err(e) = &result {
+                        match address {
+                            Some(address) => warn!(
+                                "Output '{}' failed to write batch {} to {}: {}",
+                                name,
+                                batch.batch_id,
+                                address.address(),
+                                e
+                            ),
+                            None => warn!(
+                                "Output '{}' failed to write batch {}: {}",
+                                name, batch.batch_id, e
+                            ),
+                        }
+                    }
+                    if let Some(address) = address {
+                        address.finish(batch.events);
+                        address.record(&result);
+                    }
+                    if let Route::Primary(circuit) = &route {
+                        circuit.record(&result);
+                    }
+                    batch.finish(result);
+                }
+            })
+            .map_err(|e| XatuError::Thread(format!("Failed to spawn output writer: {}", e)))?;
+
+        Ok(Lane {
+            queue: Some(queue),
+            thread: Some(thread),
+        })
+    }
+
+    /// Whether this output gets the next batch
+    fn receives(&self) -> bool {
+        match &self.route {
+            Route::Always => true,
+            Route::Primary(circuit) => circuit.primary_receives(),
+            Route::Fallback(circuit) => circuit.is_open(),
+        }
+    }
+
+    /// Queue a batch without blocking, dropping it for this output if the queue is full
+    fn enqueue(&self, batch: &Arc<PendingBatch>) {
+        let queued = self
+            .queued_events
+            .fetch_add(batch.events, Ordering::Relaxed);
+        let index = self.balancer.as_ref().map_or(0, |balancer| balancer.pick());
+        let address = self
+            .balancer
+            .as_ref()
+            .map(|balancer| &balancer.addresses()[index]);
+        if let Some(address) = address {
+            address.start(batch.events);
+        }
+        let sent = if queued + batch.events > self.max_queue_size {
+            Err(TrySendError::Full(batch.clone()))
+        } else {
+            match &self.lanes[index].queue {
+                Some(queue) => queue.try_send(batch.clone()),
+                None => Err(TrySendError::Disconnected(batch.clone())),
+            }
+        };
+        if sent.is_err() {
+            self.queued_events
+                .fetch_sub(batch.events, Ordering::Relaxed);
+            if let Some(address) = address {
+                address.finish(batch.events);
+            }
+            warn!(
+                "Output '{}' queue is full, dropping batch {} of {} events",
+                self.name, batch.batch_id, batch.events
+            );
+            // A primary too slow to keep up is failing as far as its fallback is concerned
+            if let Route::Primary(circuit) = &self.route {
+                circuit.record(&Err(XatuError::QueueFull));
+            }
+            batch.finish(Err(XatuError::QueueFull));
+        }
+    }
+
+    /// Stop the writers, dropping queued batches; waits for writes in progress to finish
+    fn stop(&mut self) {
+        self.closing.store(true, Ordering::Relaxed);
+        for lane in &mut self.lanes {
+            lane.queue = None;
+        }
+        for lane in &mut self.lanes {
+            if let Some(thread) = lane.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+impl Drop for OutputWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+pub struct XatuFFI;
+
+impl XatuFFI {
+    pub fn init_with_runtime(config: &FullConfigWithRuntime) -> Result<(), XatuError> {
+        let outputs = &config.processor.outputs;
+        let circuits: Vec<Arc<Circuit>> = outputs
+            .iter()
+            .filter_map(|output| {
+                let primary = output.config.fallback_for.as_deref()?;
+                Some(Arc::new(Circuit::new(primary, &output.name)))
+            })
+            .collect();
+        let outputs = outputs
+            .iter()
+            .map(|output| {
+                let route = match &output.config.fallback_for {
+                    Some(primary) => circuits
+                        .iter()
+                        .find(|circuit| circuit.primary() == primary)
+                        .map(|circuit| Route::Fallback(circuit.clone())),
+                    None => circuits
+                        .iter()
+                        .find(|circuit| circuit.primary() == output.name)
+                        .map(|circuit| Route::Primary(circuit.clone())),
+                };
+                OutputWorker::start(output, route.unwrap_or(Route::Always))
+            })
+            .collect::<Result<Vec<_>, XatuError>>()?;
+        info!(
+            "Xatu native exporter writing to {} output(s)",
+            outputs.len()
+        );
+
+        *OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()) = outputs;
+        Ok(())
+    }
+
+    /// Native outputs log through `tracing` directly
+    pub fn set_log_callback() -> Result<(), XatuError> {
+        Ok(())
+    }
+
+    /// Outcomes are reported directly from `submit_event_batch`
+    pub fn set_batch_callback() -> Result<(), XatuError> {
+        Ok(())
+    }
+
+    /// There is no sidecar to check, events are written in the schema this crate produces
+    pub fn check_version() -> Result<SidecarVersion, XatuError> {
+        let version = SidecarVersion {
+            version: format!("native-{}", env!("CARGO_PKG_VERSION")),
+            schema_version: EVENT_SCHEMA_VERSION,
+        };
+        crate::schema::negotiate_version(version.schema_version)?;
+        let _ = SIDECAR_VERSION.set(version.clone());
+        Ok(version)
+    }
+
+    /// Nothing to negotiate, every event type is supported
+    pub fn negotiate_capabilities() -> Result<(), XatuError> {
+        Ok(())
+    }
+
+    /// Queue a batch of `events` events serialized by `schema::serialize_batch` on every
+    /// output, the outcome is reported on the `acks` channel
+    pub fn submit_event_batch(
+        batch_id: u64,
+        events: usize,
+        json_data: String,
+    ) -> Result<(), XatuError> {
+        let outputs = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+        let targets: Vec<&OutputWorker> =
+            outputs.iter().filter(|output| output.receives()).collect();
+        if targets.is_empty() {
+            return Err(XatuError::SinkUnavailable);
+        }
+        let batch = Arc::new(PendingBatch {
+            batch_id,
+            events,
+            json_data,
+            remaining: AtomicUsize::new(targets.len()),
+            completed: AtomicBool::new(false),
+        });
+        for output in &targets {
+            output.enqueue(&batch);
+        }
+        debug!(
+            "Queued batch {} of {} events on {} output(s)",
+            batch_id,
+            events,
+            targets.len()
+        );
+        Ok(())
+    }
+
+    /// Replace a header of an `http` output, e.g. after its auth token was refreshed
+    pub fn set_output_header(output_name: &str, key: &str, value: &str) -> Result<(), XatuError> {
+        let outputs = OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(output) = outputs.iter().find(|output| output.name == output_name) else {
+            return Err(XatuError::InvalidConfig(format!(
+                "No output named '{}' to set header {} on",
+                output_name, key
+            )));
+        };
+        if let Some(headers) = &output.headers {
+            let mut headers = headers.lock().unwrap_or_else(|e| e.into_inner());
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(key));
+            headers.push((key.to_string(), value.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Native outputs write events as they are, nothing is derived from the genesis time
+    pub fn set_genesis_time(_genesis_time: u64) -> Result<(), XatuError> {
+        Ok(())
+    }
+
+    pub fn close() {
+        // Stopping the writers drops their outputs, which flushes any buffered file writes
+        let outputs = std::mem::take(&mut *OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()));
+        drop(outputs);
+    }
+}
+
+impl Drop for XatuFFI {
+    fn drop(&mut self) {
+        Self::close();
+    }
+}
@@ -0,0 +1,155 @@
+//! Network name resolution from the genesis validators root
+//!
+//! The chain spec's `config_name` and the `overrideNetworkName` setting are only labels, the
+//! genesis validators root identifies a public network for certain. Lighthouse passes the
+//! root to init so the canonical name can be resolved before the exporters start.
+//!
+//! The root and the network id also tell a devnet apart from its relaunch, which usually
+//! keeps the name. Both are recorded when the exporters start, so `init::refresh_network`
+//! can tell whether the node moved to another network since.
+
+use std::sync::RwLock;
+use tracing::{info, warn};
+use types::Hash256;
+
+/// Public networks by lowercase name and hex-encoded genesis validators root
+const KNOWN_NETWORKS: [(&str, &str); 6] = [
+    (
+        "mainnet",
+        "0x4b363db94e286120d76eb905340fdd4e54bfe9f06bf33ff6cf5ad27f511bfe95",
+    ),
+    (
+        "sepolia",
+        "0xd8ea171f3c94aea21ebc42a1ed61052acf3f9209c00e4efbaaddac09ed9b8078",
+    ),
+    (
+        "holesky",
+        "0x9143aa7c615a7f7115e2b6aac319c03529df8242ae705fba9df39b79c59fa8b1",
+    ),
+    (
+        "hoodi",
+        "0x212f13fc4df078b6cb7db228f1c8307566dcecf900867401a92023d7ba99cb5f",
+    ),
+    (
+        "gnosis",
+        "0xf5dcb5564e829aab27264b9becd5dfaa017085611224cb3036f573368dbb9d47",
+    ),
+    (
+        "chiado",
+        "0x9d642dac73058fbf39c0ae41ab1e34e4d889043cb199851ded7095bc99eb4c1e",
+    ),
+];
+
+/// The network one set of exporters follows
+#[derive(Debug, Default)]
+pub struct NetworkState {
+    genesis_validators_root: RwLock<Option<Hash256>>,
+    network_id: RwLock<Option<u64>>,
+    /// Override mismatch found when the network name was last resolved
+    mismatch: RwLock<Option<NetworkMismatch>>,
+}
+
+/// An `overrideNetworkName` that disagrees with the network detected from the root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkMismatch {
+    pub configured: String,
+    pub detected: String,
+    /// Hex-encoded genesis validators root the network was detected from
+    pub genesis_validators_root: String,
+}
+
+/// Hex encoding of a genesis validators root, as exported in events
+pub(crate) fn to_hex(root: Hash256) -> String {
+    format!("0x{}", hex::encode(root.0))
+}
+
+/// Name of the public network with this genesis validators root
+pub fn by_genesis_validators_root(root: Hash256) -> Option<&'static str> {
+    let root = to_hex(root);
+    KNOWN_NETWORKS
+        .iter()
+        .find(|(_, known)| *known == root)
+        .map(|(name, _)| *name)
+}
+
+impl NetworkState {
+    /// Set the genesis validators root of the chain the node follows
+    pub fn set_genesis_validators_root(&self, root: Hash256) {
+        *self
+            .genesis_validators_root
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = Some(root);
+    }
+
+    /// Registered genesis validators root, if any
+    pub fn genesis_validators_root(&self) -> Option<Hash256> {
+        *self
+            .genesis_validators_root
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Network id the exporters were started for, if they were started from a chain spec
+    pub fn network_id(&self) -> Option<u64> {
+        *self.network_id.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn set_network_id(&self, network_id: u64) {
+        *self.network_id.write().unwrap_or_else(|e| e.into_inner()) = Some(network_id);
+    }
+
+    /// Resolve the exported network name
+    ///
+    /// A detected public network wins over the chain spec's `config_name`. An explicit override
+    /// still wins, e.g. for shadow forks that share a public network's genesis, but disagreeing
+    /// with the detected network is recorded so it can be exported as a warning.
+    pub(crate) fn resolve_name(
+        &self,
+        override_name: Option<&str>,
+        config_name: Option<&str>,
+    ) -> String {
+        let root = self.genesis_validators_root();
+        let detected = root.and_then(by_genesis_validators_root);
+        *self.mismatch.write().unwrap_or_else(|e| e.into_inner()) = None;
+
+        if let Some(override_name) = override_name {
+            info!("Using override network name from config: {}", override_name);
+            if let (Some(detected), Some(root)) = (detected, root) {
+                if !override_name.eq_ignore_ascii_case(detected) {
+                    warn!(
+                        "Xatu network override '{}' disagrees with the genesis validators root, which is {}'s",
+                        override_name, detected
+                    );
+                    *self.mismatch.write().unwrap_or_else(|e| e.into_inner()) =
+                        Some(NetworkMismatch {
+                            configured: override_name.to_string(),
+                            detected: detected.to_string(),
+                            genesis_validators_root: to_hex(root),
+                        });
+                }
+            }
+            return override_name.to_string();
+        }
+
+        match (detected, config_name) {
+            (Some(detected), Some(config_name)) if !config_name.eq_ignore_ascii_case(detected) => {
+                warn!(
+                    "Chain spec names the network '{}', but its genesis validators root is {}'s",
+                    config_name, detected
+                );
+                detected.to_string()
+            }
+            (Some(detected), _) => detected.to_string(),
+            (None, Some(config_name)) => config_name.to_string(),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+
+    /// Override mismatch found when the network name was last resolved
+    pub(crate) fn mismatch(&self) -> Option<NetworkMismatch> {
+        self.mismatch
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
@@ -0,0 +1,58 @@
+//! Node details only lighthouse knows, reported in the `NODE_INFO` event
+//!
+//! Lighthouse registers these before initializing xatu, so they are in place when the
+//! event is emitted.
+
+use std::sync::RwLock;
+use types::data_column_custody_group::{compute_columns_for_custody_group, get_custody_groups};
+use types::{ChainSpec, EthSpec};
+
+static CUSTODY_GROUP_COUNT: RwLock<Option<u64>> = RwLock::new(None);
+
+static CUSTODY_COLUMNS: RwLock<Vec<u64>> = RwLock::new(Vec::new());
+
+/// Set the number of custody groups this node samples
+pub fn set_custody_group_count(count: u64) {
+    *CUSTODY_GROUP_COUNT
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = Some(count);
+}
+
+/// Registered custody group count, if any
+pub(crate) fn custody_group_count() -> Option<u64> {
+    *CUSTODY_GROUP_COUNT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set the data column indices this node custodies
+pub fn set_custody_columns(columns: Vec<u64>) {
+    *CUSTODY_COLUMNS.write().unwrap_or_else(|e| e.into_inner()) = columns;
+}
+
+/// Registered custody columns, empty if none were registered
+pub(crate) fn custody_columns() -> Vec<u64> {
+    CUSTODY_COLUMNS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Sorted data column indices a node custodies with `custody_group_count` groups, empty if
+/// the count is invalid for the spec
+pub fn custody_columns_for<E: EthSpec>(
+    node_id: [u8; 32],
+    custody_group_count: u64,
+    spec: &ChainSpec,
+) -> Vec<u64> {
+    let Ok(groups) = get_custody_groups(node_id, custody_group_count, spec) else {
+        return Vec::new();
+    };
+    let mut columns: Vec<u64> = groups
+        .into_iter()
+        .filter_map(|group| compute_columns_for_custody_group::<E>(group, spec).ok())
+        .flatten()
+        .collect();
+    columns.sort_unstable();
+    columns
+}
@@ -1,69 +1,175 @@
-use crate::ffi::*;
+use crate::acks::BatchAck;
+use crate::auth::TokenRefresher;
+use crate::bandwidth::BandwidthTracker;
+use crate::context::XatuContext;
+use crate::error::XatuError;
+use crate::events::{
+    AggregateAndProofEvent, AttestationEvent, BandwidthEvent, BeaconBlockEvent, ClockSkewEvent,
+    CustodyEvent, EventMeta, ExporterStatsEvent, NetworkChangedEvent, NetworkMismatchEvent,
+    NodeInfoEvent, PeerCustodyEvent, PropagationLatencyEvent, SessionStartEvent,
+    SlotAggregateEvent, SlotTiming, SubnetCoverageEvent,
+};
+use crate::ffi::XatuFFI;
+use crate::filter::EventKind;
+use crate::genesis::{Hold, PreGenesis};
+use crate::handle::{Control, ExporterPhase, ExporterState, XatuHandle};
 use crate::observer_trait::ObserverResult;
-use crossbeam_channel::{bounded, Sender};
+use crate::peers::{PeerCache, PeerDetails, PeerSubnets};
+use crate::privacy::PeerAnonymizer;
+use crate::queue_budget::{QueueBudget, QueuedEvent};
+use crate::rate_limit::RateLimiter;
+use crate::schema::EventData;
+use crate::template::TemplateVars;
+use crate::transform::Transforms;
+use crossbeam_channel::{
+    bounded, never, unbounded, Receiver, RecvTimeoutError, Sender, TrySendError,
+};
 use libp2p::PeerId;
+use lighthouse_network::types::GossipKind;
 use lighthouse_network::MessageId;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{atomic::Ordering, Arc, RwLock};
 use std::thread;
-use std::time::Duration;
-use tracing::{debug, error, info, warn};
+use std::time::{Duration, Instant};
+use tracing::{debug, debug_span, error, field, info, trace_span, warn};
 use types::{
-    BlobSidecar, DataColumnSidecar, DataColumnSubnetId, EthSpec, SignedAggregateAndProof,
-    SignedBeaconBlock, SingleAttestation, SubnetId,
+    Attestation, BlobSidecar, DataColumnSidecar, DataColumnSubnetId, EthSpec, Hash256,
+    SignedAggregateAndProof, SignedBeaconBlock, SingleAttestation, SubnetId,
 };
 
 pub struct XatuObserver {
-    initialized: Arc<AtomicBool>,
-    network_info: Option<crate::config::NetworkInfo>,
-    event_sender: Option<Sender<EventData>>,
+    /// Behind a lock so a placeholder genesis time can be corrected after init
+    network_info: RwLock<Option<crate::config::NetworkInfo>>,
+    /// Genesis validators root of the network, which tells a relaunched devnet apart
+    genesis_validators_root: RwLock<Option<Hash256>>,
+    /// Node name reported in the `SESSION_START` and `NODE_INFO` events
+    node_name: String,
+    /// Event types reported in the `NODE_INFO` event
+    enabled_event_types: Vec<String>,
+    anonymizer: PeerAnonymizer,
+    /// Shared with the batch thread, which reports subnet coverage from it
+    peers: Arc<PeerCache>,
+    /// Shared with the batch thread, which reports the totals periodically
+    bandwidth: Arc<BandwidthTracker>,
+    event_sender: Option<Sender<QueuedEvent>>,
+    /// Whether this observer initialized the sidecar and must shut it down
+    owns_ffi: bool,
+    /// Control handle for the batch thread, absent for detached observers
+    handle: Option<XatuHandle>,
+    /// Shared with the middleware and the batch thread
+    context: Arc<XatuContext>,
+    /// Keeps output auth tokens fresh while the observer lives
+    _token_refresher: Option<TokenRefresher>,
 }
 
+/// Batches are sent once they reach this many events, or on the 1 second timer
+const MAX_BATCH_SIZE: usize = 10000;
+
+/// How long each shutdown phase and a flush wait, in `on_shutdown`, `flush` and when
+/// dropped without a shutdown
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Submission attempts per batch before its events are dropped
+const MAX_BATCH_ATTEMPTS: u32 = 3;
+
+/// Serialized size at which a batch is sent when `maxBatchBytes` is unset
+const DEFAULT_MAX_BATCH_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Batches held back by the rate limit before further batches are dropped
+const MAX_THROTTLED_BATCHES: usize = 16;
+
+/// How long a submitted batch may go without an outcome before it counts as failed
+const ACK_DEADLINE: Duration = Duration::from_secs(60);
+
+/// Delay before a failed batch is submitted again
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long flush and shutdown wait for submitted batches to be acknowledged
+/// Kept below `SHUTDOWN_TIMEOUT` so the shutdown result still reaches the caller
+const ACK_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Default seconds between pipeline summary log lines
+const DEFAULT_SUMMARY_INTERVAL_SECS: u64 = 60;
+
+/// How often an `EXPORTER_STATS` event is added to the stream
+const STATS_EVENT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often a `SUBNET_COVERAGE` event is added to the stream
+const SUBNET_COVERAGE_EVENT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often a `BANDWIDTH` event is added to the stream
+const BANDWIDTH_EVENT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the clock is checked for skew
+const CLOCK_SKEW_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Skew above which `CLOCK_SKEW` events are emitted when `clockSkewThresholdMs` is unset
+const DEFAULT_CLOCK_SKEW_THRESHOLD_MS: u64 = 500;
+
+/// How often completed epochs and slots are checked for `PROPAGATION_LATENCY` and
+/// `SLOT_AGGREGATE` events, and output switches for `OUTPUT_FAILOVER` events
+const COMPLETED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 impl XatuObserver {
+    /// Create an observer with a context of its own
     pub fn new_with_full_config(
         full_config: &crate::config::FullConfig,
         network_info: Option<crate::config::NetworkInfo>,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let initialized = Arc::new(AtomicBool::new(false));
+    ) -> Result<Self, XatuError> {
+        Self::new_with_context(full_config, network_info, XatuContext::new(None))
+    }
+
+    /// Create an observer reporting into a context shared with its middleware
+    pub fn new_with_context(
+        full_config: &crate::config::FullConfig,
+        network_info: Option<crate::config::NetworkInfo>,
+        context: Arc<XatuContext>,
+    ) -> Result<Self, XatuError> {
+        let anonymizer = PeerAnonymizer::from_config(full_config);
+        if anonymizer.is_enabled() {
+            info!("Xatu peer id anonymization enabled");
+        }
+
+        // Event timestamps are taken here rather than in the sidecar, so correct them too
+        if let Some(ntp_server) = &full_config.settings.ntp_server {
+            crate::clock::start(ntp_server);
+        }
 
         // Clone for the spawned task
         let network_info_clone = network_info.clone();
+        let summary_interval = Duration::from_secs(
+            full_config
+                .settings
+                .summary_interval
+                .unwrap_or(DEFAULT_SUMMARY_INTERVAL_SECS),
+        );
 
-        // Try to get log level from RUST_LOG env var or default to info
-        let log_level = std::env::var("RUST_LOG")
-            .ok()
-            .and_then(|rust_log| {
-                // Parse common RUST_LOG patterns
-                if rust_log.contains("trace") {
-                    Some("trace".to_string())
-                } else if rust_log.contains("debug") {
-                    Some("debug".to_string())
-                } else if rust_log.contains("info") {
-                    Some("info".to_string())
-                } else if rust_log.contains("warn") {
-                    Some("warn".to_string())
-                } else if rust_log.contains("error") {
-                    Some("error".to_string())
-                } else {
-                    Some("info".to_string())
-                }
-            })
-            .or_else(|| Some("info".to_string()));
+        // Sidecar logging is configured explicitly rather than derived from RUST_LOG
+        let logging = full_config.settings.logging.clone().unwrap_or_default();
+        let log_level = Some(logging.level()?);
+
+        // The sidecar only reports broken output settings once it first connects
+        crate::config::XatuOutput::validate_all(&full_config.outputs)?;
 
         // Get implementation details
         let client_name = "lighthouse";
         let client_version = env!("CARGO_PKG_VERSION");
 
+        // Placeholders give every node sharing a config file its own identity
+        let template_vars = TemplateVars::current(
+            network_info_clone
+                .as_ref()
+                .map_or("unknown", |n| n.network_name.as_str()),
+        );
+        let node_name = configured_node_name(full_config, &template_vars)?;
+        let mut outputs = full_config.outputs.clone();
+        template_vars.expand_headers(&mut outputs)?;
+        crate::auth::inject_tokens(&mut outputs)?;
+
         // Build Xatu processor config
         let xatu_config = crate::config::XatuProcessorConfig {
-            name: full_config
-                .node
-                .as_ref()
-                .map(|n| n.name.clone())
-                .unwrap_or_else(|| "lighthouse".to_string()),
-            outputs: full_config.outputs.clone(),
+            name: node_name.clone(),
+            outputs: outputs.clone(),
             ethereum: crate::config::XatuEthereum {
                 implementation: "lighthouse".to_string(),
                 genesis_time: network_info_clone
@@ -93,158 +199,482 @@ impl XatuObserver {
                 name: client_name.to_string(),
                 version: client_version.to_string(),
             },
-            ntp_server: full_config.ntp_server.clone(),
+            ntp_server: full_config.settings.ntp_server.clone(),
         };
 
-        // Create combined config with runtime info
-        let config_with_runtime = crate::config::FullConfigWithRuntime {
+        // Create combined config with runtime info, pointed at another network on a restart
+        let mut config_with_runtime = crate::config::FullConfigWithRuntime {
             log_level,
+            logging: Some(logging),
             processor: xatu_config,
+            // Lowered once the sidecar reports its version
+            schema_version: crate::schema::EVENT_SCHEMA_VERSION,
         };
 
         // If network info is missing, fail immediately
-        if network_info.is_none() {
-            return Err("Network info is required for Xatu initialization".into());
-        }
+        let Some(info) = network_info.as_ref() else {
+            return Err(XatuError::NetworkInfoMissing);
+        };
+        context.set_timing(SlotTiming::from_network_info(info));
 
         // Create a channel to get initialization result from dedicated thread
         let (init_sender, init_receiver) = std::sync::mpsc::channel();
 
         // Create event channel for batching - use crossbeam for thread safety
-        let (event_sender, event_receiver) = bounded::<EventData>(10000);
+        let (event_sender, event_receiver) = bounded::<QueuedEvent>(10000);
+        let (control_sender, control_receiver) = unbounded::<Control>();
+        let state = Arc::new(ExporterState {
+            queue_budget: full_config.settings.max_queue_bytes.map(QueueBudget::new),
+            ..ExporterState::default()
+        });
+        let handle = XatuHandle::new(state.clone(), control_sender, event_receiver.clone());
+
+        let max_event_age_slots = full_config.settings.max_event_age_slots;
+        let clock_skew_threshold_ms = full_config
+            .settings
+            .clock_skew_threshold_ms
+            .unwrap_or(DEFAULT_CLOCK_SKEW_THRESHOLD_MS);
+        let max_batch_bytes = match full_config
+            .settings
+            .max_batch_bytes
+            .unwrap_or(DEFAULT_MAX_BATCH_BYTES)
+        {
+            0 => None,
+            bytes => Some(usize::try_from(bytes).unwrap_or(usize::MAX)),
+        };
+        let rate_limiter = full_config
+            .settings
+            .rate_limit
+            .as_ref()
+            .map(RateLimiter::from_config)
+            .transpose()?
+            .flatten();
+        let pre_genesis_config = full_config.settings.pre_genesis.clone().unwrap_or_default();
+        let pre_genesis = PreGenesis::start(&pre_genesis_config, context.timing());
+        if pre_genesis.is_some() {
+            info!("Xatu started before genesis, holding gossip events back until it");
+        }
+        let group_attestations = full_config
+            .settings
+            .aggregation
+            .as_ref()
+            .is_some_and(|aggregation| aggregation.attestation_data);
+        let transforms = full_config.settings.transforms.clone();
+        let peers = Arc::new(PeerCache::new());
+        let peers_for_thread = peers.clone();
+        let bandwidth = Arc::new(BandwidthTracker::new());
+        let bandwidth_for_thread = bandwidth.clone();
+        let anonymizer_for_thread = anonymizer.clone();
+        let context_for_thread = context.clone();
 
         // Start dedicated FFI thread
-        let initialized_for_thread = initialized.clone();
-        thread::spawn(move || {
+        let lazy_init = full_config
+            .settings
+            .lazy_init
+            .as_ref()
+            .map(|lazy_init| {
+                lazy_init
+                    .retry_interval()
+                    .map(|interval| (lazy_init.buffer_events(), interval))
+            })
+            .transpose()?;
+        // Set before the observer is returned, so a lazy init buffers from the first event
+        state.phase.advance(ExporterPhase::Initializing);
+        let batch_thread = move || {
             debug!("Starting dedicated FFI thread");
 
-            // Initialize FFI on this thread
-            debug!("Initializing Xatu FFI on dedicated thread...");
-            match XatuFFI::init_with_runtime(&config_with_runtime) {
-                Ok(()) => {
-                    initialized_for_thread.store(true, Ordering::Relaxed);
-                    let _ = init_sender.send(Ok(()));
+            // Initialize FFI on this thread, in the background with `lazyInit`
+            let buffered = match lazy_init {
+                Some((buffer_events, retry_interval)) => {
+                    let Some(buffered) = start_sidecar_lazily(
+                        &mut config_with_runtime,
+                        &state,
+                        &event_receiver,
+                        &control_receiver,
+                        buffer_events,
+                        retry_interval,
+                    ) else {
+                        state.phase.advance(ExporterPhase::Closed);
+                        return;
+                    };
+                    buffered
                 }
-                Err(e) => {
-                    error!("FATAL: Failed to initialize Xatu FFI: {}", e);
-                    let _ = init_sender.send(Err(e));
-                    return;
+                None => {
+                    if let Err(e) = start_sidecar(&config_with_runtime) {
+                        error!("FATAL: Failed to initialize Xatu FFI: {}", e);
+                        state.phase.advance(ExporterPhase::Closed);
+                        let _ = init_sender.send(Err(e));
+                        return;
+                    }
+                    VecDeque::new()
                 }
-            }
+            };
+            state.phase.advance(ExporterPhase::Running);
+            let _ = init_sender.send(Ok(()));
 
             // Continue with batch processing on same thread
-            debug!("Starting Xatu event batch processor on same thread with 1 second interval and max batch size of 10000");
-            let mut event_batch = Vec::new();
-            let mut total_events_processed = 0u64;
-            let mut total_batches_sent = 0u64;
-            let mut last_batch_time = std::time::Instant::now();
+            debug!("Starting Xatu event batch processor on same thread with 1 second interval and max batch size of {}", MAX_BATCH_SIZE);
+            // Grouped attestations would be rejected by a sidecar that doesn't know the type
+            let group_attestations = group_attestations
+                && crate::ffi::supports_event_type(EventKind::AttestationGroup.event_type());
+            let mut batcher = Batcher::new(
+                state.clone(),
+                context_for_thread.clone(),
+                group_attestations,
+                transforms,
+                max_event_age_slots,
+                max_batch_bytes,
+                rate_limiter,
+                pre_genesis,
+            );
+            for event in buffered {
+                batcher.accept(event);
+            }
+            let batch_acks = crate::acks::receiver();
+            let mut last_summary = Instant::now();
+            let mut last_stats_event = Instant::now();
+            let mut last_coverage_event = Instant::now();
+            let mut last_bandwidth_event = Instant::now();
+            let mut last_completed_poll = Instant::now();
+            let mut last_skew_check = Instant::now();
 
             loop {
-                // Check if it's time to send a batch (1 second interval)
-                let now = std::time::Instant::now();
-                let time_since_last_batch = now.duration_since(last_batch_time);
+                if !summary_interval.is_zero() && last_summary.elapsed() >= summary_interval {
+                    log_summary(&state, event_receiver.len());
+                    last_summary = Instant::now();
+                }
+                if last_stats_event.elapsed() >= STATS_EVENT_INTERVAL {
+                    batcher.push_stats(event_receiver.len());
+                    last_stats_event = Instant::now();
+                }
+                if last_coverage_event.elapsed() >= SUBNET_COVERAGE_EVENT_INTERVAL {
+                    batcher.push_subnet_coverage(&peers_for_thread);
+                    last_coverage_event = Instant::now();
+                }
+                if last_bandwidth_event.elapsed() >= BANDWIDTH_EVENT_INTERVAL {
+                    batcher.push_bandwidth(&bandwidth_for_thread, &anonymizer_for_thread);
+                    last_bandwidth_event = Instant::now();
+                }
+                if last_completed_poll.elapsed() >= COMPLETED_POLL_INTERVAL {
+                    batcher.push_latency();
+                    batcher.push_slot_aggregates();
+                    batcher.push_failovers();
+                    batcher.push_genesis();
+                    last_completed_poll = Instant::now();
+                }
+                if last_skew_check.elapsed() >= CLOCK_SKEW_CHECK_INTERVAL {
+                    batcher.push_clock_skew(clock_skew_threshold_ms);
+                    last_skew_check = Instant::now();
+                }
+                batcher.expire_unacked();
+                batcher.retry_due();
+                batcher.release_throttled();
 
-                // Try to receive events with a timeout
-                let timeout = if event_batch.is_empty() {
+                // If we have events, check more frequently
+                let timeout = if batcher.is_empty() && !batcher.is_throttled() {
                     Duration::from_secs(1)
                 } else {
-                    // If we have events, check more frequently
                     Duration::from_millis(100)
                 };
 
-                match event_receiver.recv_timeout(timeout) {
-                    Ok(event) => {
-                        event_batch.push(event);
-                        let current_batch_size = event_batch.len();
-
-                        if current_batch_size % 1000 == 0 && current_batch_size > 0 {
-                            debug!(
-                                "Batch size reached {}, will send at 10000 or next timer tick",
-                                current_batch_size
-                            );
+                crossbeam_channel::select! {
+                    recv(event_receiver) -> event => match event {
+                        Ok(event) => batcher.accept(event),
+                        Err(_) => {
+                            warn!("Event channel disconnected, stopping batch processor");
+                            break;
                         }
-
-                        // If batch gets too large, send immediately
-                        if current_batch_size >= 10000 {
-                            debug!("Batch size limit reached (10000 events), sending immediately");
-                            let batch = std::mem::take(&mut event_batch);
-                            let count = batch.len();
-                            match XatuFFI::send_event_batch(batch) {
-                                Ok(()) => {
-                                    total_events_processed += count as u64;
-                                    total_batches_sent += 1;
-                                    debug!(
-                                        "Successfully sent batch #{} with {} events (size limit). Total events: {}", 
-                                        total_batches_sent, count, total_events_processed
-                                    );
-                                    crate::metrics::inc_events_sent_batch(count);
-                                }
-                                Err(e) => {
-                                    error!("Failed to send event batch (size limit): {}", e);
-                                }
+                    },
+                    recv(batch_acks) -> batch_ack => {
+                        if let Ok(batch_ack) = batch_ack {
+                            let _ = batcher.handle_ack(batch_ack);
+                        }
+                    },
+                    recv(control_receiver) -> control => match control {
+                        Ok(Control::Flush(ack)) => {
+                            batcher.drain(&event_receiver);
+                            let sent = batcher.send("flush");
+                            let delivered = batcher.wait_for_acks(&batch_acks, ACK_TIMEOUT);
+                            let _ = ack.send(sent.and(delivered));
+                        }
+                        Ok(Control::Close(ack)) => {
+                            batcher.discard(&event_receiver, "close");
+                            info!("Xatu FFI: Closing forwarder on shutdown request");
+                            XatuFFI::close();
+                            state.phase.advance(ExporterPhase::Closed);
+                            let _ = ack.send(Ok(()));
+                            break;
+                        }
+                        Ok(Control::Restart(network_info, changed)) => {
+                            // What was queued so far belongs to the old network and its sidecar
+                            batcher.drain(&event_receiver);
+                            let sent = batcher.send("network change");
+                            let delivered = batcher.wait_for_acks(&batch_acks, ACK_TIMEOUT);
+                            if let Err(e) = sent.and(delivered) {
+                                warn!("Xatu FFI: Flush before the network change failed: {}", e);
+                            }
+                            batcher.restart(PreGenesis::start(
+                                &pre_genesis_config,
+                                context_for_thread.timing(),
+                            ));
+                            config_with_runtime.processor.ethereum.set_network(&network_info);
+                            let Some(buffered) = restart_sidecar(
+                                &mut config_with_runtime,
+                                &state,
+                                &event_receiver,
+                                &control_receiver,
+                                lazy_init,
+                            ) else {
+                                state.events_dropped.fetch_add(1, Ordering::Relaxed);
+                                break;
+                            };
+                            for event in buffered {
+                                batcher.accept(event);
+                            }
+                            // Unsupported types would get the whole batch rejected, as in `queue`
+                            if crate::ffi::supports_event_type(changed.0.event_type()) {
+                                batcher.accept(*changed);
                             }
-                            last_batch_time = now;
                         }
-                    }
-                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
+                        // The observer keeps a handle alive, so this only happens while it is dropped
+                        Err(_) => {}
+                    },
+                    default(timeout) => {
                         // Check if it's time to send what we have
-                        if time_since_last_batch >= Duration::from_secs(1)
-                            && !event_batch.is_empty()
-                            && initialized_for_thread.load(Ordering::Relaxed)
-                        {
-                            let batch = std::mem::take(&mut event_batch);
-                            let count = batch.len();
-                            match XatuFFI::send_event_batch(batch) {
-                                Ok(()) => {
-                                    total_events_processed += count as u64;
-                                    total_batches_sent += 1;
-                                    debug!(
-                                        "Successfully sent batch #{} with {} events (timer). Total events: {}", 
-                                        total_batches_sent, count, total_events_processed
-                                    );
-                                    crate::metrics::inc_events_sent_batch(count);
-                                }
-                                Err(e) => {
-                                    error!("Failed to send event batch (timer): {}", e);
-                                }
-                            }
-                            last_batch_time = now;
+                        if batcher.is_due() && !batcher.is_empty() {
+                            let _ = batcher.send("timer");
                         }
                     }
-                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
-                        warn!("Event channel disconnected, stopping batch processor");
-                        break;
-                    }
                 }
             }
-        });
 
-        // Wait for initialization result
-        match init_receiver.recv() {
-            Ok(Ok(())) => {
-                info!("Xatu FFI initialization completed successfully");
-            }
-            Ok(Err(e)) => {
-                return Err(format!("Failed to initialize Xatu FFI: {}", e).into());
-            }
-            Err(_) => {
-                return Err("FFI thread failed to send initialization result".into());
+            state.phase.advance(ExporterPhase::Closed);
+        };
+        thread::Builder::new()
+            .name("xatu-batch".to_string())
+            .spawn(batch_thread)
+            .map_err(|e| XatuError::Thread(format!("Failed to spawn FFI thread: {}", e)))?;
+
+        // Wait for initialization result, unless it happens in the background
+        if lazy_init.is_some() {
+            info!("Xatu FFI initializing in the background, buffering events until it is up");
+        } else {
+            match init_receiver.recv() {
+                Ok(Ok(())) => {
+                    info!("Xatu FFI initialization completed successfully");
+                }
+                Ok(Err(e)) => {
+                    return Err(e);
+                }
+                Err(_) => {
+                    return Err(XatuError::Thread(
+                        "FFI thread failed to send initialization result".to_string(),
+                    ));
+                }
             }
         }
 
-        // event_sender was already created above, no need to create it again
+        context.set_handle(handle.clone());
 
         Ok(Self {
-            initialized,
-            network_info,
+            network_info: RwLock::new(network_info),
+            genesis_validators_root: RwLock::new(context.networks.genesis_validators_root()),
+            node_name,
+            enabled_event_types: enabled_event_types(full_config),
+            anonymizer,
+            peers,
+            bandwidth,
             event_sender: Some(event_sender),
+            owns_ffi: true,
+            handle: Some(handle),
+            context,
+            _token_refresher: TokenRefresher::start(&outputs),
         })
     }
 
+    /// Create an observer that never touches the sidecar
+    /// Every event it would have exported is delivered on the returned receiver instead
+    /// Like the sidecar observer it applies no filters itself; wrap it with the middleware
+    pub(crate) fn new_detached(
+        full_config: &crate::config::FullConfig,
+        network_info: crate::config::NetworkInfo,
+        context: Arc<XatuContext>,
+    ) -> Result<(Self, Receiver<QueuedEvent>), XatuError> {
+        let anonymizer = PeerAnonymizer::from_config(full_config);
+        let (event_sender, event_receiver) = unbounded::<QueuedEvent>();
+        let node_name = configured_node_name(
+            full_config,
+            &TemplateVars::current(&network_info.network_name),
+        )?;
+
+        context.set_timing(SlotTiming::from_network_info(&network_info));
+
+        let observer = Self {
+            network_info: RwLock::new(Some(network_info)),
+            genesis_validators_root: RwLock::new(context.networks.genesis_validators_root()),
+            node_name,
+            enabled_event_types: enabled_event_types(full_config),
+            anonymizer,
+            peers: Arc::new(PeerCache::new()),
+            bandwidth: Arc::new(BandwidthTracker::new()),
+            event_sender: Some(event_sender),
+            owns_ffi: false,
+            handle: None,
+            context,
+            _token_refresher: None,
+        };
+
+        Ok((observer, event_receiver))
+    }
+
     pub fn with_network_info(mut self, network_info: crate::config::NetworkInfo) -> Self {
-        self.network_info = Some(network_info);
+        *self
+            .network_info
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner()) = Some(network_info);
         self
     }
+
+    /// Handle to flush or shut down the sidecar, `None` for observers that don't own it
+    pub fn handle(&self) -> Option<XatuHandle> {
+        self.handle.clone()
+    }
+
+    /// State shared with the middleware, e.g. to register a pubkey lookup
+    pub fn context(&self) -> &Arc<XatuContext> {
+        &self.context
+    }
+
+    /// Lifecycle phase of the exporter, detached observers are always running
+    fn phase(&self) -> ExporterPhase {
+        match &self.handle {
+            Some(handle) => handle.phase(),
+            None => ExporterPhase::Running,
+        }
+    }
+
+    /// Drain the queue and send it right away, waiting until the sink acknowledged it
+    /// Observers that don't own the sidecar deliver events as they come, so have nothing to do
+    pub fn flush(&self) -> Result<(), XatuError> {
+        match &self.handle {
+            Some(handle) => handle.flush(SHUTDOWN_TIMEOUT),
+            None => Ok(()),
+        }
+    }
+}
+
+impl XatuObserver {
+    /// Slot timing from the network info, or an error if it is missing
+    fn slot_timing(&self) -> Result<SlotTiming, ObserverResult> {
+        match self
+            .network_info
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            Some(info) => Ok(SlotTiming::from_network_info(info)),
+            None => {
+                error!("Xatu FFI: Network info not available");
+                Err(ObserverResult::error("Network info not available"))
+            }
+        }
+    }
+
+    /// Shared event metadata with the peer id rendered for export
+    fn event_meta(
+        &self,
+        message_id: &MessageId,
+        peer_id: &PeerId,
+        client: Option<String>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> EventMeta {
+        EventMeta::new(
+            self.anonymizer.peer_id(peer_id),
+            message_id,
+            topic,
+            message_size,
+            timestamp_millis,
+            client,
+        )
+        .with_clock_offset(crate::clock::offset_ms())
+        .with_peer(self.peer_summary(peer_id))
+    }
+
+    /// Cached details of the peer, without the remote address when peer ids are anonymized
+    fn peer_summary(&self, peer_id: &PeerId) -> Option<crate::peers::PeerSummary> {
+        let mut summary = self.peers.summary(peer_id)?;
+        if self.anonymizer.is_enabled() {
+            summary.remote_addr = None;
+        }
+        Some(summary)
+    }
+
+    /// Queue an event for the batch thread, reporting why it was dropped if it wasn't
+    fn queue(&self, event: EventData) -> ObserverResult {
+        // Unsupported types would get the whole batch rejected by the sidecar
+        if !crate::ffi::supports_event_type(event.event_type()) {
+            return ObserverResult::Ok;
+        }
+        let Some(sender) = &self.event_sender else {
+            return ObserverResult::Ok;
+        };
+        let kind = event.kind();
+        let event_type = event.event_type();
+        let span = trace_span!("xatu_queue", event_type, bytes = field::Empty).entered();
+        let state = self.handle.as_ref().map(|handle| handle.state());
+        // Events arriving after shutdown started would only be dropped at close
+        if let Some(state) = state.filter(|state| !state.phase.get().accepts_events()) {
+            state.events_dropped.fetch_add(1, Ordering::Relaxed);
+            return ObserverResult::error(format!(
+                "Exporter {}, {} event dropped",
+                state.phase.get(),
+                event_type
+            ))
+            .with_kind(kind);
+        }
+        let budget = state.and_then(|state| state.queue_budget.as_ref());
+        let bytes = budget.map_or(0, |_| event.json_len() as u64);
+        if let Some(budget) = budget {
+            span.record("bytes", bytes);
+            if !budget.try_reserve(kind, bytes) {
+                crate::metrics::inc_events_over_queue_budget(kind);
+                if let Some(state) = state {
+                    state.events_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                return ObserverResult::error(format!(
+                    "Queue budget exhausted, {} event dropped",
+                    event_type
+                ))
+                .with_kind(kind);
+            }
+        }
+        // The hook runs on a network thread, so a full queue drops the event instead
+        let Err(e) = sender.try_send((event, bytes)) else {
+            if let Some(state) = state {
+                state.events_queued.fetch_add(1, Ordering::Relaxed);
+            }
+            return ObserverResult::Ok;
+        };
+        let reason = match e {
+            TrySendError::Full(_) => {
+                crate::metrics::inc_events_queue_full(kind);
+                format!("Event queue full, {} event dropped", event_type)
+            }
+            TrySendError::Disconnected(_) => {
+                error!("Failed to queue {} event: channel disconnected", event_type);
+                format!("Event queue disconnected, {} event dropped", event_type)
+            }
+        };
+        if let Some(budget) = budget {
+            budget.release(bytes);
+        }
+        if let Some(state) = state {
+            state.events_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        ObserverResult::error(reason).with_kind(kind)
+    }
 }
 
 impl crate::observer_trait::XatuObserverTrait for XatuObserver {
@@ -252,80 +682,47 @@ impl crate::observer_trait::XatuObserverTrait for XatuObserver {
         &self,
         message_id: MessageId,
         peer_id: PeerId,
-        _client: Option<String>,
+        client: Option<String>,
         block: Arc<SignedBeaconBlock<E>>,
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
+        crate::metrics::inc_events_ingested(&topic);
+
         let slot = block.slot();
-        let signed_block_header = block.signed_block_header();
-        let block_root = signed_block_header.message.canonical_root();
         debug!(
-            "Xatu FFI: Received gossip block - slot: {}, root: 0x{}, message_id: {:?}",
-            slot,
-            hex::encode(&block_root.0[..8]),
-            message_id
+            "Xatu FFI: Received gossip block - slot: {}, message_id: {:?}",
+            slot, message_id
         );
 
-        if !self.initialized.load(Ordering::Relaxed) {
+        let phase = self.phase();
+        if !phase.is_active() {
             warn!(
-                "Xatu FFI: Not initialized yet, skipping block at slot {}",
-                slot
+                "Xatu FFI: Exporter {}, skipping block at slot {}",
+                phase, slot
             );
-            return ObserverResult::Ok;
+            return ObserverResult::error(format!("Exporter {}", phase))
+                .with_kind(EventKind::Block);
         }
 
-        let proposer_index = block.message().proposer_index();
-        let slot_u64 = slot.as_u64();
-
-        // Get network info for calculations
-        let network_info = match self.network_info.as_ref() {
-            Some(info) => info,
-            None => {
-                error!("Xatu FFI: Network info not available, cannot calculate timestamps");
-                return ObserverResult::Error("Network info not available".to_string());
-            }
+        let timing = match self.slot_timing() {
+            Ok(timing) => timing,
+            Err(result) => return result,
         };
 
-        // Calculate epoch using network-specific slots per epoch
-        let epoch = slot_u64 / network_info.slots_per_epoch;
-
-        let event = EventData::BeaconBlock {
-            peer_id: peer_id.to_string(),
-            message_id: hex::encode(&message_id.0),
+        // Block events don't carry the raw client, only its fingerprint
+        let meta = self.event_meta(
+            &message_id,
+            &peer_id,
+            client,
+            timestamp_millis,
             topic,
-            message_size: message_size as u32,
-            timestamp_ms: timestamp_millis as i64,
-            slot: slot_u64,
-            epoch,
-            block_root: format!("0x{}", hex::encode(block_root.0)),
-            proposer_index,
-        };
-
-        debug!(
-            "Xatu FFI: Processing block event - slot: {}, peer: {}",
-            slot, peer_id
+            message_size,
         );
-
-        if let Some(sender) = &self.event_sender {
-            match sender.send(event) {
-                Ok(()) => {
-                    debug!(
-                        "Queued beacon block event for slot {} from peer {}",
-                        slot, peer_id
-                    );
-                }
-                Err(e) => {
-                    error!(
-                        "Failed to queue beacon block event for slot {}: {:?}",
-                        slot, e
-                    );
-                }
-            }
-        }
-
-        ObserverResult::Ok
+        let mut event = BeaconBlockEvent::new(meta, &block, &timing, &self.context.forks);
+        event.proposer_pubkey = self.context.validators.pubkey(event.proposer_index);
+        self.queue(event.into())
     }
 
     fn on_gossip_attestation<E: EthSpec>(
@@ -339,75 +736,95 @@ impl crate::observer_trait::XatuObserverTrait for XatuObserver {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        let beacon_block_root = attestation.data.beacon_block_root;
+        crate::metrics::inc_events_ingested(&topic);
+
         debug!(
-            "Xatu FFI: Received gossip attestation - subnet: {}, beacon_block_root: 0x{}, message_id: {:?}",
-            *subnet_id,
-            hex::encode(&beacon_block_root.0[..8]),
-            message_id
+            "Xatu FFI: Received gossip attestation - slot: {}, subnet: {}, message_id: {:?}",
+            attestation.data.slot, *subnet_id, message_id
         );
 
-        if !self.initialized.load(Ordering::Relaxed) {
-            warn!("Xatu FFI: Not initialized yet, skipping attestation");
-            return ObserverResult::Ok;
+        let phase = self.phase();
+        if !phase.is_active() {
+            warn!("Xatu FFI: Exporter {}, skipping attestation", phase);
+            return ObserverResult::error(format!("Exporter {}", phase))
+                .with_kind(EventKind::Attestation);
         }
 
-        let slot = attestation.data.slot;
-        let slot_u64 = slot.as_u64();
-
-        // Get network info for epoch calculation
-        let network_info = match self.network_info.as_ref() {
-            Some(info) => info,
-            None => {
-                error!("Xatu FFI: Network info not available");
-                return ObserverResult::Error("Network info not available".to_string());
-            }
+        let timing = match self.slot_timing() {
+            Ok(timing) => timing,
+            Err(result) => return result,
         };
 
-        let epoch = slot_u64 / network_info.slots_per_epoch;
-
-        let event = EventData::Attestation {
-            peer_id: peer_id.to_string(),
-            slot: slot_u64,
-            epoch,
-            attestation_data_root: format!("0x{}", hex::encode(beacon_block_root.0)),
-            subnet_id: u64::from(subnet_id),
-            timestamp_ms: timestamp_millis as i64,
-            message_id: hex::encode(&message_id.0),
-            should_process,
+        let meta = self.event_meta(
+            &message_id,
+            &peer_id,
+            None,
+            timestamp_millis,
             topic,
-            message_size: message_size as u32,
-            // Additional attestation data fields
-            source_epoch: attestation.data.source.epoch.as_u64(),
-            source_root: format!("0x{}", hex::encode(attestation.data.source.root.0)),
-            target_epoch: attestation.data.target.epoch.as_u64(),
-            target_root: format!("0x{}", hex::encode(attestation.data.target.root.0)),
-            committee_index: attestation.committee_index,
-            // Aggregation and signature fields
-            // For single attestations, we don't have aggregation bits, so we'll use an empty string
-            aggregation_bits: String::from("0x"),
-            signature: format!("0x{}", hex::encode(attestation.signature.serialize())),
-            // Validator specific fields
-            attester_index: attestation.attester_index,
-        };
+            message_size,
+        );
+        let mut event = AttestationEvent::new(
+            meta,
+            &attestation,
+            subnet_id,
+            should_process,
+            &timing,
+            &self.context.forks,
+        );
+        event.attester_pubkey = event
+            .attester_index
+            .and_then(|index| self.context.validators.pubkey(index));
+        self.queue(event.into())
+    }
+
+    fn on_gossip_unaggregated_attestation<E: EthSpec>(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<Attestation<E>>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        crate::metrics::inc_events_ingested(&topic);
 
         debug!(
-            "Xatu FFI: Processing attestation event - slot: {}, subnet: {}, peer: {}",
-            slot, *subnet_id, peer_id
+            "Xatu FFI: Received gossip attestation with aggregation bits - slot: {}, subnet: {}, message_id: {:?}",
+            attestation.data().slot, *subnet_id, message_id
         );
 
-        if let Some(sender) = &self.event_sender {
-            if let Err(e) = sender.send(event) {
-                error!("Failed to queue attestation event: {:?}", e);
-            } else {
-                debug!(
-                    "Queued attestation event for slot {} subnet {}",
-                    slot, *subnet_id
-                );
-            }
+        let phase = self.phase();
+        if !phase.is_active() {
+            warn!("Xatu FFI: Exporter {}, skipping attestation", phase);
+            return ObserverResult::error(format!("Exporter {}", phase))
+                .with_kind(EventKind::Attestation);
         }
 
-        ObserverResult::Ok
+        let timing = match self.slot_timing() {
+            Ok(timing) => timing,
+            Err(result) => return result,
+        };
+
+        let meta = self.event_meta(
+            &message_id,
+            &peer_id,
+            None,
+            timestamp_millis,
+            topic,
+            message_size,
+        );
+        // The attester index isn't known without the committee, so there is no pubkey either
+        let event = AttestationEvent::from_attestation(
+            meta,
+            &attestation,
+            subnet_id,
+            should_process,
+            &timing,
+            &self.context.forks,
+        );
+        self.queue(event.into())
     }
 
     fn on_gossip_aggregate_and_proof<E: EthSpec>(
@@ -419,83 +836,37 @@ impl crate::observer_trait::XatuObserverTrait for XatuObserver {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        let attestation_data = aggregate.message().aggregate().data();
-        let beacon_block_root = attestation_data.beacon_block_root;
-        let aggregator_index = aggregate.message().aggregator_index();
+        crate::metrics::inc_events_ingested(&topic);
 
         debug!(
-            "Xatu FFI: Received gossip aggregate and proof - beacon_block_root: 0x{}, aggregator: {}, message_id: {:?}",
-            hex::encode(&beacon_block_root.0[..8]),
-            aggregator_index,
+            "Xatu FFI: Received gossip aggregate and proof - aggregator: {}, message_id: {:?}",
+            aggregate.message().aggregator_index(),
             message_id
         );
 
-        if !self.initialized.load(Ordering::Relaxed) {
-            warn!("Xatu FFI: Not initialized yet, skipping aggregate and proof");
-            return ObserverResult::Ok;
+        let phase = self.phase();
+        if !phase.is_active() {
+            warn!("Xatu FFI: Exporter {}, skipping aggregate and proof", phase);
+            return ObserverResult::error(format!("Exporter {}", phase))
+                .with_kind(EventKind::Aggregate);
         }
 
-        let slot = attestation_data.slot;
-        let slot_u64 = slot.as_u64();
-
-        // Get network info for epoch calculation
-        let network_info = match self.network_info.as_ref() {
-            Some(info) => info,
-            None => {
-                error!("Xatu FFI: Network info not available");
-                return ObserverResult::Error("Network info not available".to_string());
-            }
+        let timing = match self.slot_timing() {
+            Ok(timing) => timing,
+            Err(result) => return result,
         };
 
-        let epoch = slot_u64 / network_info.slots_per_epoch;
-
-        let event = EventData::AggregateAndProof {
-            peer_id: peer_id.to_string(),
-            slot: slot_u64,
-            epoch,
-            attestation_data_root: format!("0x{}", hex::encode(beacon_block_root.0)),
-            aggregator_index,
-            timestamp_ms: timestamp_millis as i64,
-            message_id: hex::encode(&message_id.0),
+        let meta = self.event_meta(
+            &message_id,
+            &peer_id,
+            None,
+            timestamp_millis,
             topic,
-            message_size: message_size as u32,
-            // Additional attestation data fields
-            source_epoch: attestation_data.source.epoch.as_u64(),
-            source_root: format!("0x{}", hex::encode(attestation_data.source.root.0)),
-            target_epoch: attestation_data.target.epoch.as_u64(),
-            target_root: format!("0x{}", hex::encode(attestation_data.target.root.0)),
-            // For Electra, get committee index from committee_bits; for pre-Electra use data.index
-            committee_index: aggregate
-                .message()
-                .aggregate()
-                .committee_index()
-                .unwrap_or(attestation_data.index),
-            // Aggregation and signature fields
-            aggregation_bits: match aggregate.message().aggregate() {
-                types::AttestationRef::Base(att) => {
-                    format!("0x{}", hex::encode(att.aggregation_bits.as_slice()))
-                }
-                types::AttestationRef::Electra(att) => {
-                    format!("0x{}", hex::encode(att.aggregation_bits.as_slice()))
-                }
-            },
-            signature: format!("0x{}", hex::encode(aggregate.signature().serialize())),
-        };
-
-        debug!(
-            "Xatu FFI: Processing aggregate and proof event - slot: {}, aggregator: {}, peer: {}",
-            slot, aggregator_index, peer_id
+            message_size,
         );
-
-        if let Some(sender) = &self.event_sender {
-            if let Err(e) = sender.send(event) {
-                error!("Failed to queue aggregate and proof event: {:?}", e);
-            } else {
-                debug!("Queued aggregate and proof event for slot {}", slot);
-            }
-        }
-
-        ObserverResult::Ok
+        let mut event = AggregateAndProofEvent::new(meta, &aggregate, &timing, &self.context.forks);
+        event.aggregator_pubkey = self.context.validators.pubkey(event.aggregator_index);
+        self.queue(event.into())
     }
 
     fn on_gossip_blob_sidecar<E: EthSpec>(
@@ -509,74 +880,42 @@ impl crate::observer_trait::XatuObserverTrait for XatuObserver {
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        let block_root = blob_sidecar.block_root();
-        let slot = blob_sidecar.slot();
+        crate::metrics::inc_events_ingested(&topic);
 
         debug!(
-            "Xatu FFI: Received gossip blob sidecar - slot: {}, index: {}, root: 0x{}, message_id: {:?}",
-            slot,
+            "Xatu FFI: Received gossip blob sidecar - slot: {}, index: {}, message_id: {:?}",
+            blob_sidecar.slot(),
             blob_index,
-            hex::encode(&block_root.0[..8]),
             message_id
         );
 
-        if !self.initialized.load(Ordering::Relaxed) {
-            warn!("Xatu FFI: Not initialized yet, skipping blob sidecar");
-            return ObserverResult::Ok;
+        let phase = self.phase();
+        if !phase.is_active() {
+            warn!("Xatu FFI: Exporter {}, skipping blob sidecar", phase);
+            return ObserverResult::error(format!("Exporter {}", phase))
+                .with_kind(EventKind::BlobSidecar);
         }
 
-        let slot_u64 = slot.as_u64();
-
-        // Get network info for epoch calculation
-        let network_info = match self.network_info.as_ref() {
-            Some(info) => info,
-            None => {
-                error!("Xatu FFI: Network info not available");
-                return ObserverResult::Error("Network info not available".to_string());
-            }
+        let timing = match self.slot_timing() {
+            Ok(timing) => timing,
+            Err(result) => return result,
         };
 
-        let epoch = slot_u64 / network_info.slots_per_epoch;
-
-        let event = EventData::BlobSidecar {
-            peer_id: peer_id.to_string(),
-            slot: slot_u64,
-            epoch,
-            block_root: format!("0x{}", hex::encode(block_root.0)),
-            parent_root: format!(
-                "0x{}",
-                hex::encode(blob_sidecar.signed_block_header.message.parent_root.0)
-            ),
-            state_root: format!(
-                "0x{}",
-                hex::encode(blob_sidecar.signed_block_header.message.state_root.0)
-            ),
-            proposer_index: blob_sidecar.block_proposer_index(),
-            blob_index,
-            timestamp_ms: timestamp_millis as i64,
-            message_id: hex::encode(&message_id.0),
+        let meta = self.event_meta(
+            &message_id,
+            &peer_id,
             client,
+            timestamp_millis,
             topic,
-            message_size: message_size as u32,
-        };
-
-        debug!(
-            "Xatu FFI: Processing blob sidecar event - slot: {}, index: {}, peer: {}",
-            slot, blob_index, peer_id
+            message_size,
         );
-
-        if let Some(sender) = &self.event_sender {
-            if let Err(e) = sender.send(event) {
-                error!("Failed to queue blob sidecar event: {:?}", e);
-            } else {
-                debug!(
-                    "Queued blob sidecar event for slot {} index {}",
-                    slot, blob_index
-                );
-            }
-        }
-
-        ObserverResult::Ok
+        self.queue(EventData::blob_sidecar(
+            meta,
+            blob_index,
+            &blob_sidecar,
+            &timing,
+            &self.context.forks,
+        ))
     }
 
     fn on_gossip_data_column_sidecar<E: EthSpec>(
@@ -584,93 +923,48 @@ impl crate::observer_trait::XatuObserverTrait for XatuObserver {
         message_id: MessageId,
         peer_id: PeerId,
         client: Option<String>,
-        _subnet_id: DataColumnSubnetId,
+        subnet_id: DataColumnSubnetId,
         column_sidecar: Arc<DataColumnSidecar<E>>,
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
     ) -> ObserverResult {
-        let block_root = column_sidecar.block_root();
-        let slot = column_sidecar.slot();
-        let column_index = *column_sidecar.index();
-        let kzg_commitments_count = column_sidecar
-            .kzg_commitments()
-            .map(|c| c.len() as u32)
-            .unwrap_or(0);
-
-        // Extract variant-specific fields (only available on Fulu)
-        let (parent_root, state_root, proposer_index) =
-            if let Ok(header) = column_sidecar.signed_block_header() {
-                (
-                    format!("0x{}", hex::encode(header.message.parent_root.0)),
-                    format!("0x{}", hex::encode(header.message.state_root.0)),
-                    header.message.proposer_index,
-                )
-            } else {
-                // Gloas variant: these fields are not available
-                (String::new(), String::new(), 0)
-            };
+        crate::metrics::inc_events_ingested(&topic);
 
         debug!(
-            "Xatu FFI: Received gossip data column sidecar - slot: {}, column_index: {}, root: 0x{}, message_id: {:?}",
-            slot,
-            column_index,
-            hex::encode(&block_root.0[..8]),
+            "Xatu FFI: Received gossip data column sidecar - slot: {}, column_index: {}, message_id: {:?}",
+            column_sidecar.slot(),
+            column_sidecar.index(),
             message_id
         );
 
-        if !self.initialized.load(Ordering::Relaxed) {
-            warn!("Xatu FFI: Not initialized yet, skipping data column sidecar");
-            return ObserverResult::Ok;
+        let phase = self.phase();
+        if !phase.is_active() {
+            warn!("Xatu FFI: Exporter {}, skipping data column sidecar", phase);
+            return ObserverResult::error(format!("Exporter {}", phase))
+                .with_kind(EventKind::DataColumnSidecar);
         }
 
-        let slot_u64 = slot.as_u64();
-
-        // Get network info for epoch calculation
-        let network_info = match self.network_info.as_ref() {
-            Some(info) => info,
-            None => {
-                error!("Xatu FFI: Network info not available");
-                return ObserverResult::Error("Network info not available".to_string());
-            }
+        let timing = match self.slot_timing() {
+            Ok(timing) => timing,
+            Err(result) => return result,
         };
 
-        let epoch = slot_u64 / network_info.slots_per_epoch;
-
-        let event = EventData::DataColumnSidecar {
-            peer_id: peer_id.to_string(),
-            slot: slot_u64,
-            epoch,
-            block_root: format!("0x{}", hex::encode(block_root.0)),
-            parent_root,
-            state_root,
-            proposer_index,
-            column_index,
-            kzg_commitments_count,
-            timestamp_ms: timestamp_millis as i64,
-            message_id: hex::encode(&message_id.0),
+        let meta = self.event_meta(
+            &message_id,
+            &peer_id,
             client,
+            timestamp_millis,
             topic,
-            message_size: message_size as u32,
-        };
-
-        debug!(
-            "Xatu FFI: Processing data column sidecar event - slot: {}, column_index: {}, peer: {}",
-            slot, column_index, peer_id
+            message_size,
         );
-
-        if let Some(sender) = &self.event_sender {
-            if let Err(e) = sender.send(event) {
-                error!("Failed to queue data column sidecar event: {:?}", e);
-            } else {
-                debug!(
-                    "Queued data column sidecar event for slot {} column_index {}",
-                    slot, column_index
-                );
-            }
-        }
-
-        ObserverResult::Ok
+        self.queue(EventData::data_column_sidecar(
+            meta,
+            &column_sidecar,
+            subnet_id,
+            &timing,
+            &self.context.forks,
+        ))
     }
 }
 
@@ -684,8 +978,8 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    ) {
-        let _ = <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_block::<E>(
+    ) -> ObserverResult {
+        <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_block::<E>(
             self,
             message_id,
             peer_id,
@@ -694,7 +988,7 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
             timestamp_millis,
             topic,
             message_size,
-        );
+        )
     }
 
     fn on_gossip_attestation(
@@ -707,8 +1001,8 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    ) {
-        let _ = <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_attestation::<E>(
+    ) -> ObserverResult {
+        <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_attestation::<E>(
             self,
             message_id,
             peer_id,
@@ -718,7 +1012,31 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
             timestamp_millis,
             topic,
             message_size,
-        );
+        )
+    }
+
+    fn on_gossip_unaggregated_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<Attestation<E>>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_unaggregated_attestation::<E>(
+            self,
+            message_id,
+            peer_id,
+            attestation,
+            subnet_id,
+            should_process,
+            timestamp_millis,
+            topic,
+            message_size,
+        )
     }
 
     fn on_gossip_aggregate_and_proof(
@@ -729,17 +1047,16 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    ) {
-        let _ =
-            <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_aggregate_and_proof::<E>(
-                self,
-                message_id,
-                peer_id,
-                aggregate,
-                timestamp_millis,
-                topic,
-                message_size,
-            );
+    ) -> ObserverResult {
+        <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_aggregate_and_proof::<E>(
+            self,
+            message_id,
+            peer_id,
+            aggregate,
+            timestamp_millis,
+            topic,
+            message_size,
+        )
     }
 
     fn on_gossip_blob_sidecar(
@@ -752,8 +1069,8 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    ) {
-        let _ = <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_blob_sidecar::<E>(
+    ) -> ObserverResult {
+        <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_blob_sidecar::<E>(
             self,
             message_id,
             peer_id,
@@ -763,7 +1080,7 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
             timestamp_millis,
             topic,
             message_size,
-        );
+        )
     }
 
     fn on_gossip_data_column_sidecar(
@@ -776,27 +1093,1087 @@ impl<E: EthSpec> crate::Xatu<E> for XatuObserver {
         timestamp_millis: u64,
         topic: String,
         message_size: usize,
-    ) {
-        let _ =
-            <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_data_column_sidecar::<E>(
-                self,
-                message_id,
-                peer_id,
-                client,
-                subnet_id,
-                column_sidecar,
-                timestamp_millis,
-                topic,
-                message_size,
+    ) -> ObserverResult {
+        <Self as crate::observer_trait::XatuObserverTrait>::on_gossip_data_column_sidecar::<E>(
+            self,
+            message_id,
+            peer_id,
+            client,
+            subnet_id,
+            column_sidecar,
+            timestamp_millis,
+            topic,
+            message_size,
+        )
+    }
+
+    fn on_peer_connected(&self, peer_id: &PeerId, details: &PeerDetails) -> ObserverResult {
+        self.peers.connected(*peer_id, details.clone());
+        ObserverResult::Ok
+    }
+
+    fn on_peer_disconnected(&self, peer_id: &PeerId) -> ObserverResult {
+        self.peers.disconnected(peer_id);
+        ObserverResult::Ok
+    }
+
+    fn on_start(&self, network_info: &crate::NetworkInfo) -> ObserverResult {
+        let mut results = vec![
+            self.queue(
+                SessionStartEvent::new(
+                    self.node_name.clone(),
+                    network_info,
+                    crate::clock::offset_ms(),
+                )
+                .into(),
+            ),
+            self.queue(
+                NodeInfoEvent::new(
+                    self.node_name.clone(),
+                    network_info,
+                    self.enabled_event_types.clone(),
+                    crate::clock::offset_ms(),
+                )
+                .into(),
+            ),
+        ];
+        if let Some(mismatch) = self.context.networks.mismatch() {
+            results.push(
+                self.queue(
+                    NetworkMismatchEvent::new(
+                        self.node_name.clone(),
+                        &mismatch,
+                        crate::clock::offset_ms(),
+                    )
+                    .into(),
+                ),
+            );
+        }
+        ObserverResult::combine(results)
+    }
+
+    fn on_custody_changed(
+        &self,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        let mut columns = custody_columns.to_vec();
+        columns.sort_unstable();
+        let previous = crate::node::custody_group_count();
+        if previous == Some(custody_group_count) && crate::node::custody_columns() == columns {
+            return ObserverResult::Ok;
+        }
+
+        let event = CustodyEvent::new(
+            self.node_name.clone(),
+            custody_group_count,
+            previous,
+            &columns,
+            crate::clock::offset_ms(),
+        );
+        // NODE_INFO events emitted from now on report the new count
+        crate::node::set_custody_group_count(custody_group_count);
+        crate::node::set_custody_columns(columns);
+        self.queue(event.into())
+    }
+
+    fn on_peer_custody(
+        &self,
+        peer_id: &PeerId,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        // Metadata is re-requested periodically, only changes are exported
+        let Some(previous) = self.peers.update_custody(*peer_id, custody_group_count) else {
+            return ObserverResult::Ok;
+        };
+        self.queue(
+            PeerCustodyEvent::new(
+                self.anonymizer.peer_id(peer_id),
+                self.peer_summary(peer_id),
+                custody_group_count,
+                previous,
+                custody_columns,
+                crate::clock::offset_ms(),
+            )
+            .into(),
+        )
+    }
+
+    fn on_gossip_message(
+        &self,
+        topic: &str,
+        _message_id: &MessageId,
+        peer_id: &PeerId,
+        raw_size: usize,
+        _decoded_kind: &GossipKind,
+    ) -> ObserverResult {
+        self.bandwidth.inbound(topic, peer_id, raw_size);
+        ObserverResult::Ok
+    }
+
+    fn on_gossip_sent(&self, topic: &str, peer_id: Option<&PeerId>, size: usize) -> ObserverResult {
+        self.bandwidth.outbound(topic, peer_id, size);
+        ObserverResult::Ok
+    }
+
+    fn on_peer_subnets(&self, peer_id: &PeerId, subnets: &PeerSubnets) -> ObserverResult {
+        // Exported in aggregate by the batch thread
+        self.peers.update_subnets(*peer_id, subnets.clone());
+        ObserverResult::Ok
+    }
+
+    fn on_genesis_time_changed(&self, genesis_time: u64) -> ObserverResult {
+        {
+            let mut network_info = self.network_info.write().unwrap_or_else(|e| e.into_inner());
+            let Some(network_info) = network_info.as_mut() else {
+                return ObserverResult::error("Network info not available");
+            };
+            if network_info.genesis_time == genesis_time {
+                return ObserverResult::Ok;
+            }
+            info!(
+                "Xatu genesis time corrected from {} to {}",
+                network_info.genesis_time, genesis_time
             );
+            network_info.genesis_time = genesis_time;
+            self.context
+                .set_timing(SlotTiming::from_network_info(network_info));
+        }
+
+        // The sidecar derives its own slot fields from the genesis time it was started with
+        if !self.owns_ffi || !self.phase().is_open() {
+            return ObserverResult::Ok;
+        }
+        match XatuFFI::set_genesis_time(genesis_time) {
+            Ok(()) => ObserverResult::Ok,
+            Err(e) => {
+                error!("Xatu FFI: Failed to update the sidecar genesis time: {}", e);
+                ObserverResult::error(format!("Genesis time update failed: {}", e))
+            }
+        }
+    }
+
+    fn on_network_changed(&self, network_info: &crate::NetworkInfo) -> ObserverResult {
+        let root = self.context.networks.genesis_validators_root();
+        let (previous, previous_root) = {
+            let mut current = self.network_info.write().unwrap_or_else(|e| e.into_inner());
+            let mut current_root = self
+                .genesis_validators_root
+                .write()
+                .unwrap_or_else(|e| e.into_inner());
+            let Some(previous) = current.clone() else {
+                return ObserverResult::error("Network info not available");
+            };
+            if *current_root == root && previous.network_id == network_info.network_id {
+                return ObserverResult::Ok;
+            }
+            *current = Some(network_info.clone());
+            let previous_root = std::mem::replace(&mut *current_root, root);
+            self.context
+                .set_timing(SlotTiming::from_network_info(network_info));
+            (previous, previous_root)
+        };
+        warn!(
+            "Xatu network changed from {} (id {}) to {} (id {})",
+            previous.network_name,
+            previous.network_id,
+            network_info.network_name,
+            network_info.network_id
+        );
+
+        let changed: EventData = NetworkChangedEvent::new(
+            self.node_name.clone(),
+            &previous,
+            previous_root.map(crate::networks::to_hex),
+            network_info,
+            root.map(crate::networks::to_hex),
+            crate::clock::offset_ms(),
+        )
+        .into();
+
+        // The sidecar was started for the old network, so the batch thread starts it over for
+        // the new one and sends the event through it, without holding up the caller
+        match &self.handle {
+            Some(handle) if self.owns_ffi && self.phase().accepts_events() => {
+                match handle.restart(network_info.clone(), changed) {
+                    Ok(()) => ObserverResult::Ok,
+                    Err(e) => {
+                        error!("Xatu FFI: Failed to restart for the new network: {}", e);
+                        ObserverResult::error(format!("Restart failed: {}", e))
+                    }
+                }
+            }
+            _ => self.queue(changed),
+        }
+    }
+
+    fn flush(&self) -> ObserverResult {
+        match XatuObserver::flush(self) {
+            Ok(()) => ObserverResult::Ok,
+            Err(e) => {
+                error!("Xatu FFI: Failed to flush: {}", e);
+                ObserverResult::error(format!("Flush failed: {}", e))
+            }
+        }
+    }
+
+    fn on_shutdown(&self) -> ObserverResult {
+        let Some(handle) = &self.handle else {
+            return ObserverResult::Ok;
+        };
+        match handle.shutdown(SHUTDOWN_TIMEOUT) {
+            Ok(()) => ObserverResult::Ok,
+            Err(e) => {
+                error!("Xatu FFI: Failed to shut down cleanly: {}", e);
+                ObserverResult::error(format!("Shutdown failed: {}", e))
+            }
+        }
     }
 }
 
 impl Drop for XatuObserver {
     fn drop(&mut self) {
-        if self.initialized.load(Ordering::Relaxed) {
+        if !self.owns_ffi || !self.phase().is_active() {
+            return;
+        }
+        // Normally `on_shutdown` or the handle already shut down; otherwise do it here, on the
+        // batch thread, so the final batches aren't cut off mid-send
+        let shut_down = self
+            .handle
+            .as_ref()
+            .is_some_and(|handle| handle.shutdown(SHUTDOWN_TIMEOUT).is_ok());
+        if !shut_down && self.phase().is_open() {
             info!("Xatu FFI: Closing forwarder");
             XatuFFI::close();
         }
     }
 }
+
+/// Register the callbacks, agree on a schema version and initialize the sidecar
+fn start_sidecar(
+    config_with_runtime: &crate::config::FullConfigWithRuntime,
+) -> Result<(), XatuError> {
+    // Sidecar logs go through tracing unless they have their own file
+    let log_to_file = config_with_runtime
+        .logging
+        .as_ref()
+        .is_some_and(|logging| logging.file.is_some());
+    if !log_to_file {
+        XatuFFI::set_log_callback()?;
+    }
+    XatuFFI::set_batch_callback()?;
+
+    // Refuse to start a sidecar that can't parse any schema we can write
+    XatuFFI::check_version()?;
+    XatuFFI::negotiate_capabilities()?;
+
+    // Tell the sidecar which schema the batches will arrive in
+    let config_with_runtime = crate::config::FullConfigWithRuntime {
+        schema_version: crate::schema::negotiated_version(),
+        ..config_with_runtime.clone()
+    };
+    debug!("Initializing Xatu FFI on dedicated thread...");
+    XatuFFI::init_with_runtime(&config_with_runtime)
+}
+
+/// Start the sidecar every `retry_interval` until it succeeds, holding up to `buffer_events`
+/// queued events meanwhile and dropping the newer ones
+/// A restart for another network meanwhile changes the config the next attempt starts with
+/// Returns the held events, or `None` if the exporter was closed or dropped first
+fn start_sidecar_lazily(
+    config_with_runtime: &mut crate::config::FullConfigWithRuntime,
+    state: &ExporterState,
+    event_receiver: &Receiver<QueuedEvent>,
+    control_receiver: &Receiver<Control>,
+    buffer_events: usize,
+    retry_interval: Duration,
+) -> Option<VecDeque<QueuedEvent>> {
+    let drop_event = |(_, bytes): &QueuedEvent| {
+        if let Some(budget) = &state.queue_budget {
+            budget.release(*bytes);
+        }
+        state.events_dropped.fetch_add(1, Ordering::Relaxed);
+    };
+    let mut buffered = VecDeque::new();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match start_sidecar(config_with_runtime) {
+            Ok(()) => {
+                info!(
+                    "Xatu FFI initialized after {} attempt(s), sending {} buffered events",
+                    attempts,
+                    buffered.len()
+                );
+                return Some(buffered);
+            }
+            Err(e) => warn!(
+                "Xatu FFI init attempt {} failed, retrying in {:?}: {}",
+                attempts, retry_interval, e
+            ),
+        }
+
+        let retry_at = Instant::now() + retry_interval;
+        while let Some(wait) = retry_at.checked_duration_since(Instant::now()) {
+            crossbeam_channel::select! {
+                recv(event_receiver) -> event => match event {
+                    Ok(event) if buffered.len() < buffer_events => buffered.push_back(event),
+                    Ok(event) => drop_event(&event),
+                    Err(_) => return None,
+                },
+                recv(control_receiver) -> control => match control {
+                    // Nothing can be delivered before the sidecar is up
+                    Ok(Control::Flush(ack)) => {
+                        let _ = ack.send(Err(XatuError::SinkUnavailable));
+                    }
+                    Ok(Control::Close(ack)) => {
+                        let queued = event_receiver.try_iter().collect::<Vec<_>>();
+                        let unsent = buffered.len() + queued.len();
+                        if unsent > 0 {
+                            warn!(
+                                "Dropping {} events buffered before the sidecar was up",
+                                unsent
+                            );
+                        }
+                        buffered.iter().chain(&queued).for_each(drop_event);
+                        state.phase.advance(ExporterPhase::Closed);
+                        let _ = ack.send(Ok(()));
+                        return None;
+                    }
+                    // Not up yet, so it can start for the new network right away
+                    Ok(Control::Restart(network_info, changed)) => {
+                        config_with_runtime.processor.ethereum.set_network(&network_info);
+                        if buffered.len() < buffer_events {
+                            buffered.push_back(*changed);
+                        } else {
+                            drop_event(&changed);
+                        }
+                    }
+                    Err(_) => {}
+                },
+                default(wait) => {}
+            }
+        }
+    }
+}
+
+/// Close the sidecar and start it again for another network, in the background with
+/// `lazyInit` like the first time
+/// Returns the events held during a lazy init, or `None` if the sidecar is not up again
+fn restart_sidecar(
+    config_with_runtime: &mut crate::config::FullConfigWithRuntime,
+    state: &ExporterState,
+    event_receiver: &Receiver<QueuedEvent>,
+    control_receiver: &Receiver<Control>,
+    lazy_init: Option<(usize, Duration)>,
+) -> Option<VecDeque<QueuedEvent>> {
+    info!(
+        "Xatu FFI: Restarting the sidecar for network {}",
+        config_with_runtime.processor.ethereum.network.name
+    );
+    XatuFFI::close();
+    state.phase.advance(ExporterPhase::Initializing);
+    match lazy_init {
+        Some((buffer_events, retry_interval)) => {
+            let buffered = start_sidecar_lazily(
+                config_with_runtime,
+                state,
+                event_receiver,
+                control_receiver,
+                buffer_events,
+                retry_interval,
+            )?;
+            state.phase.advance(ExporterPhase::Running);
+            Some(buffered)
+        }
+        None => {
+            let started = start_sidecar(config_with_runtime);
+            let next = match &started {
+                Ok(()) => ExporterPhase::Running,
+                Err(e) => {
+                    error!("Xatu FFI: Failed to restart the sidecar: {}", e);
+                    ExporterPhase::Closed
+                }
+            };
+            state.phase.advance(next);
+            started.is_ok().then(VecDeque::new)
+        }
+    }
+}
+
+/// Operational heartbeat of the batch thread
+fn log_summary(state: &ExporterState, queue_len: usize) {
+    let last_error = state
+        .last_error
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    info!(
+        events_queued = state.events_queued.load(Ordering::Relaxed),
+        events_sent = state.events_sent.load(Ordering::Relaxed),
+        events_dropped = state.events_dropped.load(Ordering::Relaxed),
+        batches_sent = state.batches_sent.load(Ordering::Relaxed),
+        send_errors = state.send_errors.load(Ordering::Relaxed),
+        batches_in_flight = state.batches_in_flight.load(Ordering::Relaxed),
+        queue_len,
+        last_error = last_error.as_deref().unwrap_or("none"),
+        "Xatu pipeline summary"
+    );
+}
+
+/// Configured node name with its placeholders expanded, `lighthouse` if unset
+fn configured_node_name(
+    full_config: &crate::config::FullConfig,
+    template_vars: &TemplateVars,
+) -> Result<String, XatuError> {
+    match &full_config.node {
+        Some(node) => template_vars.expand(&node.name),
+        None => Ok("lighthouse".to_string()),
+    }
+}
+
+/// Event types left enabled by the sampling config
+/// Malformed filter configs are rejected by the filter middleware, so they fall back to none
+fn enabled_event_types(full_config: &crate::config::FullConfig) -> Vec<String> {
+    // Slot bounds don't affect which kinds are enabled
+    crate::filter::EventFilter::from_config(full_config, 1)
+        .map(|filter| {
+            filter
+                .enabled_kinds()
+                .into_iter()
+                .map(|kind| kind.event_type().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A submitted batch and how often it has been tried
+struct InFlightBatch {
+    events: Vec<EventData>,
+    attempts: u32,
+    /// When the current attempt was submitted
+    submitted_at: Instant,
+}
+
+/// Events collected on the batch thread between sends
+struct Batcher {
+    events: Vec<EventData>,
+    state: Arc<ExporterState>,
+    /// Timing and the measurements summarized into the exporter's own events
+    context: Arc<XatuContext>,
+    /// Replace attestations with identical data by one `ATTESTATION_GROUP` event per batch
+    group_attestations: bool,
+    /// User transforms run on each batch before it is sent
+    transforms: Transforms,
+    /// Dequeued events more than this many slots behind the wallclock are dropped
+    max_event_age_slots: Option<u64>,
+    /// Serialized size at which the batch is sent early, `None` without a byte cap
+    max_batch_bytes: Option<usize>,
+    /// Serialized size of `events` counted per event, only with a byte cap
+    bytes: usize,
+    last_batch_time: Instant,
+    /// When the last non-empty batch was handed to the sink
+    last_flush: Option<Instant>,
+    consecutive_failures: u64,
+    /// Submitted batches waiting for their outcome, by batch id
+    in_flight: HashMap<u64, InFlightBatch>,
+    /// Failed batches waiting to be submitted again, with the time they are due
+    retries: Vec<(Instant, InFlightBatch)>,
+    rate_limiter: Option<RateLimiter>,
+    /// Batches waiting for the rate limit in submission order, with their serialized JSON
+    throttled: VecDeque<(String, InFlightBatch)>,
+    /// Gossip events held back until genesis, `None` once it passed
+    pre_genesis: Option<PreGenesis>,
+}
+
+impl Batcher {
+    fn new(
+        state: Arc<ExporterState>,
+        context: Arc<XatuContext>,
+        group_attestations: bool,
+        transforms: Transforms,
+        max_event_age_slots: Option<u64>,
+        max_batch_bytes: Option<usize>,
+        rate_limiter: Option<RateLimiter>,
+        pre_genesis: Option<PreGenesis>,
+    ) -> Self {
+        Self {
+            events: Vec::new(),
+            state,
+            context,
+            group_attestations,
+            transforms,
+            max_event_age_slots,
+            max_batch_bytes,
+            bytes: 0,
+            last_batch_time: Instant::now(),
+            last_flush: None,
+            consecutive_failures: 0,
+            in_flight: HashMap::new(),
+            retries: Vec::new(),
+            rate_limiter,
+            throttled: VecDeque::new(),
+            pre_genesis,
+        }
+    }
+
+    fn push(&mut self, event: EventData) {
+        self.push_sized(event, 0);
+    }
+
+    /// Add an event whose serialized size was already measured, 0 if it wasn't
+    /// Each event is measured at most once, when queued with a queue budget or here
+    fn push_sized(&mut self, event: EventData, bytes: u64) {
+        if self.max_batch_bytes.is_some() {
+            self.bytes += match bytes {
+                0 => event.json_len(),
+                bytes => usize::try_from(bytes).unwrap_or(usize::MAX),
+            };
+        }
+        self.events.push(event);
+    }
+
+    /// Add an event taken off the queue, unless it has gone stale while waiting or arrived
+    /// before genesis
+    fn receive(&mut self, (event, bytes): QueuedEvent) {
+        self.release_queued(bytes);
+        if self.is_expired(&event) {
+            self.state.events_dropped.fetch_add(1, Ordering::Relaxed);
+            crate::metrics::inc_events_expired(event.kind());
+            return;
+        }
+        let kind = event.kind();
+        let timing = self.context.timing();
+        let event = match &mut self.pre_genesis {
+            Some(pre_genesis) => match pre_genesis.hold(event, timing) {
+                Hold::Pass(event) => event,
+                Hold::Buffered => return,
+                Hold::Dropped => {
+                    self.state.events_dropped.fetch_add(1, Ordering::Relaxed);
+                    crate::metrics::inc_events_dropped_pre_genesis(kind);
+                    return;
+                }
+            },
+            None => event,
+        };
+        self.push_sized(event, bytes);
+    }
+
+    /// Give back the queue budget an event reserved while it waited
+    fn release_queued(&self, bytes: u64) {
+        if let Some(budget) = &self.state.queue_budget {
+            budget.release(bytes);
+        }
+    }
+
+    fn is_expired(&self, event: &EventData) -> bool {
+        let (Some(max_age_slots), Some(slot)) = (self.max_event_age_slots, event.slot()) else {
+            return false;
+        };
+        // Without the genesis time there is no wallclock slot to compare against
+        let Some(timing) = self.context.timing() else {
+            return false;
+        };
+        let wallclock_slot = timing.wallclock_slot(crate::clock::corrected_now_ms());
+        wallclock_slot.saturating_sub(slot) > max_age_slots
+    }
+
+    /// Add an `EXPORTER_STATS` event with the current counters, if the sidecar supports it
+    fn push_stats(&mut self, queue_len: usize) {
+        if !crate::ffi::supports_event_type(EventKind::ExporterStats.event_type()) {
+            return;
+        }
+        let stats = self.state.snapshot(queue_len);
+        self.push(
+            ExporterStatsEvent::new(&stats, self.consecutive_failures, crate::clock::offset_ms())
+                .into(),
+        );
+    }
+
+    fn push_subnet_coverage(&mut self, peers: &PeerCache) {
+        if !crate::ffi::supports_event_type(EventKind::SubnetCoverage.event_type()) {
+            return;
+        }
+        let Some(coverage) = peers.subnet_coverage(self.context.forks.subnet_counts()) else {
+            return;
+        };
+        self.push(SubnetCoverageEvent::new(coverage, crate::clock::offset_ms()).into());
+    }
+
+    fn push_bandwidth(&mut self, bandwidth: &BandwidthTracker, anonymizer: &PeerAnonymizer) {
+        if !crate::ffi::supports_event_type(EventKind::Bandwidth.event_type()) {
+            return;
+        }
+        let Some(summary) = bandwidth.take(anonymizer) else {
+            return;
+        };
+        self.push(BandwidthEvent::new(summary, crate::clock::offset_ms()).into());
+    }
+
+    fn push_latency(&mut self) {
+        let completed = self.context.latency.take_completed();
+        if completed.is_empty()
+            || !crate::ffi::supports_event_type(EventKind::PropagationLatency.event_type())
+        {
+            return;
+        }
+        for latency in completed {
+            self.push(PropagationLatencyEvent::new(latency, crate::clock::offset_ms()).into());
+        }
+    }
+
+    fn push_slot_aggregates(&mut self) {
+        let completed = self.context.slot_aggregates.take_completed();
+        if completed.is_empty()
+            || !crate::ffi::supports_event_type(EventKind::SlotAggregate.event_type())
+        {
+            return;
+        }
+        let Some(timing) = self.context.timing() else {
+            return;
+        };
+        for aggregate in completed {
+            self.push(
+                SlotAggregateEvent::new(aggregate, &timing, crate::clock::offset_ms()).into(),
+            );
+        }
+    }
+
+    fn push_failovers(&mut self) {
+        let failovers = crate::ffi::take_failovers();
+        if failovers.is_empty()
+            || !crate::ffi::supports_event_type(EventKind::OutputFailover.event_type())
+        {
+            return;
+        }
+        for failover in failovers {
+            self.push(failover.into());
+        }
+    }
+
+    /// Once genesis passes, add the `GENESIS` marker followed by the events held back
+    fn push_genesis(&mut self) {
+        let timing = self.context.timing();
+        if !self
+            .pre_genesis
+            .as_ref()
+            .is_some_and(|pre_genesis| pre_genesis.is_over(timing))
+        {
+            return;
+        }
+        let Some(pre_genesis) = self.pre_genesis.take() else {
+            return;
+        };
+        let (marker, buffered) = pre_genesis.finish(timing);
+        info!(
+            "Genesis reached, exporting {} gossip events held back before it ({} dropped)",
+            marker.events_buffered, marker.events_dropped
+        );
+        if crate::ffi::supports_event_type(EventKind::Genesis.event_type()) {
+            self.push(marker.into());
+        }
+        for event in buffered {
+            self.push(event);
+            if self.len() >= MAX_BATCH_SIZE || self.is_over_byte_budget() {
+                let _ = self.send("genesis");
+            }
+        }
+    }
+
+    /// Add a `CLOCK_SKEW` event if the clock looks skewed, warning either way
+    fn push_clock_skew(&mut self, threshold_ms: u64) {
+        let Some(skew) = self.context.skew.check(self.context.timing(), threshold_ms) else {
+            return;
+        };
+        warn!(
+            ntp_offset_ms = ?skew.ntp_offset_ms,
+            peer_implied_skew_ms = ?skew.peer_implied_skew_ms,
+            threshold_ms,
+            "Xatu clock skew exceeds threshold, propagation timings are unreliable"
+        );
+        if crate::ffi::supports_event_type(EventKind::ClockSkew.event_type()) {
+            self.push(ClockSkewEvent::new(skew).into());
+        }
+    }
+
+    /// Move everything already queued into the batch
+    fn drain(&mut self, receiver: &Receiver<QueuedEvent>) {
+        for event in receiver.try_iter() {
+            self.receive(event);
+        }
+    }
+
+    /// Drop everything not yet delivered, when closing without a final flush
+    fn discard(&mut self, receiver: &Receiver<QueuedEvent>, reason: &str) {
+        let queued = receiver
+            .try_iter()
+            .inspect(|(_, bytes)| self.release_queued(*bytes))
+            .count();
+        let unsent = queued
+            + self.events.len()
+            + self
+                .in_flight
+                .values()
+                .chain(self.retries.iter().map(|(_, batch)| batch))
+                .chain(self.throttled.iter().map(|(_, batch)| batch))
+                .map(|batch| batch.events.len())
+                .sum::<usize>()
+            + self
+                .pre_genesis
+                .as_ref()
+                .map_or(0, PreGenesis::buffered_len);
+        if unsent > 0 {
+            warn!("Dropping {} undelivered events on {}", unsent, reason);
+            self.state
+                .events_dropped
+                .fetch_add(unsent as u64, Ordering::Relaxed);
+        }
+        self.events.clear();
+        self.bytes = 0;
+        self.in_flight.clear();
+        self.retries.clear();
+        self.throttled.clear();
+        self.pre_genesis = None;
+        self.update_in_flight();
+    }
+
+    /// Start over for another network once its old batches were flushed, dropping those that
+    /// still failed, and hold gossip back again if the new network is before genesis
+    fn restart(&mut self, pre_genesis: Option<PreGenesis>) {
+        self.discard(&never(), "network change");
+        self.pre_genesis = pre_genesis;
+    }
+
+    fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Whether the events collected so far reach the byte cap
+    fn is_over_byte_budget(&self) -> bool {
+        self.max_batch_bytes
+            .is_some_and(|max_batch_bytes| self.bytes >= max_batch_bytes)
+    }
+
+    /// Whether batches are waiting for the rate limit
+    fn is_throttled(&self) -> bool {
+        !self.throttled.is_empty()
+    }
+
+    /// Take an event off the queue, sending the batch right away once it is full
+    fn accept(&mut self, event: QueuedEvent) {
+        self.receive(event);
+        if self.len() >= MAX_BATCH_SIZE {
+            debug!(
+                "Batch size limit reached ({} events), sending immediately",
+                MAX_BATCH_SIZE
+            );
+            let _ = self.send("size limit");
+        } else if self.is_over_byte_budget() {
+            debug!(
+                "Batch byte cap reached ({} bytes), sending immediately",
+                self.bytes
+            );
+            let _ = self.send("byte limit");
+        }
+    }
+
+    /// Check if the 1 second batch interval has elapsed
+    fn is_due(&self) -> bool {
+        self.last_batch_time.elapsed() >= Duration::from_secs(1)
+    }
+
+    /// Transform and submit the batch, `reason` is only used for logging
+    /// Fails only if the batch was dropped, a batch waiting for a retry counts as sent
+    fn send(&mut self, reason: &str) -> Result<(), XatuError> {
+        let span = debug_span!(
+            "xatu_batch",
+            reason,
+            queued = self.events.len(),
+            events = field::Empty
+        )
+        .entered();
+        let mut batch = self.transforms.apply(std::mem::take(&mut self.events));
+        self.bytes = 0;
+        if self.group_attestations {
+            batch = crate::attestation_groups::group_attestations(batch);
+        }
+        self.last_batch_time = Instant::now();
+        span.record("events", batch.len());
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        crate::metrics::observe_batch(
+            batch.len(),
+            self.last_flush.map(|last| now.duration_since(last)),
+        );
+        self.last_flush = Some(now);
+        crate::tap::publish(&batch);
+
+        self.submit(
+            InFlightBatch {
+                events: batch,
+                attempts: 0,
+                submitted_at: now,
+            },
+            reason,
+        )
+    }
+
+    /// Submit a batch once the rate limit allows, behind any batch already waiting for it
+    /// The batch is serialized once here, and its size is what the rate limit counts
+    fn submit(&mut self, batch: InFlightBatch, reason: &str) -> Result<(), XatuError> {
+        let json_data = match crate::schema::serialize_batch(&batch.events) {
+            Ok(json_data) => json_data,
+            // Serializing the same events again would only fail again
+            Err(e) => {
+                error!(
+                    "Dropping batch of {} events ({}), it failed to serialize: {}",
+                    batch.events.len(),
+                    reason,
+                    e
+                );
+                crate::metrics::inc_batches_unserializable();
+                self.state
+                    .events_dropped
+                    .fetch_add(batch.events.len() as u64, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+        if self.is_throttled() || !self.acquire(batch.events.len(), json_data.len()) {
+            if self.throttled.len() >= MAX_THROTTLED_BATCHES {
+                warn!(
+                    "Dropping batch of {} events ({}), {} batches already wait for the rate limit",
+                    batch.events.len(),
+                    reason,
+                    self.throttled.len()
+                );
+                crate::metrics::inc_batches_dropped_rate_limited();
+                self.state
+                    .events_dropped
+                    .fetch_add(batch.events.len() as u64, Ordering::Relaxed);
+                return Err(XatuError::QueueFull);
+            }
+            debug!(
+                "Holding back batch of {} events ({}) for the rate limit",
+                batch.events.len(),
+                reason
+            );
+            crate::metrics::inc_batches_rate_limited();
+            self.throttled.push_back((json_data, batch));
+            self.update_in_flight();
+            return Ok(());
+        }
+        self.submit_now(batch, json_data, reason)
+    }
+
+    /// Submit batches waiting for the rate limit, oldest first, as far as it allows
+    fn release_throttled(&mut self) {
+        while let Some((json_data, batch)) = self.throttled.front() {
+            if !self.acquire(batch.events.len(), json_data.len()) {
+                break;
+            }
+            if let Some((json_data, batch)) = self.throttled.pop_front() {
+                let _ = self.submit_now(batch, json_data, "rate limit");
+            }
+        }
+    }
+
+    fn acquire(&mut self, events: usize, bytes: usize) -> bool {
+        match &mut self.rate_limiter {
+            Some(limiter) => limiter.try_acquire(events, bytes),
+            None => true,
+        }
+    }
+
+    /// Submit a serialized batch under a fresh id, so late outcomes of earlier attempts are
+    /// ignored
+    fn submit_now(
+        &mut self,
+        mut batch: InFlightBatch,
+        json_data: String,
+        reason: &str,
+    ) -> Result<(), XatuError> {
+        let batch_id = crate::acks::next_batch_id();
+        batch.attempts += 1;
+        batch.submitted_at = Instant::now();
+        let _span = debug_span!(
+            "xatu_submit",
+            batch_id,
+            events = batch.events.len(),
+            attempt = batch.attempts
+        )
+        .entered();
+        match XatuFFI::submit_event_batch(batch_id, batch.events.len(), json_data) {
+            Ok(()) => {
+                debug!(
+                    "Submitted batch {} of {} events ({}, attempt {})",
+                    batch_id,
+                    batch.events.len(),
+                    reason,
+                    batch.attempts
+                );
+                self.in_flight.insert(batch_id, batch);
+                self.update_in_flight();
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to submit event batch ({}): {}", reason, e);
+                self.failed(batch, e)
+            }
+        }
+    }
+
+    /// Record the outcome of a submitted batch
+    /// Fails only if the batch was dropped after its last attempt
+    fn handle_ack(&mut self, ack: BatchAck) -> Result<(), XatuError> {
+        // Unknown ids belong to an earlier observer in this process
+        let Some(batch) = self.in_flight.remove(&ack.batch_id) else {
+            return Ok(());
+        };
+        match ack.result {
+            Ok(()) => {
+                self.delivered(batch);
+                self.update_in_flight();
+                Ok(())
+            }
+            Err(e) => {
+                error!("Event batch {} failed: {}", ack.batch_id, e);
+                self.failed(batch, e)
+            }
+        }
+    }
+
+    /// Fail submitted batches whose outcome never came, so they are retried or dropped
+    /// A late outcome for the expired id is ignored
+    fn expire_unacked(&mut self) {
+        let expired: Vec<u64> = self
+            .in_flight
+            .iter()
+            .filter(|(_, batch)| batch.submitted_at.elapsed() >= ACK_DEADLINE)
+            .map(|(batch_id, _)| *batch_id)
+            .collect();
+        for batch_id in expired {
+            if let Some(batch) = self.in_flight.remove(&batch_id) {
+                error!(
+                    "Event batch {} got no outcome within {:?}",
+                    batch_id, ACK_DEADLINE
+                );
+                let _ = self.failed(
+                    batch,
+                    XatuError::Timeout(format!("batch {} was not acknowledged", batch_id)),
+                );
+            }
+        }
+    }
+
+    /// Submit failed batches whose retry delay has passed
+    fn retry_due(&mut self) {
+        if self.retries.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.retries)
+            .into_iter()
+            .partition(|(due_at, _)| *due_at <= now);
+        self.retries = waiting;
+        for (_, batch) in due {
+            self.state.batches_retried.fetch_add(1, Ordering::Relaxed);
+            let _ = self.submit(batch, "retry");
+        }
+    }
+
+    /// Process outcomes and retries until nothing is in flight or `timeout` passes
+    /// Fails if a batch was dropped meanwhile, or if batches are still in flight
+    fn wait_for_acks(
+        &mut self,
+        batch_acks: &Receiver<BatchAck>,
+        timeout: Duration,
+    ) -> Result<(), XatuError> {
+        let deadline = Instant::now() + timeout;
+        let mut result = Ok(());
+        while !self.in_flight.is_empty() || !self.retries.is_empty() || self.is_throttled() {
+            let now = Instant::now();
+            if now >= deadline {
+                return result.and(Err(XatuError::Timeout(format!(
+                    "{} batches still in flight",
+                    self.in_flight.len() + self.retries.len() + self.throttled.len()
+                ))));
+            }
+            self.expire_unacked();
+            self.retry_due();
+            self.release_throttled();
+            match batch_acks.recv_timeout((deadline - now).min(RETRY_DELAY)) {
+                Ok(batch_ack) => {
+                    if let Err(e) = self.handle_ack(batch_ack) {
+                        result = Err(e);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        result
+    }
+
+    fn delivered(&mut self, batch: InFlightBatch) {
+        self.state
+            .events_sent
+            .fetch_add(batch.events.len() as u64, Ordering::Relaxed);
+        self.state.batches_sent.fetch_add(1, Ordering::Relaxed);
+        if self.state.phase.advance(ExporterPhase::Running) {
+            info!("Xatu exporter recovered, sends are succeeding again");
+        }
+        crate::metrics::SentCounts::from_batch(&batch.events).record();
+        self.consecutive_failures = 0;
+        crate::metrics::set_consecutive_send_failures(0);
+        crate::metrics::set_last_successful_send_now();
+    }
+
+    /// Record a failed attempt and schedule a retry, or drop the batch after its last attempt
+    fn failed(&mut self, batch: InFlightBatch, error: XatuError) -> Result<(), XatuError> {
+        self.state.send_errors.fetch_add(1, Ordering::Relaxed);
+        if self.state.phase.advance(ExporterPhase::Degraded) {
+            warn!("Xatu exporter degraded, a send failed: {}", error);
+        }
+        *self
+            .state
+            .last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(error.to_string());
+        self.consecutive_failures += 1;
+        crate::metrics::inc_send_errors(&error);
+        crate::metrics::set_consecutive_send_failures(self.consecutive_failures);
+
+        let result = if batch.attempts < MAX_BATCH_ATTEMPTS {
+            self.retries.push((Instant::now() + RETRY_DELAY, batch));
+            Ok(())
+        } else {
+            error!(
+                "Dropping batch of {} events after {} failed attempts",
+                batch.events.len(),
+                batch.attempts
+            );
+            self.state
+                .events_dropped
+                .fetch_add(batch.events.len() as u64, Ordering::Relaxed);
+            Err(error)
+        };
+        self.update_in_flight();
+        result
+    }
+
+    /// Publish the number of batches not yet delivered or dropped
+    fn update_in_flight(&self) {
+        let count = self.in_flight.len() + self.retries.len() + self.throttled.len();
+        self.state
+            .batches_in_flight
+            .store(count as u64, Ordering::Relaxed);
+        crate::metrics::set_batches_in_flight(count);
+    }
+}
@@ -30,6 +30,20 @@ pub(crate) trait XatuObserverTrait: Send + Sync {
         ObserverResult::Ok
     }
 
+    fn on_gossip_unaggregated_attestation<E: types::EthSpec>(
+        &self,
+        _message_id: MessageId,
+        _peer_id: libp2p::PeerId,
+        _attestation: std::sync::Arc<types::Attestation<E>>,
+        _subnet_id: types::SubnetId,
+        _should_process: bool,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
     fn on_gossip_aggregate_and_proof<E: types::EthSpec>(
         &self,
         _message_id: MessageId,
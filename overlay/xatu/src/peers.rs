@@ -0,0 +1,260 @@
+//! Connected peer cache used to enrich events with peer details
+//!
+//! Lighthouse reports connections through `Xatu::on_peer_connected` and
+//! `Xatu::on_peer_disconnected`; every event from a cached peer then carries a compact
+//! `peer` object instead of only the bare peer id.
+
+use crate::forks::SubnetCounts;
+use libp2p::PeerId;
+use lighthouse_network::{Eth2Enr, PeerInfo};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Instant;
+use types::EthSpec;
+
+/// Which side opened the connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionDirection {
+    Inbound,
+    Outbound,
+}
+
+/// Connection details reported when a peer connects
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PeerDetails {
+    /// Raw libp2p agent string
+    pub agent: Option<String>,
+    pub direction: Option<ConnectionDirection>,
+    /// Remote multiaddr of the connection
+    pub remote_addr: Option<String>,
+    /// Protocols announced by the peer
+    pub protocols: Vec<String>,
+}
+
+impl PeerDetails {
+    /// Details of a connected peer as recorded in Lighthouse's peer db
+    pub fn from_peer_info<E: EthSpec>(info: &PeerInfo<E>) -> Self {
+        Self {
+            agent: info.client().agent_string.clone(),
+            direction: info
+                .connection_direction()
+                .map(|direction| match direction {
+                    lighthouse_network::ConnectionDirection::Incoming => {
+                        ConnectionDirection::Inbound
+                    }
+                    lighthouse_network::ConnectionDirection::Outgoing => {
+                        ConnectionDirection::Outbound
+                    }
+                }),
+            remote_addr: info.seen_multiaddrs().next().map(ToString::to_string),
+            // The peer db doesn't keep the identify protocols
+            protocols: Vec::new(),
+        }
+    }
+}
+
+/// Gossip subnets a peer advertises in its metadata or ENR
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PeerSubnets {
+    pub attestation: Vec<u64>,
+    pub sync_committee: Vec<u64>,
+    /// Data column subnets derived from the peer's custody
+    pub data_column: Vec<u64>,
+}
+
+impl PeerSubnets {
+    /// Subnets of a peer from its metadata in Lighthouse's peer db, else from its ENR
+    /// `None` if neither is known yet
+    pub fn from_peer_info<E: EthSpec>(info: &PeerInfo<E>) -> Option<Self> {
+        let (attestation, sync_committee) = match (info.meta_data(), info.enr()) {
+            (Some(meta_data), _) => (
+                set_bits(meta_data.attnets().iter()),
+                meta_data
+                    .syncnets()
+                    .map(|syncnets| set_bits(syncnets.iter()))
+                    .unwrap_or_default(),
+            ),
+            (None, Some(enr)) => (
+                enr.attestation_bitfield::<E>()
+                    .map(|attnets| set_bits(attnets.iter()))
+                    .unwrap_or_default(),
+                enr.sync_committee_bitfield::<E>()
+                    .map(|syncnets| set_bits(syncnets.iter()))
+                    .unwrap_or_default(),
+            ),
+            (None, None) => return None,
+        };
+        let mut data_column: Vec<u64> =
+            info.custody_subnets_iter().map(|subnet| **subnet).collect();
+        data_column.sort_unstable();
+        Some(Self {
+            attestation,
+            sync_committee,
+            data_column,
+        })
+    }
+}
+
+/// Indices of the set bits of a subnet bitfield
+fn set_bits(bits: impl Iterator<Item = bool>) -> Vec<u64> {
+    bits.enumerate()
+        .filter(|(_, set)| *set)
+        .map(|(index, _)| index as u64)
+        .collect()
+}
+
+/// Number of peers advertising each subnet, indexed by subnet id
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SubnetCoverage {
+    /// Peers that advertised their subnets
+    pub peers: u64,
+    pub attestation: Vec<u64>,
+    pub sync_committee: Vec<u64>,
+    pub data_column: Vec<u64>,
+}
+
+/// Compact peer details attached to exported events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PeerSummary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<ConnectionDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_addr: Option<String>,
+    /// Time since the connection was established
+    pub connection_age_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocols: Vec<String>,
+}
+
+#[derive(Debug)]
+struct CachedPeer {
+    details: PeerDetails,
+    connected_at: Instant,
+}
+
+/// Details of currently connected peers
+#[derive(Debug, Default)]
+pub struct PeerCache {
+    peers: RwLock<HashMap<PeerId, CachedPeer>>,
+    /// Advertised custody group counts, kept apart as metadata can arrive before the
+    /// connection is reported
+    custody: RwLock<HashMap<PeerId, u64>>,
+    subnets: RwLock<HashMap<PeerId, PeerSubnets>>,
+}
+
+impl PeerCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new connection, replacing any previous details for the peer
+    pub fn connected(&self, peer_id: PeerId, details: PeerDetails) {
+        self.write().insert(
+            peer_id,
+            CachedPeer {
+                details,
+                connected_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Forget a disconnected peer
+    pub fn disconnected(&self, peer_id: &PeerId) {
+        self.write().remove(peer_id);
+        self.custody
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(peer_id);
+        self.subnets
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(peer_id);
+    }
+
+    /// Record the subnets a peer advertises, replacing the previous ones
+    pub fn update_subnets(&self, peer_id: PeerId, subnets: PeerSubnets) {
+        self.subnets
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(peer_id, subnets);
+    }
+
+    /// Peers per subnet, `None` if no peer advertised its subnets
+    /// Counts cover at least `counts` subnets, so uncovered subnets show up as zero
+    pub fn subnet_coverage(&self, counts: Option<SubnetCounts>) -> Option<SubnetCoverage> {
+        let subnets = self.subnets.read().unwrap_or_else(|e| e.into_inner());
+        if subnets.is_empty() {
+            return None;
+        }
+        let mut coverage = SubnetCoverage {
+            peers: subnets.len() as u64,
+            ..Default::default()
+        };
+        if let Some(counts) = counts {
+            coverage.attestation.resize(counts.attestation as usize, 0);
+            coverage
+                .sync_committee
+                .resize(counts.sync_committee as usize, 0);
+            coverage.data_column.resize(counts.data_column as usize, 0);
+        }
+        for peer in subnets.values() {
+            count_subnets(&mut coverage.attestation, &peer.attestation);
+            count_subnets(&mut coverage.sync_committee, &peer.sync_committee);
+            count_subnets(&mut coverage.data_column, &peer.data_column);
+        }
+        Some(coverage)
+    }
+
+    /// Record the custody group count a peer advertises, returning the previous one
+    /// `None` as the outer value means the count didn't change
+    pub fn update_custody(&self, peer_id: PeerId, custody_group_count: u64) -> Option<Option<u64>> {
+        let previous = self
+            .custody
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(peer_id, custody_group_count);
+        (previous != Some(custody_group_count)).then_some(previous)
+    }
+
+    /// Summary of a connected peer, `None` if it isn't cached
+    pub fn summary(&self, peer_id: &PeerId) -> Option<PeerSummary> {
+        let peers = self.peers.read().unwrap_or_else(|e| e.into_inner());
+        let peer = peers.get(peer_id)?;
+        Some(PeerSummary {
+            agent: peer.details.agent.clone(),
+            direction: peer.details.direction,
+            remote_addr: peer.details.remote_addr.clone(),
+            connection_age_ms: peer.connected_at.elapsed().as_millis() as u64,
+            protocols: peer.details.protocols.clone(),
+        })
+    }
+
+    /// Number of cached peers
+    pub fn len(&self) -> usize {
+        self.peers.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Check if no peers are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<PeerId, CachedPeer>> {
+        self.peers.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn count_subnets(coverage: &mut Vec<u64>, subnets: &[u64]) {
+    for &subnet in subnets {
+        let index = subnet as usize;
+        if index >= coverage.len() {
+            coverage.resize(index + 1, 0);
+        }
+        coverage[index] += 1;
+    }
+}
@@ -0,0 +1,57 @@
+//! Peer id anonymization for operators who don't want to expose peer identities
+
+use crate::config::FullConfig;
+use libp2p::PeerId;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Renders peer ids for export, optionally replacing them with a salted hash
+#[derive(Debug, Clone, Default)]
+pub struct PeerAnonymizer {
+    salt: Option<Vec<u8>>,
+}
+
+impl PeerAnonymizer {
+    /// Build the anonymizer from the full config
+    pub fn from_config(config: &FullConfig) -> Self {
+        let salt = config
+            .settings
+            .privacy
+            .as_ref()
+            .filter(|privacy| privacy.anonymize_peer_ids)
+            .map(|privacy| match &privacy.salt {
+                Some(salt) => salt.as_bytes().to_vec(),
+                None => random_salt(),
+            });
+
+        Self { salt }
+    }
+
+    /// Check if peer ids are being anonymized
+    pub fn is_enabled(&self) -> bool {
+        self.salt.is_some()
+    }
+
+    /// Get the peer id string to export
+    pub fn peer_id(&self, peer_id: &PeerId) -> String {
+        match &self.salt {
+            Some(salt) => {
+                let mut hasher = Sha256::new();
+                hasher.update(salt);
+                hasher.update(peer_id.to_bytes());
+                format!("0x{}", hex::encode(hasher.finalize()))
+            }
+            None => peer_id.to_string(),
+        }
+    }
+}
+
+fn random_salt() -> Vec<u8> {
+    // RandomState is seeded from the OS RNG, which is enough for a per-run salt
+    let mut salt = Vec::with_capacity(32);
+    for _ in 0..4 {
+        salt.extend_from_slice(&RandomState::new().build_hasher().finish().to_be_bytes());
+    }
+    salt
+}
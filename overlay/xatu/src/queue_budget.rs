@@ -0,0 +1,83 @@
+//! Memory budget of the event queue
+//!
+//! With `maxQueueBytes` set, the observer adds the approximate size of every event it queues,
+//! its serialized length, and the batch thread subtracts the amount queued with the event
+//! when taking it off, so each event is measured once. The
+//! queue can't be reordered, so lower priority events are refused at a lower fill level
+//! instead: attestations once half the budget is used, the other gossip events at three
+//! quarters, and blocks and the exporter's own events only at the full budget. During a sink
+//! outage the queue fills up with the most valuable events first, and memory stays bounded
+//! however large the individual events are.
+
+use crate::events::EventData;
+use crate::filter::EventKind;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// An event on the queue with the bytes it reserved, 0 without a budget
+pub(crate) type QueuedEvent = (EventData, u64);
+
+#[derive(Debug)]
+pub(crate) struct QueueBudget {
+    max_bytes: u64,
+    queued_bytes: AtomicU64,
+}
+
+impl QueueBudget {
+    pub(crate) fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            queued_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Reserve room for an event of `bytes` bytes, false if its kind's share is used up
+    pub(crate) fn try_reserve(&self, kind: EventKind, bytes: u64) -> bool {
+        let limit = self.max_bytes.saturating_mul(share_percent(kind)) / 100;
+        let fits = |queued: u64| queued.checked_add(bytes).filter(|next| *next <= limit);
+        let reserved = self
+            .queued_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, fits);
+        match reserved {
+            Ok(queued) => {
+                crate::metrics::set_queue_bytes(queued + bytes);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Give back the room of an event taken off the queue
+    pub(crate) fn release(&self, bytes: u64) {
+        let queued = self.queued_bytes.fetch_sub(bytes, Ordering::Relaxed);
+        crate::metrics::set_queue_bytes(queued.saturating_sub(bytes));
+    }
+
+    pub(crate) fn queued_bytes(&self) -> u64 {
+        self.queued_bytes.load(Ordering::Relaxed)
+    }
+}
+
+/// Percentage of the budget that may be in use when an event of this kind is queued
+fn share_percent(kind: EventKind) -> u64 {
+    match kind {
+        // By far the most numerous, and each one matters least
+        EventKind::Attestation => 50,
+        EventKind::Aggregate | EventKind::BlobSidecar | EventKind::DataColumnSidecar => 75,
+        EventKind::Block
+        | EventKind::SessionStart
+        | EventKind::NodeInfo
+        | EventKind::ExporterStats
+        | EventKind::NetworkMismatch
+        | EventKind::Custody
+        | EventKind::PeerCustody
+        | EventKind::SubnetCoverage
+        | EventKind::Bandwidth
+        | EventKind::PropagationLatency
+        | EventKind::SlotAggregate
+        | EventKind::AttestationGroup
+        | EventKind::ClockSkew
+        | EventKind::OutputFailover
+        | EventKind::Genesis
+        | EventKind::NetworkChanged => 100,
+    }
+}
@@ -0,0 +1,106 @@
+//! Outbound rate limiting toward the sink
+//!
+//! With `rateLimit` set, every batch submission, retries included, takes tokens from a
+//! bucket per limit that refills at the configured rate and holds at most `burstSeconds`
+//! of it. A batch that finds a bucket short waits on the batch thread, which stops taking
+//! events off the queue meanwhile, so a backlog drains at the limit instead of in one burst
+//! and anything beyond the queue is dropped upstream. A batch larger than a full bucket
+//! still goes out once the bucket is full, leaving it in debt, so the long-run rate holds.
+
+use crate::config::RateLimitConfig;
+use crate::error::XatuError;
+use std::time::{Duration, Instant};
+
+/// Seconds of the rate a bucket holds when `burstSeconds` is unset
+const DEFAULT_BURST_SECONDS: f64 = 1.0;
+
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second
+    rate: f64,
+    capacity: f64,
+    /// Negative after a batch larger than the capacity went out
+    tokens: f64,
+}
+
+impl Bucket {
+    fn new(rate: u64, burst_seconds: f64) -> Self {
+        let rate = rate as f64;
+        let capacity = rate * burst_seconds;
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+    }
+
+    /// Whether `cost` can be taken now, a full bucket covers any cost
+    fn has(&self, cost: usize) -> bool {
+        self.tokens >= (cost as f64).min(self.capacity)
+    }
+}
+
+/// Token buckets for events and bytes per second, either may be unset
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    events: Option<Bucket>,
+    bytes: Option<Bucket>,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Build the limiter from the config, `None` without any limit
+    pub(crate) fn from_config(config: &RateLimitConfig) -> Result<Option<Self>, XatuError> {
+        let burst_seconds = config.burst_seconds.unwrap_or(DEFAULT_BURST_SECONDS);
+        if !(burst_seconds.is_finite() && burst_seconds > 0.0) {
+            return Err(XatuError::InvalidConfig(format!(
+                "rateLimit burstSeconds must be above zero, got {}",
+                burst_seconds
+            )));
+        }
+        if config.events_per_second == Some(0) || config.bytes_per_second == Some(0) {
+            return Err(XatuError::InvalidConfig(
+                "rateLimit eventsPerSecond and bytesPerSecond must be above zero".to_string(),
+            ));
+        }
+        if config.events_per_second.is_none() && config.bytes_per_second.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            events: config
+                .events_per_second
+                .map(|rate| Bucket::new(rate, burst_seconds)),
+            bytes: config
+                .bytes_per_second
+                .map(|rate| Bucket::new(rate, burst_seconds)),
+            last_refill: Instant::now(),
+        }))
+    }
+
+    /// Take tokens for a batch of `events` events and `bytes` bytes if every bucket has
+    /// enough, otherwise take nothing
+    pub(crate) fn try_acquire(&mut self, events: usize, bytes: usize) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill);
+        self.last_refill = now;
+        for bucket in [&mut self.events, &mut self.bytes].into_iter().flatten() {
+            bucket.refill(elapsed);
+        }
+        let short = |bucket: &Option<Bucket>, cost: usize| {
+            bucket.as_ref().is_some_and(|bucket| !bucket.has(cost))
+        };
+        if short(&self.events, events) || short(&self.bytes, bytes) {
+            return false;
+        }
+        for (bucket, cost) in [(&mut self.events, events), (&mut self.bytes, bytes)] {
+            if let Some(bucket) = bucket {
+                bucket.tokens -= cost as f64;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,109 @@
+//! Registry of exporter implementations selectable by name from config
+
+use crate::config::NetworkInfo;
+use crate::context::XatuContext;
+use crate::error::XatuError;
+use crate::filter::EventFilter;
+use crate::middleware::layers_with_filter;
+use crate::observer_ffi::XatuObserver;
+use crate::testing::NoopXatu;
+use crate::{Xatu, XatuConfig};
+use std::collections::HashMap;
+use std::sync::Arc;
+use types::EthSpec;
+
+/// Name of the exporter used when the config doesn't select one
+pub const DEFAULT_EXPORTER: &str = "ffi";
+
+/// Factory creating an exporter from the config, network info and the context its
+/// middleware records into
+pub type ExporterFactory<E> = Box<
+    dyn Fn(&XatuConfig, NetworkInfo, Arc<XatuContext>) -> Result<Arc<dyn Xatu<E>>, XatuError>
+        + Send
+        + Sync,
+>;
+
+/// Named `Xatu<E>` implementations that can be selected with the `exporter` config field
+pub struct ExporterRegistry<E: EthSpec> {
+    factories: HashMap<String, ExporterFactory<E>>,
+}
+
+impl<E: EthSpec> Default for ExporterRegistry<E> {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+impl<E: EthSpec> ExporterRegistry<E> {
+    /// Create an empty registry
+    pub fn empty() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Create a registry with the built-in exporters registered
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::empty();
+        registry.register(DEFAULT_EXPORTER, |config, network_info, context| {
+            let exporter = XatuObserver::new_with_context(
+                &config.get_full_config(),
+                Some(network_info),
+                context,
+            )?;
+            Ok(Arc::new(exporter) as Arc<dyn Xatu<E>>)
+        });
+        registry.register("noop", |_, _, _| Ok(Arc::new(NoopXatu) as Arc<dyn Xatu<E>>));
+        registry
+    }
+
+    /// Register an exporter factory, replacing any existing one with the same name
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&XatuConfig, NetworkInfo, Arc<XatuContext>) -> Result<Arc<dyn Xatu<E>>, XatuError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    /// Check if an exporter is registered under this name
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Names of all registered exporters
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Create the exporter selected by the config, wrapped in the configured middleware
+    pub fn create(
+        &self,
+        config: &XatuConfig,
+        network_info: NetworkInfo,
+        context: Arc<XatuContext>,
+    ) -> Result<Arc<dyn Xatu<E>>, XatuError> {
+        let name = config.exporter.as_deref().unwrap_or(DEFAULT_EXPORTER);
+        let factory = self.factories.get(name).ok_or_else(|| {
+            XatuError::InvalidConfig(format!(
+                "unknown exporter '{}', registered exporters: {:?}",
+                name,
+                self.names()
+            ))
+        })?;
+        // Reject a bad filter before the factory starts the sidecar
+        let full_config = config.get_full_config();
+        let filter = EventFilter::from_config(&full_config, network_info.slots_per_epoch)?;
+        let exporter = factory(config, network_info, context.clone())?;
+        Ok(layers_with_filter(
+            exporter,
+            &full_config,
+            Arc::new(filter),
+            context,
+        ))
+    }
+}
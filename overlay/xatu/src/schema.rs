@@ -0,0 +1,410 @@
+//! The exported event schema
+//!
+//! Every event type is declared once here, with its `EventData` variant, event struct,
+//! `EventKind` and `event_type` tag. The observer builds events against it and both the
+//! sidecar and native exporters serialize them from it, so there is no second copy to drift.
+//!
+//! JSON Schema documents are generated from the same types so downstream pipelines can
+//! validate and codegen against exactly what this crate produces.
+
+use crate::error::XatuError;
+use crate::events::{
+    AggregateAndProofEvent, AttestationEvent, AttestationGroupEvent, BandwidthEvent,
+    BeaconBlockEvent, BlobSidecarEvent, ClockSkewEvent, CustodyEvent, DataColumnSidecarEvent,
+    ExportEvent, ExporterStatsEvent, GenesisEvent, NetworkChangedEvent, NetworkMismatchEvent,
+    NodeInfoEvent, OutputFailoverEvent, PeerCustodyEvent, PropagationLatencyEvent,
+    SessionStartEvent, SlotAggregateEvent, SubnetCoverageEvent,
+};
+use crate::filter::EventKind;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tracing::{debug_span, field, warn};
+
+/// Version of the event JSON produced by this crate
+/// Bump it whenever the sidecar needs changes to parse the exported events
+///
+/// - 1: batches are a bare JSON array of events
+/// - 2: batches are an object carrying their `schema_version` next to the `events` array
+/// - 3: string values of the `DICTIONARY_FIELDS` are written once per batch in its
+///   `dictionary` array and replaced by their index in it
+pub const EVENT_SCHEMA_VERSION: u32 = 3;
+
+/// Fields whose string values repeat across the events of a batch, dictionary encoded from
+/// schema version 3 on wherever they appear in an event, nested objects included
+pub const DICTIONARY_FIELDS: [&str; 19] = [
+    "session_id",
+    "peer_id",
+    "topic",
+    "fork_name",
+    "fork_digest",
+    "topic_fork_name",
+    "topic_fork_digest",
+    "agent",
+    "direction",
+    "remote_addr",
+    "implementation",
+    "version",
+    "platform",
+    "client",
+    "network_name",
+    "node_name",
+    "attestation_data_root",
+    "source_root",
+    "target_root",
+];
+
+/// Oldest schema version batches can still be written in for an older sink
+pub const MIN_EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// Schema version batches are written in, lowered at init if the sink is older
+static NEGOTIATED_VERSION: AtomicU32 = AtomicU32::new(EVENT_SCHEMA_VERSION);
+
+/// Pick the schema version to write for a sink that parses up to `sink_version`
+/// Fails if the sink is older than the oldest version this crate can still write
+pub fn negotiate_version(sink_version: u32) -> Result<u32, XatuError> {
+    if sink_version < MIN_EVENT_SCHEMA_VERSION {
+        return Err(XatuError::IncompatibleSidecar(format!(
+            "sink parses event schema {}, but this crate writes schema {} at the oldest",
+            sink_version, MIN_EVENT_SCHEMA_VERSION
+        )));
+    }
+    let version = sink_version.min(EVENT_SCHEMA_VERSION);
+    if version < EVENT_SCHEMA_VERSION {
+        warn!(
+            "Xatu sink parses event schema {}, downgrading batches from schema {}",
+            version, EVENT_SCHEMA_VERSION
+        );
+    }
+    NEGOTIATED_VERSION.store(version, Ordering::Relaxed);
+    Ok(version)
+}
+
+/// Schema version batches are currently written in
+pub fn negotiated_version() -> u32 {
+    NEGOTIATED_VERSION.load(Ordering::Relaxed)
+}
+
+/// Serialize a batch in the negotiated schema version, as both exporters hand it to the sink
+pub(crate) fn serialize_batch(events: &[EventData]) -> Result<String, XatuError> {
+    let span = debug_span!(
+        "xatu_serialize",
+        events = events.len(),
+        bytes = field::Empty
+    )
+    .entered();
+    let json_data = EventData::to_json_batch(events, negotiated_version())
+        .map_err(|e| XatuError::Serialization(format!("Failed to serialize events: {}", e)))?;
+    span.record("bytes", json_data.len());
+    Ok(json_data)
+}
+
+/// Declare the event types
+///
+/// The tag lookup matches on `EventKind` and `EventData::kind` on the variants, so a kind
+/// without an event type, or a variant without a kind, fails to compile. A kind listed twice
+/// is an unreachable pattern, rejected by clippy's `-D warnings`.
+macro_rules! event_types {
+    ($($variant:ident($event:ident) => $kind:ident = $tag:literal,)+) => {
+        /// An exported event, serialized with an `event_type` tag
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+        #[serde(tag = "event_type")]
+        pub enum EventData {
+            $(
+                #[serde(rename = $tag)]
+                $variant($event),
+            )+
+        }
+
+        impl EventData {
+            /// The kind of message this event was built from
+            pub fn kind(&self) -> EventKind {
+                match self {
+                    $(EventData::$variant(_) => EventKind::$kind,)+
+                }
+            }
+        }
+
+        $(
+            impl ExportEvent for $event {
+                const KIND: EventKind = EventKind::$kind;
+            }
+
+            impl From<$event> for EventData {
+                fn from(event: $event) -> Self {
+                    EventData::$variant(event)
+                }
+            }
+        )+
+
+        /// The `event_type` tag events of this kind are serialized with
+        pub(crate) const fn event_type(kind: EventKind) -> &'static str {
+            match kind {
+                $(EventKind::$kind => $tag,)+
+            }
+        }
+
+        /// Number of declared event types
+        const EVENT_TYPE_COUNT: usize = [$($tag),+].len();
+    };
+}
+
+event_types! {
+    BeaconBlock(BeaconBlockEvent) => Block = "BEACON_BLOCK",
+    Attestation(AttestationEvent) => Attestation = "ATTESTATION",
+    AggregateAndProof(AggregateAndProofEvent) => Aggregate = "AGGREGATE_AND_PROOF",
+    BlobSidecar(BlobSidecarEvent) => BlobSidecar = "BLOB_SIDECAR",
+    DataColumnSidecar(DataColumnSidecarEvent) => DataColumnSidecar = "DATA_COLUMN_SIDECAR",
+    SessionStart(SessionStartEvent) => SessionStart = "SESSION_START",
+    NodeInfo(NodeInfoEvent) => NodeInfo = "NODE_INFO",
+    ExporterStats(ExporterStatsEvent) => ExporterStats = "EXPORTER_STATS",
+    NetworkMismatch(NetworkMismatchEvent) => NetworkMismatch = "NETWORK_MISMATCH",
+    Custody(CustodyEvent) => Custody = "CUSTODY",
+    PeerCustody(PeerCustodyEvent) => PeerCustody = "PEER_CUSTODY",
+    SubnetCoverage(SubnetCoverageEvent) => SubnetCoverage = "SUBNET_COVERAGE",
+    Bandwidth(BandwidthEvent) => Bandwidth = "BANDWIDTH",
+    PropagationLatency(PropagationLatencyEvent) => PropagationLatency = "PROPAGATION_LATENCY",
+    SlotAggregate(SlotAggregateEvent) => SlotAggregate = "SLOT_AGGREGATE",
+    AttestationGroup(AttestationGroupEvent) => AttestationGroup = "ATTESTATION_GROUP",
+    ClockSkew(ClockSkewEvent) => ClockSkew = "CLOCK_SKEW",
+    OutputFailover(OutputFailoverEvent) => OutputFailover = "OUTPUT_FAILOVER",
+    Genesis(GenesisEvent) => Genesis = "GENESIS",
+    NetworkChanged(NetworkChangedEvent) => NetworkChanged = "NETWORK_CHANGED",
+}
+
+// Capability negotiation and metrics iterate `EventKind::ALL`, it must list every type
+const _: () = assert!(EventKind::ALL.len() == EVENT_TYPE_COUNT);
+
+impl EventData {
+    /// The `event_type` tag this event is serialized with
+    pub fn event_type(&self) -> &'static str {
+        self.kind().event_type()
+    }
+
+    /// Gossip topic the event was received on, `None` for node lifecycle events
+    pub fn topic(&self) -> Option<&str> {
+        match self {
+            EventData::BeaconBlock(event) => Some(&event.topic),
+            EventData::Attestation(event) => Some(&event.topic),
+            EventData::AggregateAndProof(event) => Some(&event.topic),
+            EventData::BlobSidecar(event) => Some(&event.topic),
+            EventData::DataColumnSidecar(event) => Some(&event.topic),
+            EventData::SessionStart(_)
+            | EventData::NodeInfo(_)
+            | EventData::ExporterStats(_)
+            | EventData::NetworkMismatch(_)
+            | EventData::Custody(_)
+            | EventData::PeerCustody(_)
+            | EventData::SubnetCoverage(_)
+            | EventData::Bandwidth(_)
+            | EventData::PropagationLatency(_)
+            | EventData::SlotAggregate(_)
+            | EventData::AttestationGroup(_)
+            | EventData::ClockSkew(_)
+            | EventData::OutputFailover(_)
+            | EventData::Genesis(_)
+            | EventData::NetworkChanged(_) => None,
+        }
+    }
+
+    /// Slot the event belongs to, `None` for events not tied to a slot
+    pub fn slot(&self) -> Option<u64> {
+        match self {
+            EventData::BeaconBlock(event) => Some(event.slot),
+            EventData::Attestation(event) => Some(event.slot),
+            EventData::AggregateAndProof(event) => Some(event.slot),
+            EventData::BlobSidecar(event) => Some(event.slot),
+            EventData::DataColumnSidecar(event) => Some(event.slot),
+            EventData::SlotAggregate(event) => Some(event.slot),
+            EventData::AttestationGroup(event) => Some(event.slot),
+            EventData::SessionStart(_)
+            | EventData::NodeInfo(_)
+            | EventData::ExporterStats(_)
+            | EventData::NetworkMismatch(_)
+            | EventData::Custody(_)
+            | EventData::PeerCustody(_)
+            | EventData::SubnetCoverage(_)
+            | EventData::Bandwidth(_)
+            | EventData::PropagationLatency(_)
+            | EventData::ClockSkew(_)
+            | EventData::OutputFailover(_)
+            | EventData::Genesis(_)
+            | EventData::NetworkChanged(_) => None,
+        }
+    }
+
+    /// Size of the event serialized as JSON, counted without building the string
+    pub fn json_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        match serde_json::to_writer(&mut counter, self) {
+            Ok(()) => counter.0,
+            Err(_) => 0,
+        }
+    }
+
+    /// Serialize a batch of events in the given schema version, as sent to the sink
+    pub fn to_json_batch(
+        events: &[EventData],
+        schema_version: u32,
+    ) -> Result<String, serde_json::Error> {
+        if schema_version < 2 {
+            return serde_json::to_string(events);
+        }
+        if schema_version < 3 {
+            return serde_json::to_string(&Batch {
+                schema_version,
+                events,
+            });
+        }
+        let mut dictionary = Dictionary::default();
+        let events = events
+            .iter()
+            .map(|event| {
+                let mut value = serde_json::to_value(event)?;
+                dictionary.encode(&mut value);
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>, serde_json::Error>>()?;
+        serde_json::to_string(&DictionaryBatch {
+            schema_version,
+            dictionary: dictionary.entries,
+            events,
+        })
+    }
+}
+
+/// Replace the dictionary indexes in an event of a schema 3 batch by the strings they stand
+/// for; indexes outside `dictionary` are left as they are
+pub fn expand_dictionary(dictionary: &[Value], event: &mut Value) {
+    match event {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let entry = field
+                    .as_u64()
+                    .filter(|_| DICTIONARY_FIELDS.contains(&key.as_str()))
+                    .and_then(|index| dictionary.get(usize::try_from(index).ok()?));
+                match entry {
+                    Some(entry) => *field = entry.clone(),
+                    None => expand_dictionary(dictionary, field),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                expand_dictionary(dictionary, item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Strings of the dictionary encoded fields of a batch, in first-seen order
+#[derive(Default)]
+struct Dictionary {
+    entries: Vec<String>,
+    indexes: HashMap<String, usize>,
+}
+
+impl Dictionary {
+    /// Replace the dictionary fields' strings in `value` by their index
+    fn encode(&mut self, value: &mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    match field {
+                        Value::String(string) if DICTIONARY_FIELDS.contains(&key.as_str()) => {
+                            *field = Value::from(self.index(std::mem::take(string)));
+                        }
+                        _ => self.encode(field),
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.encode(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn index(&mut self, string: String) -> usize {
+        if let Some(index) = self.indexes.get(&string) {
+            return *index;
+        }
+        let index = self.entries.len();
+        self.entries.push(string.clone());
+        self.indexes.insert(string, index);
+        index
+    }
+}
+
+/// Writer that only counts what is written to it
+struct ByteCounter(usize);
+
+impl std::io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Batch envelope of schema version 2
+#[derive(Serialize)]
+struct Batch<'a> {
+    schema_version: u32,
+    events: &'a [EventData],
+}
+
+/// Batch envelope from schema version 3 on
+#[derive(Serialize)]
+struct DictionaryBatch {
+    schema_version: u32,
+    dictionary: Vec<String>,
+    events: Vec<Value>,
+}
+
+/// JSON Schema for the full `EventData` enum (one `oneOf` entry per event type)
+pub fn event_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(EventData))
+        .expect("JSON Schema should always serialize")
+}
+
+/// Standalone JSON Schema documents for every event type, keyed by `event_type`
+pub fn event_type_schemas() -> Vec<(String, Value)> {
+    let root = event_schema();
+    let schema_uri = root.get("$schema").cloned();
+    let definitions = root.get("definitions").cloned();
+
+    root.get("oneOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|variant| {
+            let event_type = variant
+                .pointer("/properties/event_type/enum/0")
+                .and_then(Value::as_str)?
+                .to_string();
+
+            let mut document = Map::new();
+            if let Some(uri) = &schema_uri {
+                document.insert("$schema".to_string(), uri.clone());
+            }
+            document.insert("title".to_string(), Value::String(event_type.clone()));
+            if let Value::Object(fields) = variant {
+                document.extend(fields.clone());
+            }
+            if let Some(definitions) = &definitions {
+                document.insert("definitions".to_string(), definitions.clone());
+            }
+
+            Some((event_type, Value::Object(document)))
+        })
+        .collect()
+}
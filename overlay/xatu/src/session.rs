@@ -0,0 +1,14 @@
+//! Per-run session id
+//!
+//! Every process gets a fresh UUID that is attached to all events, so restarts of the
+//! same named node can be told apart downstream.
+
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+static SESSION_ID: LazyLock<String> = LazyLock::new(|| Uuid::new_v4().to_string());
+
+/// Session id of this process
+pub fn id() -> &'static str {
+    &SESSION_ID
+}
@@ -1,5 +1,10 @@
 //! Shim module for creating Xatu exporter
 
+use crate::config::{FullConfig, NetworkInfo};
+use crate::context::XatuContext;
+use crate::error::XatuError;
+use crate::filter::EventFilter;
+use crate::middleware::layers_with_filter;
 use crate::observer_ffi::XatuObserver;
 use crate::Xatu;
 use std::sync::Arc;
@@ -21,10 +26,11 @@ pub fn create_exporter_from_config<E: EthSpec>(
     }
 
     let full_config = config.get_full_config();
-    match XatuObserver::new_with_full_config(&full_config, None) {
+    let exporter = build_layered::<E>(&full_config, None, E::slots_per_epoch());
+    match exporter {
         Ok(middleware) => {
             tracing::info!("Xatu exporter created successfully with config");
-            Some(Arc::new(middleware))
+            Some(middleware)
         }
         Err(e) => {
             tracing::error!("Failed to create Xatu: {}", e);
@@ -44,11 +50,31 @@ pub fn create_exporter_with_network_info<E: EthSpec>(
     }
 
     let full_config = config.get_full_config();
-    match XatuObserver::new_with_full_config(&full_config, Some(network_info)) {
-        Ok(middleware) => Some(Arc::new(middleware)),
+    let slots_per_epoch = network_info.slots_per_epoch;
+    let exporter = build_layered::<E>(&full_config, Some(network_info), slots_per_epoch);
+    match exporter {
+        Ok(middleware) => Some(middleware),
         Err(e) => {
             tracing::error!("FATAL: Failed to create Xatu with network info: {}", e);
             panic!("FATAL: Failed to initialize Xatu - network info is required but initialization failed: {}", e);
         }
     }
 }
+
+/// Build the observer wrapped in the configured middleware
+/// The filter is checked first so a bad config fails before the sidecar is started
+fn build_layered<E: EthSpec>(
+    full_config: &FullConfig,
+    network_info: Option<NetworkInfo>,
+    slots_per_epoch: u64,
+) -> Result<Arc<dyn Xatu<E>>, XatuError> {
+    let filter = Arc::new(EventFilter::from_config(full_config, slots_per_epoch)?);
+    let context = XatuContext::new(None);
+    let observer = XatuObserver::new_with_context(full_config, network_info, context.clone())?;
+    Ok(layers_with_filter(
+        Arc::new(observer),
+        full_config,
+        filter,
+        context,
+    ))
+}
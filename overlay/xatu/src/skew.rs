@@ -0,0 +1,104 @@
+//! Clock skew detection
+//!
+//! Every propagation measurement is relative to the local clock, so a skewed clock silently
+//! shifts all of them. Skew is estimated two ways: the NTP offset between the corrected and
+//! the system clock, and the arrival of gossip blocks relative to their slot start. Honest
+//! proposers publish at the start of the slot, so a median block arrival before the slot
+//! starts means the corrected clock runs behind, and one past the attestation deadline
+//! means it likely runs ahead. The batch thread checks both periodically and exports a
+//! `CLOCK_SKEW` event while either exceeds the threshold.
+
+use crate::events::SlotTiming;
+use crate::filter::EventKind;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Block arrivals the median is taken over, about two epochs on mainnet
+const WINDOW: usize = 64;
+
+/// Block arrivals needed before the peer-implied skew is estimated
+const MIN_SAMPLES: usize = 8;
+
+/// Recent block arrivals of one exporter, relative to their slot start
+#[derive(Default)]
+pub(crate) struct BlockArrivals(Mutex<VecDeque<i64>>);
+
+/// Skew estimates at the time of a check that exceeded the threshold
+/// Both are the offset to add to the clock, positive when it runs behind
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClockSkew {
+    pub(crate) threshold_ms: u64,
+    /// NTP-corrected minus system time, `None` until NTP has synced
+    pub(crate) ntp_offset_ms: Option<i64>,
+    pub(crate) block_samples: u64,
+    /// Median block arrival relative to the slot start, on the corrected clock
+    pub(crate) median_block_arrival_ms: Option<i64>,
+    /// Skew left after NTP correction, as implied by the block arrivals
+    pub(crate) peer_implied_skew_ms: Option<i64>,
+}
+
+impl BlockArrivals {
+    /// Record the arrival of a gossip event, only blocks are used
+    /// `timestamp_ms` is the local arrival time, before clock correction
+    pub(crate) fn record(
+        &self,
+        timing: &SlotTiming,
+        kind: EventKind,
+        slot: u64,
+        timestamp_ms: u64,
+    ) {
+        if kind != EventKind::Block {
+            return;
+        }
+        let arrival_ms = i64::try_from(timestamp_ms)
+            .unwrap_or(i64::MAX)
+            .saturating_add(crate::clock::offset_ms().unwrap_or(0));
+
+        let mut arrivals = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if arrivals.len() == WINDOW {
+            arrivals.pop_front();
+        }
+        arrivals.push_back(timing.propagation_ms(slot, arrival_ms));
+    }
+
+    /// Estimate the skew, `None` unless an estimate exceeds `threshold_ms`
+    pub(crate) fn check(&self, timing: Option<SlotTiming>, threshold_ms: u64) -> Option<ClockSkew> {
+        let ntp_offset_ms = crate::clock::offset_ms();
+        let (block_samples, median_block_arrival_ms) = {
+            let arrivals = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            let mut sorted: Vec<i64> = arrivals.iter().copied().collect();
+            let count = sorted.len();
+            let median = (count >= MIN_SAMPLES).then(|| *sorted.select_nth_unstable(count / 2).1);
+            (count as u64, median)
+        };
+        let peer_implied_skew_ms = median_block_arrival_ms
+            .zip(timing)
+            .map(|(median_ms, timing)| implied_skew_ms(median_ms, &timing));
+
+        let exceeds =
+            |skew_ms: Option<i64>| skew_ms.is_some_and(|ms| ms.unsigned_abs() > threshold_ms);
+        if !exceeds(ntp_offset_ms) && !exceeds(peer_implied_skew_ms) {
+            return None;
+        }
+        Some(ClockSkew {
+            threshold_ms,
+            ntp_offset_ms,
+            block_samples,
+            median_block_arrival_ms,
+            peer_implied_skew_ms,
+        })
+    }
+}
+
+/// Skew implied by the median block arrival, zero while it is within the first third of the
+/// slot, when blocks are expected to arrive
+fn implied_skew_ms(median_ms: i64, timing: &SlotTiming) -> i64 {
+    let deadline_ms = (timing.seconds_per_slot.saturating_mul(1000) / 3) as i64;
+    if median_ms < 0 {
+        -median_ms
+    } else if median_ms > deadline_ms {
+        deadline_ms - median_ms
+    } else {
+        0
+    }
+}
@@ -0,0 +1,121 @@
+//! Per-slot aggregates of gossip events
+//!
+//! With `aggregation.mode` set, every gossip event that passes the peer and slot filters is
+//! folded into a per (slot, event type) aggregate before sampling. A slot is aggregated once
+//! events from two slots later arrive, and the batch thread exports the aggregates as
+//! `SLOT_AGGREGATE` events. Events arriving for a slot after that are not counted.
+
+use crate::events::SlotTiming;
+use crate::filter::EventKind;
+use libp2p::PeerId;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+
+/// Arrivals of one exporter's slots, open and aggregated
+#[derive(Default)]
+pub(crate) struct SlotAggregator {
+    open: Mutex<OpenSlots>,
+    completed: Mutex<Vec<SlotAggregate>>,
+}
+
+/// Arrivals of the slots still receiving events, by event kind
+#[derive(Default)]
+struct OpenSlots {
+    slots: BTreeMap<u64, Vec<(EventKind, Arrivals)>>,
+    /// Slots before this one were aggregated, late events for them are dropped
+    closed_before: u64,
+}
+
+#[derive(Default)]
+struct Arrivals {
+    peers: HashSet<PeerId>,
+    /// Milliseconds between the slot start and each arrival
+    offsets_ms: Vec<i64>,
+}
+
+/// Aggregate of one event kind in one slot
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SlotAggregate {
+    pub(crate) slot: u64,
+    pub(crate) kind: EventKind,
+    pub(crate) count: u64,
+    pub(crate) unique_peers: u64,
+    pub(crate) min_arrival_ms: i64,
+    pub(crate) median_arrival_ms: i64,
+}
+
+impl Arrivals {
+    fn aggregate(mut self, slot: u64, kind: EventKind) -> SlotAggregate {
+        let count = self.offsets_ms.len();
+        let (_, median, _) = self.offsets_ms.select_nth_unstable(count / 2);
+        let median_arrival_ms = *median;
+        SlotAggregate {
+            slot,
+            kind,
+            count: count as u64,
+            unique_peers: self.peers.len() as u64,
+            min_arrival_ms: self.offsets_ms.iter().copied().min().unwrap_or_default(),
+            median_arrival_ms,
+        }
+    }
+}
+
+impl SlotAggregator {
+    /// Record the arrival of a gossip event for its slot
+    /// `timestamp_ms` is the local arrival time, before clock correction
+    pub(crate) fn record(
+        &self,
+        timing: &SlotTiming,
+        kind: EventKind,
+        slot: u64,
+        peer_id: &PeerId,
+        timestamp_ms: u64,
+    ) {
+        let arrival_ms = i64::try_from(timestamp_ms)
+            .unwrap_or(i64::MAX)
+            .saturating_add(crate::clock::offset_ms().unwrap_or(0));
+        // A slot far ahead of the clock would close every open slot early
+        if slot > timing.wallclock_slot(arrival_ms) + 1 {
+            return;
+        }
+
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        if slot < open.closed_before {
+            return;
+        }
+        // Events from two slots later mean the older ones have settled
+        let settled = slot.saturating_sub(1);
+        if settled > open.closed_before {
+            let still_open = open.slots.split_off(&settled);
+            let completed = std::mem::replace(&mut open.slots, still_open);
+            open.closed_before = settled;
+            self.completed
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .extend(completed.into_iter().flat_map(|(slot, kinds)| {
+                    kinds
+                        .into_iter()
+                        .map(move |(kind, arrivals)| arrivals.aggregate(slot, kind))
+                }));
+        }
+
+        let kinds = open.slots.entry(slot).or_default();
+        let index = match kinds.iter().position(|(existing, _)| *existing == kind) {
+            Some(index) => index,
+            None => {
+                kinds.push((kind, Arrivals::default()));
+                kinds.len() - 1
+            }
+        };
+        let arrivals = &mut kinds[index].1;
+        arrivals.peers.insert(*peer_id);
+        arrivals
+            .offsets_ms
+            .push(timing.propagation_ms(slot, arrival_ms));
+    }
+
+    /// Take the aggregates of slots completed since the last call, oldest first
+    pub(crate) fn take_completed(&self) -> Vec<SlotAggregate> {
+        std::mem::take(&mut *self.completed.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
@@ -0,0 +1,38 @@
+//! In-process subscription to the live event stream
+//!
+//! Other components in the same process (debug endpoints, analyzers) can subscribe to
+//! every event the exporter sends, after transforms have run, without adding an observer.
+
+use crate::events::EventData;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before slow subscribers start lagging
+pub const TAP_CAPACITY: usize = 4096;
+
+static TAP: LazyLock<broadcast::Sender<EventData>> =
+    LazyLock::new(|| broadcast::channel(TAP_CAPACITY).0);
+
+/// Subscribe to every event sent from now on
+/// Subscribers that fall more than `TAP_CAPACITY` events behind receive `RecvError::Lagged`
+pub fn subscribe() -> broadcast::Receiver<EventData> {
+    TAP.subscribe()
+}
+
+/// Number of live subscribers
+pub fn subscriber_count() -> usize {
+    TAP.receiver_count()
+}
+
+/// Publish a batch to every subscriber, skipping the clones when nobody is listening
+pub(crate) fn publish(batch: &[EventData]) {
+    if TAP.receiver_count() == 0 {
+        return;
+    }
+    for event in batch {
+        // Only fails when the last subscriber went away mid-batch
+        if TAP.send(event.clone()).is_err() {
+            break;
+        }
+    }
+}
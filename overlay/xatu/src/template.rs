@@ -0,0 +1,79 @@
+//! Placeholders in the node name and output headers, expanded once at init
+//!
+//! Lets a fleet share one config file while every node reports its own identity.
+
+use crate::config::XatuOutput;
+use crate::error::XatuError;
+
+/// Values the placeholders expand to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TemplateVars {
+    pub(crate) hostname: String,
+    pub(crate) network: String,
+    pub(crate) pid: u32,
+}
+
+impl TemplateVars {
+    /// Values for this process on the given network
+    pub(crate) fn current(network: &str) -> Self {
+        Self {
+            hostname: hostname(),
+            network: network.to_string(),
+            pid: std::process::id(),
+        }
+    }
+
+    /// Expand `{hostname}`, `{network}` and `{pid}`, failing on any other placeholder
+    pub(crate) fn expand(&self, template: &str) -> Result<String, XatuError> {
+        let mut expanded = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            expanded.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                return Err(XatuError::InvalidConfig(format!(
+                    "Unclosed placeholder in '{}'",
+                    template
+                )));
+            };
+            match &rest[start + 1..start + len] {
+                "hostname" => expanded.push_str(&self.hostname),
+                "network" => expanded.push_str(&self.network),
+                "pid" => expanded.push_str(&self.pid.to_string()),
+                other => {
+                    return Err(XatuError::InvalidConfig(format!(
+                        "Unknown placeholder {{{}}} in '{}', expected {{hostname}}, {{network}} or {{pid}}",
+                        other, template
+                    )))
+                }
+            }
+            rest = &rest[start + len + 1..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    /// Expand the placeholders in every output header value
+    pub(crate) fn expand_headers(&self, outputs: &mut [XatuOutput]) -> Result<(), XatuError> {
+        for output in outputs {
+            for value in output.config.headers.values_mut() {
+                *value = self.expand(value).map_err(|e| {
+                    XatuError::InvalidConfig(format!("Output '{}' headers: {}", output.name, e))
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Host name from the environment or the kernel, `unknown` if neither has one
+fn hostname() -> String {
+    let from_env = ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok());
+    from_env
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -0,0 +1,600 @@
+//! Built-in observers and exporters for exercising the event path without the sidecar
+
+pub mod generator;
+
+use crate::config::NetworkInfo;
+use crate::context::XatuContext;
+use crate::error::XatuError;
+use crate::events::EventData;
+use crate::filter::{EventFilter, EventKind};
+use crate::middleware::layers_with_filter;
+use crate::observer_ffi::XatuObserver;
+use crate::peers::PeerSubnets;
+use crate::queue_budget::QueuedEvent;
+use crate::{ObserverResult, Xatu, XatuConfig};
+use crossbeam_channel::Receiver;
+use libp2p::PeerId;
+use lighthouse_network::MessageId;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use types::{
+    Attestation, BlobSidecar, DataColumnSidecar, DataColumnSubnetId, EthSpec,
+    SignedAggregateAndProof, SignedBeaconBlock, SingleAttestation, SubnetId,
+};
+
+/// Observer that ignores every event
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopXatu;
+
+impl<E: EthSpec> Xatu<E> for NoopXatu {
+    fn on_gossip_block(
+        &self,
+        _message_id: MessageId,
+        _peer_id: PeerId,
+        _client: Option<String>,
+        _block: Arc<SignedBeaconBlock<E>>,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    fn on_gossip_attestation(
+        &self,
+        _message_id: MessageId,
+        _peer_id: PeerId,
+        _attestation: Arc<SingleAttestation>,
+        _subnet_id: SubnetId,
+        _should_process: bool,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    fn on_gossip_aggregate_and_proof(
+        &self,
+        _message_id: MessageId,
+        _peer_id: PeerId,
+        _aggregate: Arc<SignedAggregateAndProof<E>>,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    fn on_gossip_blob_sidecar(
+        &self,
+        _message_id: MessageId,
+        _peer_id: PeerId,
+        _client: Option<String>,
+        _blob_index: u64,
+        _blob_sidecar: Arc<BlobSidecar<E>>,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+
+    fn on_gossip_data_column_sidecar(
+        &self,
+        _message_id: MessageId,
+        _peer_id: PeerId,
+        _client: Option<String>,
+        _subnet_id: DataColumnSubnetId,
+        _column_sidecar: Arc<DataColumnSidecar<E>>,
+        _timestamp_millis: u64,
+        _topic: String,
+        _message_size: usize,
+    ) -> ObserverResult {
+        ObserverResult::Ok
+    }
+}
+
+/// A single call recorded by `MockXatu`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedCall {
+    pub kind: EventKind,
+    pub message_id: MessageId,
+    pub peer_id: PeerId,
+    pub client: Option<String>,
+    pub slot: u64,
+    pub timestamp_millis: u64,
+    pub topic: String,
+    pub message_size: usize,
+}
+
+/// Observer that records every call for later inspection
+#[derive(Debug, Clone, Default)]
+pub struct MockXatu {
+    calls: Arc<Mutex<Vec<RecordedCall>>>,
+}
+
+impl MockXatu {
+    /// Create an empty mock
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All recorded calls in the order they were received
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.lock().clone()
+    }
+
+    /// Recorded calls of a single event kind
+    pub fn calls_of_kind(&self, kind: EventKind) -> Vec<RecordedCall> {
+        self.lock()
+            .iter()
+            .filter(|call| call.kind == kind)
+            .cloned()
+            .collect()
+    }
+
+    /// Recorded calls from a single peer
+    pub fn calls_from_peer(&self, peer_id: &PeerId) -> Vec<RecordedCall> {
+        self.lock()
+            .iter()
+            .filter(|call| call.peer_id == *peer_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of recorded calls
+    pub fn count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Number of recorded calls of a single event kind
+    pub fn count_of_kind(&self, kind: EventKind) -> usize {
+        self.lock().iter().filter(|call| call.kind == kind).count()
+    }
+
+    /// Forget all recorded calls
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn record(&self, call: RecordedCall) -> ObserverResult {
+        self.lock().push(call);
+        ObserverResult::Ok
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<RecordedCall>> {
+        // A panic while holding the lock can't leave the Vec in an invalid state
+        self.calls.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<E: EthSpec> Xatu<E> for MockXatu {
+    fn on_gossip_block(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        block: Arc<SignedBeaconBlock<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.record(RecordedCall {
+            kind: EventKind::Block,
+            message_id,
+            peer_id,
+            client,
+            slot: block.slot().as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+        })
+    }
+
+    fn on_gossip_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<SingleAttestation>,
+        _subnet_id: SubnetId,
+        _should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.record(RecordedCall {
+            kind: EventKind::Attestation,
+            message_id,
+            peer_id,
+            client: None,
+            slot: attestation.data.slot.as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+        })
+    }
+
+    fn on_gossip_unaggregated_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<Attestation<E>>,
+        _subnet_id: SubnetId,
+        _should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.record(RecordedCall {
+            kind: EventKind::Attestation,
+            message_id,
+            peer_id,
+            client: None,
+            slot: attestation.data().slot.as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+        })
+    }
+
+    fn on_gossip_aggregate_and_proof(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        aggregate: Arc<SignedAggregateAndProof<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.record(RecordedCall {
+            kind: EventKind::Aggregate,
+            message_id,
+            peer_id,
+            client: None,
+            slot: aggregate.message().aggregate().data().slot.as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+        })
+    }
+
+    fn on_gossip_blob_sidecar(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        _blob_index: u64,
+        blob_sidecar: Arc<BlobSidecar<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.record(RecordedCall {
+            kind: EventKind::BlobSidecar,
+            message_id,
+            peer_id,
+            client,
+            slot: blob_sidecar.slot().as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+        })
+    }
+
+    fn on_gossip_data_column_sidecar(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        _subnet_id: DataColumnSubnetId,
+        column_sidecar: Arc<DataColumnSidecar<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.record(RecordedCall {
+            kind: EventKind::DataColumnSidecar,
+            message_id,
+            peer_id,
+            client,
+            slot: column_sidecar.slot().as_u64(),
+            timestamp_millis,
+            topic,
+            message_size,
+        })
+    }
+}
+
+/// Exporter that builds events exactly like the sidecar exporter but keeps them in memory
+///
+/// The configured filter, sampling and other middleware run in front of it, as they do
+/// for the sidecar exporter. At most `capacity` events are retained; older events are
+/// dropped first.
+pub struct CapturingExporter<E: EthSpec> {
+    exporter: Arc<dyn Xatu<E>>,
+    context: Arc<XatuContext>,
+    receiver: Receiver<QueuedEvent>,
+    events: Mutex<VecDeque<EventData>>,
+    capacity: usize,
+}
+
+impl<E: EthSpec> CapturingExporter<E> {
+    /// Default number of retained events
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    /// Create a capturing exporter from the config and network info
+    pub fn new(config: &XatuConfig, network_info: NetworkInfo) -> Result<Self, XatuError> {
+        Self::with_capacity(config, network_info, Self::DEFAULT_CAPACITY)
+    }
+
+    /// Create a capturing exporter retaining at most `capacity` events
+    pub fn with_capacity(
+        config: &XatuConfig,
+        network_info: NetworkInfo,
+        capacity: usize,
+    ) -> Result<Self, XatuError> {
+        let full_config = config.get_full_config();
+        let filter = EventFilter::from_config(&full_config, network_info.slots_per_epoch)?;
+        let context = XatuContext::new(None);
+        let (observer, receiver) =
+            XatuObserver::new_detached(&full_config, network_info, context.clone())?;
+        Ok(Self {
+            exporter: layers_with_filter(
+                Arc::new(observer),
+                &full_config,
+                Arc::new(filter),
+                context.clone(),
+            ),
+            context,
+            receiver,
+            events: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            capacity,
+        })
+    }
+
+    /// State the events are built from, e.g. to register a fork schedule or pubkey lookup
+    pub fn context(&self) -> &Arc<XatuContext> {
+        &self.context
+    }
+
+    /// All retained events in the order they were built
+    pub fn events(&self) -> Vec<EventData> {
+        self.drain().iter().cloned().collect()
+    }
+
+    /// Retained events of a single kind
+    pub fn events_of_type(&self, kind: EventKind) -> Vec<EventData> {
+        self.drain()
+            .iter()
+            .filter(|event| event.kind() == kind)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of retained events
+    pub fn count(&self) -> usize {
+        self.drain().len()
+    }
+
+    /// Wait until at least `count` events are retained, returning false on timeout
+    pub fn wait_for_count(&self, count: usize, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.count() >= count {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            // Block on the channel rather than spinning
+            match self.receiver.recv_timeout(deadline - now) {
+                Ok((event, _)) => self.retain(&mut self.lock(), event),
+                Err(_) => return self.count() >= count,
+            }
+        }
+    }
+
+    /// Forget all retained events
+    pub fn clear(&self) {
+        self.drain().clear();
+    }
+
+    fn drain(&self) -> std::sync::MutexGuard<'_, VecDeque<EventData>> {
+        let mut events = self.lock();
+        while let Ok((event, _)) = self.receiver.try_recv() {
+            self.retain(&mut events, event);
+        }
+        events
+    }
+
+    fn retain(&self, events: &mut VecDeque<EventData>, event: EventData) {
+        if self.capacity == 0 {
+            return;
+        }
+        while events.len() >= self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<EventData>> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn captured(&self, result: ObserverResult) -> ObserverResult {
+        drop(self.drain());
+        result
+    }
+}
+
+impl<E: EthSpec> Xatu<E> for CapturingExporter<E> {
+    fn on_gossip_block(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        block: Arc<SignedBeaconBlock<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.captured(self.exporter.on_gossip_block(
+            message_id,
+            peer_id,
+            client,
+            block,
+            timestamp_millis,
+            topic,
+            message_size,
+        ))
+    }
+
+    fn on_gossip_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<SingleAttestation>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.captured(self.exporter.on_gossip_attestation(
+            message_id,
+            peer_id,
+            attestation,
+            subnet_id,
+            should_process,
+            timestamp_millis,
+            topic,
+            message_size,
+        ))
+    }
+
+    fn on_gossip_unaggregated_attestation(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        attestation: Arc<Attestation<E>>,
+        subnet_id: SubnetId,
+        should_process: bool,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.captured(self.exporter.on_gossip_unaggregated_attestation(
+            message_id,
+            peer_id,
+            attestation,
+            subnet_id,
+            should_process,
+            timestamp_millis,
+            topic,
+            message_size,
+        ))
+    }
+
+    fn on_gossip_aggregate_and_proof(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        aggregate: Arc<SignedAggregateAndProof<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.captured(self.exporter.on_gossip_aggregate_and_proof(
+            message_id,
+            peer_id,
+            aggregate,
+            timestamp_millis,
+            topic,
+            message_size,
+        ))
+    }
+
+    fn on_gossip_blob_sidecar(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        blob_index: u64,
+        blob_sidecar: Arc<BlobSidecar<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.captured(self.exporter.on_gossip_blob_sidecar(
+            message_id,
+            peer_id,
+            client,
+            blob_index,
+            blob_sidecar,
+            timestamp_millis,
+            topic,
+            message_size,
+        ))
+    }
+
+    fn on_gossip_data_column_sidecar(
+        &self,
+        message_id: MessageId,
+        peer_id: PeerId,
+        client: Option<String>,
+        subnet_id: DataColumnSubnetId,
+        column_sidecar: Arc<DataColumnSidecar<E>>,
+        timestamp_millis: u64,
+        topic: String,
+        message_size: usize,
+    ) -> ObserverResult {
+        self.captured(self.exporter.on_gossip_data_column_sidecar(
+            message_id,
+            peer_id,
+            client,
+            subnet_id,
+            column_sidecar,
+            timestamp_millis,
+            topic,
+            message_size,
+        ))
+    }
+
+    fn on_custody_changed(
+        &self,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        self.captured(
+            self.exporter
+                .on_custody_changed(custody_group_count, custody_columns),
+        )
+    }
+
+    fn on_peer_custody(
+        &self,
+        peer_id: &PeerId,
+        custody_group_count: u64,
+        custody_columns: &[u64],
+    ) -> ObserverResult {
+        self.captured(
+            self.exporter
+                .on_peer_custody(peer_id, custody_group_count, custody_columns),
+        )
+    }
+
+    fn on_peer_subnets(&self, peer_id: &PeerId, subnets: &PeerSubnets) -> ObserverResult {
+        self.exporter.on_peer_subnets(peer_id, subnets)
+    }
+
+    fn on_genesis_time_changed(&self, genesis_time: u64) -> ObserverResult {
+        self.exporter.on_genesis_time_changed(genesis_time)
+    }
+
+    fn on_network_changed(&self, network_info: &NetworkInfo) -> ObserverResult {
+        self.captured(self.exporter.on_network_changed(network_info))
+    }
+}
@@ -0,0 +1,557 @@
+//! Synthetic gossip traffic for load testing without a live network
+//!
+//! `GossipGenerator` builds fake blocks, attestations, aggregates, blob and data column
+//! sidecars slot by slot, with mainnet-like counts and arrival times, and delivers them to
+//! any `Xatu` implementation. The traffic is deterministic for a given seed.
+//!
+//! Blocks are built for the chain spec's genesis fork, so use a spec with the forks of
+//! interest scheduled at genesis. Aggregates always use the Electra format.
+
+use crate::config::NetworkInfo;
+use crate::{ObserverResult, Xatu};
+use libp2p::PeerId;
+use lighthouse_network::MessageId;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use types::{
+    AggregateAndProofElectra, AggregateSignature, AttestationData, AttestationElectra, BeaconBlock,
+    BitList, BitVector, BlobSidecar, ChainSpec, Checkpoint, DataColumnSidecar,
+    DataColumnSidecarFulu, DataColumnSubnetId, Epoch, EthSpec, FixedVector, Hash256, KzgCommitment,
+    KzgProof, Signature, SignedAggregateAndProof, SignedAggregateAndProofElectra,
+    SignedBeaconBlock, SignedBeaconBlockHeader, SingleAttestation, Slot, SubnetId, VariableList,
+};
+
+/// Attestation subnets on the network
+const ATTESTATION_SUBNET_COUNT: u64 = 64;
+
+/// Data column subnets on the network
+const DATA_COLUMN_SUBNET_COUNT: u64 = 128;
+
+/// Agent strings peers are picked from
+const CLIENT_AGENTS: [&str; 6] = [
+    "Lighthouse/v7.1.0-abcdef0/x86_64-linux",
+    "Prysm/v6.0.4/0a1b2c3d",
+    "teku/teku/v25.6.0/linux-x86_64/-eclipseadoptium-openjdk64bitservervm-java-21",
+    "nimbus",
+    "lodestar/v1.31.0/8a9b0c1d",
+    "erigon/caplin",
+];
+
+/// Traffic mix and timing of the generated network
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorConfig {
+    /// Seed of the generator, equal seeds produce equal traffic
+    pub seed: u64,
+    /// Genesis time in seconds since the unix epoch, defaults to `start_slot` slots ago
+    pub genesis_time: u64,
+    pub seconds_per_slot: u64,
+    pub slots_per_epoch: u64,
+    /// First generated slot
+    pub start_slot: u64,
+    /// Chance of a slot having a block, between 0.0 and 1.0
+    pub block_probability: f64,
+    /// Unaggregated attestations received per slot
+    pub attestations_per_slot: u32,
+    /// Aggregates received per slot
+    pub aggregates_per_slot: u32,
+    /// Blob sidecars received per block
+    pub blobs_per_block: u32,
+    /// Data column sidecars received per block
+    pub columns_per_block: u32,
+    /// Distinct peers messages arrive from
+    pub peer_count: usize,
+}
+
+impl GeneratorConfig {
+    /// Mainnet-like Electra traffic of a node on two attestation subnets
+    pub fn electra() -> Self {
+        Self {
+            seed: 0,
+            genesis_time: 0,
+            seconds_per_slot: 12,
+            slots_per_epoch: 32,
+            start_slot: 1_000,
+            block_probability: 0.99,
+            attestations_per_slot: 1_000,
+            aggregates_per_slot: 1_024,
+            blobs_per_block: 6,
+            columns_per_block: 0,
+            peer_count: 80,
+        }
+        .with_genesis_now()
+    }
+
+    /// Mainnet-like Fulu traffic of a node custodying 8 columns, blobs are gone
+    pub fn fulu() -> Self {
+        Self {
+            blobs_per_block: 0,
+            columns_per_block: 8,
+            ..Self::electra()
+        }
+    }
+
+    /// Use a different seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Network info matching the generated traffic, for building the observer under test
+    pub fn network_info(&self) -> NetworkInfo {
+        NetworkInfo {
+            genesis_time: self.genesis_time,
+            network_name: "generator".to_string(),
+            network_id: 0,
+            slots_per_epoch: self.slots_per_epoch,
+            seconds_per_slot: self.seconds_per_slot,
+        }
+    }
+
+    /// Place genesis so that `start_slot` begins now
+    fn with_genesis_now(mut self) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.genesis_time =
+            now.saturating_sub(self.start_slot.saturating_mul(self.seconds_per_slot));
+        self
+    }
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self::fulu()
+    }
+}
+
+/// A generated gossip message, ready to be delivered to an observer
+pub enum GeneratedMessage<E: EthSpec> {
+    Block {
+        block: Arc<SignedBeaconBlock<E>>,
+        meta: MessageMeta,
+    },
+    Attestation {
+        attestation: Arc<SingleAttestation>,
+        subnet_id: SubnetId,
+        meta: MessageMeta,
+    },
+    Aggregate {
+        aggregate: Arc<SignedAggregateAndProof<E>>,
+        meta: MessageMeta,
+    },
+    BlobSidecar {
+        blob_index: u64,
+        blob_sidecar: Arc<BlobSidecar<E>>,
+        meta: MessageMeta,
+    },
+    DataColumnSidecar {
+        subnet_id: DataColumnSubnetId,
+        column_sidecar: Arc<DataColumnSidecar<E>>,
+        meta: MessageMeta,
+    },
+}
+
+/// Gossip details shared by every generated message
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageMeta {
+    pub message_id: MessageId,
+    pub peer_id: PeerId,
+    pub client: Option<String>,
+    /// Arrival time in milliseconds since the unix epoch
+    pub timestamp_millis: u64,
+    pub topic: String,
+    /// Compressed size on the gossip wire
+    pub message_size: usize,
+}
+
+impl<E: EthSpec> GeneratedMessage<E> {
+    /// Gossip details of the message
+    pub fn meta(&self) -> &MessageMeta {
+        match self {
+            GeneratedMessage::Block { meta, .. }
+            | GeneratedMessage::Attestation { meta, .. }
+            | GeneratedMessage::Aggregate { meta, .. }
+            | GeneratedMessage::BlobSidecar { meta, .. }
+            | GeneratedMessage::DataColumnSidecar { meta, .. } => meta,
+        }
+    }
+
+    /// Hand the message to the observer through its matching gossip hook
+    pub fn deliver<X: Xatu<E> + ?Sized>(self, observer: &X) -> ObserverResult {
+        match self {
+            GeneratedMessage::Block { block, meta } => observer.on_gossip_block(
+                meta.message_id,
+                meta.peer_id,
+                meta.client,
+                block,
+                meta.timestamp_millis,
+                meta.topic,
+                meta.message_size,
+            ),
+            GeneratedMessage::Attestation {
+                attestation,
+                subnet_id,
+                meta,
+            } => observer.on_gossip_attestation(
+                meta.message_id,
+                meta.peer_id,
+                attestation,
+                subnet_id,
+                true,
+                meta.timestamp_millis,
+                meta.topic,
+                meta.message_size,
+            ),
+            GeneratedMessage::Aggregate { aggregate, meta } => observer
+                .on_gossip_aggregate_and_proof(
+                    meta.message_id,
+                    meta.peer_id,
+                    aggregate,
+                    meta.timestamp_millis,
+                    meta.topic,
+                    meta.message_size,
+                ),
+            GeneratedMessage::BlobSidecar {
+                blob_index,
+                blob_sidecar,
+                meta,
+            } => observer.on_gossip_blob_sidecar(
+                meta.message_id,
+                meta.peer_id,
+                meta.client,
+                blob_index,
+                blob_sidecar,
+                meta.timestamp_millis,
+                meta.topic,
+                meta.message_size,
+            ),
+            GeneratedMessage::DataColumnSidecar {
+                subnet_id,
+                column_sidecar,
+                meta,
+            } => observer.on_gossip_data_column_sidecar(
+                meta.message_id,
+                meta.peer_id,
+                meta.client,
+                subnet_id,
+                column_sidecar,
+                meta.timestamp_millis,
+                meta.topic,
+                meta.message_size,
+            ),
+        }
+    }
+}
+
+/// Outcome of delivering generated traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GeneratorStats {
+    pub slots: u64,
+    pub messages: u64,
+    /// Messages the observer returned an error for
+    pub errors: u64,
+}
+
+/// Slot by slot generator of synthetic gossip traffic
+pub struct GossipGenerator<E: EthSpec> {
+    config: GeneratorConfig,
+    spec: ChainSpec,
+    rng: SplitMix64,
+    peers: Vec<(PeerId, &'static str)>,
+    next_slot: u64,
+    parent_root: Hash256,
+    _phantom: std::marker::PhantomData<E>,
+}
+
+impl<E: EthSpec> GossipGenerator<E> {
+    pub fn new(config: GeneratorConfig, spec: &ChainSpec) -> Self {
+        let mut rng = SplitMix64(config.seed);
+        let peers = (0..config.peer_count.max(1))
+            .map(|_| {
+                let agent = CLIENT_AGENTS[rng.below(CLIENT_AGENTS.len() as u64) as usize];
+                (PeerId::random(), agent)
+            })
+            .collect();
+        let parent_root = Hash256::from(rng.bytes32());
+
+        Self {
+            next_slot: config.start_slot,
+            config,
+            spec: spec.clone(),
+            rng,
+            peers,
+            parent_root,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// The slot `next_slot_messages` generates next
+    pub fn next_slot(&self) -> u64 {
+        self.next_slot
+    }
+
+    /// Every message of the next slot, ordered by arrival time
+    pub fn next_slot_messages(&mut self) -> Vec<GeneratedMessage<E>> {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        let slot_start_ms = self
+            .config
+            .genesis_time
+            .saturating_add(slot.saturating_mul(self.config.seconds_per_slot))
+            .saturating_mul(1000);
+
+        let mut messages = Vec::new();
+        let block_root = Hash256::from(self.rng.bytes32());
+        let source = Checkpoint {
+            epoch: Epoch::new(self.epoch(slot).saturating_sub(1)),
+            root: Hash256::from(self.rng.bytes32()),
+        };
+        let target = Checkpoint {
+            epoch: Epoch::new(self.epoch(slot)),
+            root: Hash256::from(self.rng.bytes32()),
+        };
+
+        // Blocks land in the first third of the slot, sidecars right after
+        if self.rng.chance(self.config.block_probability) {
+            let arrival_ms = slot_start_ms + self.rng.range(500, 3_000);
+            let block = self.block(slot);
+            let header = block.signed_block_header();
+            messages.push(GeneratedMessage::Block {
+                meta: self.meta(arrival_ms, "beacon_block", 40_000, 150_000),
+                block: Arc::new(block),
+            });
+
+            for blob_index in 0..self.config.blobs_per_block as u64 {
+                let arrival_ms = arrival_ms + self.rng.range(50, 800);
+                let topic = format!("blob_sidecar_{}", blob_index);
+                messages.push(GeneratedMessage::BlobSidecar {
+                    blob_index,
+                    blob_sidecar: Arc::new(self.blob_sidecar(blob_index, &header)),
+                    meta: self.meta(arrival_ms, &topic, 90_000, 131_072),
+                });
+            }
+
+            for _ in 0..self.config.columns_per_block {
+                let arrival_ms = arrival_ms + self.rng.range(50, 1_500);
+                let column_index = self.rng.below(DATA_COLUMN_SUBNET_COUNT);
+                let topic = format!("data_column_sidecar_{}", column_index);
+                messages.push(GeneratedMessage::DataColumnSidecar {
+                    subnet_id: DataColumnSubnetId::new(column_index),
+                    column_sidecar: Arc::new(self.column_sidecar(column_index, &header)),
+                    meta: self.meta(arrival_ms, &topic, 8_000, 20_000),
+                });
+            }
+            self.parent_root = block_root;
+        }
+
+        // Attestations are due a third into the slot, aggregates two thirds
+        let third_ms = self.config.seconds_per_slot * 1000 / 3;
+        let data = AttestationData {
+            slot: Slot::new(slot),
+            index: 0,
+            beacon_block_root: block_root,
+            source,
+            target,
+        };
+        for _ in 0..self.config.attestations_per_slot {
+            let arrival_ms = slot_start_ms + third_ms + self.rng.range(0, 1_500);
+            let subnet = self.rng.below(ATTESTATION_SUBNET_COUNT);
+            let topic = format!("beacon_attestation_{}", subnet);
+            messages.push(GeneratedMessage::Attestation {
+                attestation: Arc::new(SingleAttestation {
+                    committee_index: subnet,
+                    attester_index: self.rng.below(1_000_000),
+                    data: data.clone(),
+                    signature: AggregateSignature::empty(),
+                }),
+                subnet_id: SubnetId::new(subnet),
+                meta: self.meta(arrival_ms, &topic, 230, 260),
+            });
+        }
+        for _ in 0..self.config.aggregates_per_slot {
+            let arrival_ms = slot_start_ms + 2 * third_ms + self.rng.range(0, 1_500);
+            let aggregate = self.aggregate(&data);
+            messages.push(GeneratedMessage::Aggregate {
+                aggregate: Arc::new(aggregate),
+                meta: self.meta(arrival_ms, "beacon_aggregate_and_proof", 450, 700),
+            });
+        }
+
+        messages.sort_by_key(|message| message.meta().timestamp_millis);
+        messages
+    }
+
+    /// Deliver `slots` slots of traffic to the observer
+    /// With `paced` set every message is delivered at its arrival time, otherwise as fast
+    /// as the observer accepts them
+    pub fn run<X: Xatu<E> + ?Sized>(
+        &mut self,
+        observer: &X,
+        slots: u64,
+        paced: bool,
+    ) -> GeneratorStats {
+        let mut stats = GeneratorStats::default();
+        for _ in 0..slots {
+            for message in self.next_slot_messages() {
+                if paced {
+                    let now_ms = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_millis() as u64)
+                        .unwrap_or(0);
+                    let wait_ms = message.meta().timestamp_millis.saturating_sub(now_ms);
+                    if wait_ms > 0 {
+                        std::thread::sleep(Duration::from_millis(wait_ms));
+                    }
+                }
+                stats.messages += 1;
+                if message.deliver(observer) != ObserverResult::Ok {
+                    stats.errors += 1;
+                }
+            }
+            stats.slots += 1;
+        }
+        stats
+    }
+
+    fn epoch(&self, slot: u64) -> u64 {
+        slot / self.config.slots_per_epoch.max(1)
+    }
+
+    fn meta(&mut self, arrival_ms: u64, topic: &str, min_size: u64, max_size: u64) -> MessageMeta {
+        let (peer_id, agent) = self.peers[self.rng.below(self.peers.len() as u64) as usize];
+        MessageMeta {
+            message_id: MessageId::new(&self.rng.bytes32()[..20]),
+            peer_id,
+            client: Some(agent.to_string()),
+            timestamp_millis: arrival_ms,
+            topic: format!("/eth2/00000000/{}/ssz_snappy", topic),
+            message_size: self.rng.range(min_size, max_size) as usize,
+        }
+    }
+
+    fn block(&mut self, slot: u64) -> SignedBeaconBlock<E> {
+        let mut block = BeaconBlock::<E>::empty(&self.spec);
+        *block.slot_mut() = Slot::new(slot);
+        *block.proposer_index_mut() = self.rng.below(1_000_000);
+        *block.parent_root_mut() = self.parent_root;
+        *block.state_root_mut() = Hash256::from(self.rng.bytes32());
+        let commitments = (0..self.config.blobs_per_block.max(self.commitment_count()))
+            .map(|_| self.commitment())
+            .collect::<Vec<_>>();
+        if let Ok(block_commitments) = block.body_mut().blob_kzg_commitments_mut() {
+            *block_commitments = VariableList::from(commitments);
+        }
+        SignedBeaconBlock::from_block(block, Signature::empty())
+    }
+
+    /// Blob count implied by the column sidecars, each column carries a cell per blob
+    fn commitment_count(&self) -> u32 {
+        if self.config.columns_per_block > 0 {
+            6
+        } else {
+            0
+        }
+    }
+
+    fn blob_sidecar(&mut self, index: u64, header: &SignedBeaconBlockHeader) -> BlobSidecar<E> {
+        let mut sidecar = BlobSidecar::<E>::empty();
+        sidecar.index = index;
+        sidecar.kzg_commitment = self.commitment();
+        sidecar.signed_block_header = header.clone();
+        sidecar
+    }
+
+    fn column_sidecar(
+        &mut self,
+        index: u64,
+        header: &SignedBeaconBlockHeader,
+    ) -> DataColumnSidecar<E> {
+        let blobs = self.commitment_count() as usize;
+        DataColumnSidecar::Fulu(DataColumnSidecarFulu {
+            index,
+            column: VariableList::from(vec![FixedVector::default(); blobs]),
+            kzg_commitments: VariableList::from(
+                (0..blobs).map(|_| self.commitment()).collect::<Vec<_>>(),
+            ),
+            kzg_proofs: VariableList::from(vec![KzgProof::empty(); blobs]),
+            signed_block_header: header.clone(),
+            kzg_commitments_inclusion_proof: FixedVector::default(),
+        })
+    }
+
+    fn aggregate(&mut self, data: &AttestationData) -> SignedAggregateAndProof<E> {
+        let committee_size = 400;
+        let committee_index = self.rng.below(ATTESTATION_SUBNET_COUNT) as usize;
+        let mut aggregation_bits =
+            BitList::with_capacity(committee_size).expect("committee fits in a bitlist");
+        for index in 0..committee_size {
+            // Aggregates typically carry most of the committee
+            if self.rng.chance(0.9) {
+                let _ = aggregation_bits.set(index, true);
+            }
+        }
+        let mut committee_bits = BitVector::new();
+        let _ = committee_bits.set(committee_index, true);
+
+        SignedAggregateAndProof::Electra(SignedAggregateAndProofElectra {
+            message: AggregateAndProofElectra {
+                aggregator_index: self.rng.below(1_000_000),
+                aggregate: AttestationElectra {
+                    aggregation_bits,
+                    data: data.clone(),
+                    signature: AggregateSignature::empty(),
+                    committee_bits,
+                },
+                selection_proof: Signature::empty(),
+            },
+            signature: Signature::empty(),
+        })
+    }
+
+    fn commitment(&mut self) -> KzgCommitment {
+        let mut bytes = [0u8; 48];
+        bytes[..32].copy_from_slice(&self.rng.bytes32());
+        bytes[32..].copy_from_slice(&self.rng.bytes32()[..16]);
+        KzgCommitment(bytes)
+    }
+}
+
+/// Small deterministic PRNG, good enough for traffic shapes
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..bound`, 0 for an empty range
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next() % bound
+        }
+    }
+
+    /// Uniform in `min..max`
+    fn range(&mut self, min: u64, max: u64) -> u64 {
+        min + self.below(max.saturating_sub(min))
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    fn bytes32(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next().to_le_bytes());
+        }
+        bytes
+    }
+}
@@ -0,0 +1,53 @@
+//! User-defined transforms run on the batch thread before events are serialized
+//!
+//! Transforms can redact, tag or drop events without forking the crate. They are attached
+//! to the exporter through its config and applied in the order they were added.
+
+use crate::events::EventData;
+use std::fmt;
+use std::sync::Arc;
+
+/// A transform gets each event to edit and returns the event to send, or `None` to drop it
+pub type EventTransform = Arc<dyn Fn(&mut EventData) -> Option<EventData> + Send + Sync>;
+
+/// Ordered transforms run by one exporter
+#[derive(Clone, Default)]
+pub struct Transforms(Vec<EventTransform>);
+
+impl fmt::Debug for Transforms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transforms")
+            .field("count", &self.0.len())
+            .finish()
+    }
+}
+
+impl Transforms {
+    /// Add a transform run after the ones already added
+    pub fn push<F>(&mut self, transform: F)
+    where
+        F: Fn(&mut EventData) -> Option<EventData> + Send + Sync + 'static,
+    {
+        self.0.push(Arc::new(transform));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run every transform over a batch
+    pub(crate) fn apply(&self, batch: Vec<EventData>) -> Vec<EventData> {
+        if self.0.is_empty() {
+            return batch;
+        }
+
+        batch
+            .into_iter()
+            .filter_map(|event| {
+                self.0
+                    .iter()
+                    .try_fold(event, |mut event, transform| transform(&mut event))
+            })
+            .collect()
+    }
+}
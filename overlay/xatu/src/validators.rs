@@ -0,0 +1,35 @@
+//! Validator index to pubkey resolution
+//!
+//! Lighthouse registers a lookup backed by its validator pubkey cache; block, attestation
+//! and aggregate events are then enriched with the proposer/attester/aggregator pubkey.
+
+use std::sync::{Arc, RwLock};
+use types::PublicKeyBytes;
+
+/// Resolves a validator index to its pubkey, `None` if the index is unknown
+pub type PubkeyLookup = Arc<dyn Fn(u64) -> Option<PublicKeyBytes> + Send + Sync>;
+
+/// The lookup one set of exporters enriches events with
+#[derive(Default)]
+pub struct ValidatorPubkeys(RwLock<Option<PubkeyLookup>>);
+
+impl ValidatorPubkeys {
+    /// Set the lookup used to enrich events, replacing any previous one
+    pub fn set_pubkey_lookup<F>(&self, lookup: F)
+    where
+        F: Fn(u64) -> Option<PublicKeyBytes> + Send + Sync + 'static,
+    {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(lookup));
+    }
+
+    /// Remove the lookup, events are no longer enriched with pubkeys
+    pub fn clear_pubkey_lookup(&self) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Hex-encoded pubkey of the validator, if a lookup is set and knows the index
+    pub(crate) fn pubkey(&self, index: u64) -> Option<String> {
+        let lookup = self.0.read().unwrap_or_else(|e| e.into_inner()).clone()?;
+        lookup(index).map(|pubkey| format!("0x{}", hex::encode(pubkey.as_serialized())))
+    }
+}
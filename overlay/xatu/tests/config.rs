@@ -0,0 +1,173 @@
+//! Config file handling: the shipped example and how the enabled state is resolved
+
+use std::path::PathBuf;
+use std::time::Duration;
+use xatu::config::{EnableMode, LazyInitConfig, XatuOutput, DISABLE_XATU_ENV};
+use xatu::XatuConfig;
+
+/// Path in the temp dir unique to this test process
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("xatu-config-test-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn example_config_parses_and_round_trips() {
+    let example = XatuConfig::example();
+    assert!(example.is_enabled());
+    let expected = serde_json::to_value(&example).expect("example should serialize");
+
+    for file_name in ["example.yaml", "example.json"] {
+        let path = temp_path(file_name);
+        let path_str = path.to_str().expect("temp path should be utf-8");
+        example
+            .to_file(path_str)
+            .expect("example should be written");
+        let loaded = XatuConfig::from_file(path_str).expect("written example should load");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            serde_json::to_value(&loaded).expect("loaded example should serialize"),
+            expected,
+            "{} did not round trip",
+            file_name
+        );
+    }
+
+    // The commented file written by `write_example` loads as the same config
+    let path = temp_path("commented.yaml");
+    let path_str = path.to_str().expect("temp path should be utf-8");
+    XatuConfig::write_example(path_str).expect("commented example should be written");
+    let loaded = XatuConfig::from_file(path_str).expect("commented example should load");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(
+        serde_json::to_value(&loaded).expect("loaded example should serialize"),
+        expected
+    );
+}
+
+#[test]
+fn enabled_precedence_is_config_then_env_then_default() {
+    // A config file value wins whether or not DISABLE_XATU is set
+    for env_disabled in [false, true] {
+        for enabled in [false, true] {
+            let mode = EnableMode::resolve(Some(enabled), env_disabled);
+            assert_eq!(mode, EnableMode::Config(enabled));
+            assert_eq!(mode.is_enabled(), enabled);
+        }
+    }
+
+    // Without a config file DISABLE_XATU decides, and xatu runs by default
+    assert_eq!(EnableMode::resolve(None, true), EnableMode::EnvDisabled);
+    assert!(!EnableMode::EnvDisabled.is_enabled());
+    assert_eq!(EnableMode::resolve(None, false), EnableMode::Default);
+    assert!(EnableMode::Default.is_enabled());
+}
+
+#[test]
+fn disable_env_applies_when_the_config_fails_to_load() {
+    let missing = temp_path("missing.yaml");
+    let loaded = XatuConfig::from_file(missing.to_str().expect("temp path should be utf-8"));
+    assert!(loaded.is_err());
+
+    // The only test touching DISABLE_XATU, so it can't race another one
+    std::env::set_var(DISABLE_XATU_ENV, "1");
+    let disabled = XatuConfig::resolve_enabled(loaded.as_ref().ok());
+    let configured = XatuConfig::resolve_enabled(Some(&XatuConfig::enabled()));
+    std::env::remove_var(DISABLE_XATU_ENV);
+    let default = XatuConfig::resolve_enabled(None);
+
+    assert_eq!(disabled, EnableMode::EnvDisabled);
+    assert_eq!(configured, EnableMode::Config(true));
+    assert_eq!(default, EnableMode::Default);
+}
+
+#[test]
+fn invalid_overrides_leave_the_config_unchanged() {
+    let overrides = |vars: &'static [(&'static str, &'static str)]| {
+        move |key: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        }
+    };
+
+    // A bad header entry fails after the name and address were read
+    let mut config = XatuConfig::enabled();
+    let result = config.apply_overrides(overrides(&[
+        ("XATU_NAME", "overridden"),
+        ("XATU_OUTPUT_ADDRESS", "xatu.example.com:443"),
+        ("XATU_OUTPUT_HEADERS", "authorization"),
+    ]));
+    assert!(result.is_err());
+    assert_eq!(config.name, None);
+    assert!(config.outputs.is_none());
+
+    // Headers without any output to go to are refused
+    let result = config.apply_overrides(overrides(&[("XATU_OUTPUT_HEADERS", "a=b")]));
+    assert!(result.is_err());
+    assert!(config.outputs.is_none());
+
+    config
+        .apply_overrides(overrides(&[
+            ("XATU_OUTPUT_ADDRESS", "xatu.example.com:443"),
+            ("XATU_OUTPUT_HEADERS", "a=b"),
+        ]))
+        .expect("headers with an address should apply");
+    let outputs = config.outputs.expect("an output should be added");
+    assert_eq!(outputs[0].config.address, "xatu.example.com:443");
+    assert_eq!(outputs[0].config.headers["a"], "b");
+}
+
+#[cfg(not(feature = "no-sidecar"))]
+#[test]
+fn native_only_output_settings_are_rejected_by_sidecar_builds() {
+    let outputs = |config: serde_json::Value| -> Vec<XatuOutput> {
+        serde_json::from_value(serde_json::json!([
+            { "name": "primary", "type": "http", "config": { "address": "http://a:8081" } },
+            { "name": "secondary", "type": "http", "config": config },
+        ]))
+        .expect("outputs should parse")
+    };
+
+    let plain = outputs(serde_json::json!({ "address": "http://b:8081" }));
+    assert!(XatuOutput::validate_all(&plain).is_ok());
+
+    // The sidecar would send to both outputs and the first address only
+    let fallback = outputs(serde_json::json!({
+        "address": "http://b:8081",
+        "fallbackFor": "primary",
+    }));
+    let error = XatuOutput::validate_all(&fallback).expect_err("fallbackFor should be rejected");
+    assert!(error.to_string().contains("fallbackFor"));
+    let balanced = outputs(serde_json::json!({
+        "address": "http://b:8081",
+        "addresses": ["http://c:8081"],
+    }));
+    let error = XatuOutput::validate_all(&balanced).expect_err("addresses should be rejected");
+    assert!(error.to_string().contains("addresses"));
+}
+
+#[test]
+fn durations_parse_like_go() {
+    let retry_interval = |value: &str| {
+        LazyInitConfig {
+            retry_interval: Some(value.to_string()),
+            ..Default::default()
+        }
+        .retry_interval()
+    };
+
+    assert_eq!(retry_interval("1m30s").unwrap(), Duration::from_secs(90));
+    assert_eq!(retry_interval("1.5h").unwrap(), Duration::from_secs(5400));
+    assert_eq!(retry_interval("250ms").unwrap(), Duration::from_millis(250));
+    assert_eq!(retry_interval("15").unwrap(), Duration::from_secs(15));
+    for invalid in ["", "1m30", "1x", "1.2.3s"] {
+        assert!(
+            retry_interval(invalid).is_err(),
+            "{:?} should be rejected",
+            invalid
+        );
+    }
+    // Overflow is an error rather than a panic
+    let error = retry_interval("99999999999999h").expect_err("overflow should be rejected");
+    assert!(error.to_string().contains("too long"));
+}
@@ -0,0 +1,329 @@
+//! End-to-end tests of `XatuObserver` against the in-process fake sidecar
+//!
+//! Events go through the real batch thread and FFI boundary and are recorded by the fake.
+//! Run with `cargo test -p xatu --features fake-sidecar`. The fake's state is process-global,
+//! so everything runs as one sequential test.
+
+#![cfg(feature = "fake-sidecar")]
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use types::{ChainSpec, ForkName, Hash256, MainnetEthSpec};
+use xatu::events::EVENT_SCHEMA_VERSION;
+use xatu::fake_sidecar;
+use xatu::testing::generator::{GeneratedMessage, GeneratorConfig, GossipGenerator};
+use xatu::{ExporterPhase, NetworkInfo, ObserverResult, Xatu, XatuConfig, XatuObserver};
+
+type E = MainnetEthSpec;
+
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn observer_exports_through_fake_sidecar() {
+    let generator_config = GeneratorConfig::fulu();
+    let network_info = generator_config.network_info();
+
+    let mut config = XatuConfig::enabled();
+    config.name = Some("fake-sidecar-test".to_string());
+    let observer =
+        XatuObserver::new_with_full_config(&config.get_full_config(), Some(network_info.clone()))
+            .expect("observer should start against the fake sidecar");
+    let handle = observer.handle().expect("observer should expose a handle");
+    assert!(observer.context().handle().is_some());
+    assert!(fake_sidecar::is_initialized());
+    assert_eq!(handle.phase(), ExporterPhase::Running);
+    assert!(fake_sidecar::config().is_some_and(|yaml| yaml.contains("fake-sidecar-test")));
+
+    // Lifecycle events reach the sidecar on flush
+    let result = <XatuObserver as Xatu<E>>::on_start(&observer, &network_info);
+    assert_eq!(result, ObserverResult::Ok);
+    handle.flush(FLUSH_TIMEOUT).expect("flush should succeed");
+    let event_types: Vec<String> = fake_sidecar::batches()
+        .iter()
+        .flat_map(|batch| batch.event_types())
+        .collect();
+    assert_eq!(event_types, ["SESSION_START", "NODE_INFO"]);
+    assert!(fake_sidecar::batches()
+        .iter()
+        .all(|batch| batch.schema_version == EVENT_SCHEMA_VERSION));
+
+    // A batch the sidecar fails to send is retried under a new id
+    fake_sidecar::reset();
+    fake_sidecar::fail_next(1, -3);
+    let _ = <XatuObserver as Xatu<E>>::on_start(&observer, &network_info);
+    handle
+        .flush(FLUSH_TIMEOUT)
+        .expect("retried flush should succeed");
+    let batches = fake_sidecar::batches();
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[0].status, -3);
+    assert_eq!(batches[1].status, 0);
+    assert_ne!(batches[0].batch_id, batches[1].batch_id);
+    assert_eq!(batches[0].events, batches[1].events);
+    assert_eq!(handle.stats().batches_retried, 1);
+    assert!(handle.is_healthy());
+
+    // Generated gossip traffic is delivered in full
+    fake_sidecar::reset();
+    let spec = ForkName::Fulu.make_genesis_spec(ChainSpec::mainnet());
+    let mut generator = GossipGenerator::<E>::new(generator_config, &spec);
+    let stats = generator.run(&observer, 2, false);
+    assert_eq!(stats.errors, 0);
+    observer.flush().expect("observer flush should succeed");
+    assert!(fake_sidecar::wait_for_delivered(
+        stats.messages as usize,
+        FLUSH_TIMEOUT
+    ));
+    assert_eq!(
+        fake_sidecar::delivered_events().len(),
+        stats.messages as usize
+    );
+    assert_eq!(handle.stats().batches_in_flight, 0);
+
+    // A corrected genesis time reaches the sidecar
+    let genesis_time = network_info.genesis_time + 12;
+    let result = <XatuObserver as Xatu<E>>::on_genesis_time_changed(&observer, genesis_time);
+    assert_eq!(result, ObserverResult::Ok);
+    assert_eq!(fake_sidecar::genesis_time(), Some(genesis_time));
+
+    // A relaunched network restarts the sidecar for it and is announced by NETWORK_CHANGED
+    fake_sidecar::reset();
+    observer
+        .context()
+        .networks
+        .set_genesis_validators_root(Hash256::repeat_byte(0xbb));
+    let relaunched = NetworkInfo {
+        network_id: network_info.network_id + 1,
+        genesis_time,
+        ..network_info.clone()
+    };
+    let result = <XatuObserver as Xatu<E>>::on_network_changed(&observer, &relaunched);
+    assert_eq!(result, ObserverResult::Ok);
+    // The restart runs on the batch thread, the flush is only handled once it is done
+    handle
+        .flush(FLUSH_TIMEOUT)
+        .expect("flush after the restart should succeed");
+    assert_eq!(handle.phase(), ExporterPhase::Running);
+    let network_id = format!("id: {}", relaunched.network_id);
+    assert!(fake_sidecar::config().is_some_and(|yaml| yaml.contains(&network_id)));
+    let events = fake_sidecar::delivered_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event_type"], "NETWORK_CHANGED");
+    assert_eq!(events[0]["previous_network_id"], network_info.network_id);
+    assert_eq!(events[0]["network_id"], relaunched.network_id);
+    assert_eq!(
+        events[0]["genesis_validators_root"],
+        format!("0x{}", "bb".repeat(32))
+    );
+    let result = <XatuObserver as Xatu<E>>::on_network_changed(&observer, &relaunched);
+    assert_eq!(result, ObserverResult::Ok);
+    handle.flush(FLUSH_TIMEOUT).expect("flush should succeed");
+    assert_eq!(fake_sidecar::delivered_events().len(), 1);
+
+    // Shutdown drains the queue first, then closes the sidecar
+    fake_sidecar::reset();
+    let _ = <XatuObserver as Xatu<E>>::on_start(&observer, &network_info);
+    handle.drain(FLUSH_TIMEOUT).expect("drain should succeed");
+    assert_eq!(handle.phase(), ExporterPhase::ShuttingDown);
+    let event_types: Vec<String> = fake_sidecar::batches()
+        .iter()
+        .flat_map(|batch| batch.event_types())
+        .collect();
+    assert_eq!(event_types, ["SESSION_START", "NODE_INFO"]);
+    let dropped = handle.stats().events_dropped;
+    let result = <XatuObserver as Xatu<E>>::on_start(&observer, &network_info);
+    assert_eq!(result.error_count(), 2);
+    assert_eq!(handle.stats().events_dropped, dropped + 2);
+    handle.close(FLUSH_TIMEOUT).expect("close should succeed");
+    assert!(!fake_sidecar::is_initialized());
+    assert_eq!(handle.phase(), ExporterPhase::Closed);
+    let result = <XatuObserver as Xatu<E>>::on_shutdown(&observer);
+    assert_eq!(result, ObserverResult::Ok);
+    drop(observer);
+
+    // A sidecar older than any schema the crate can write is refused
+    fake_sidecar::set_schema_version(0);
+    assert!(XatuObserver::new_with_full_config(
+        &config.get_full_config(),
+        Some(network_info.clone())
+    )
+    .is_err());
+
+    // An older sidecar gets batches in its schema version
+    fake_sidecar::reset();
+    fake_sidecar::set_schema_version(1);
+    let observer =
+        XatuObserver::new_with_full_config(&config.get_full_config(), Some(network_info.clone()))
+            .expect("observer should start against an older sidecar");
+    assert!(fake_sidecar::config().is_some_and(|yaml| yaml.contains("schema_version: 1")));
+    let _ = <XatuObserver as Xatu<E>>::on_start(&observer, &network_info);
+    observer
+        .handle()
+        .expect("observer should expose a handle")
+        .flush(FLUSH_TIMEOUT)
+        .expect("downgraded flush should succeed");
+    let batches = fake_sidecar::batches();
+    assert!(!batches.is_empty());
+    assert!(batches.iter().all(|batch| batch.schema_version == 1));
+    assert_eq!(batches[0].events[1]["event_schema_version"], 1);
+    let _ = <XatuObserver as Xatu<E>>::on_shutdown(&observer);
+    fake_sidecar::set_schema_version(EVENT_SCHEMA_VERSION);
+    drop(observer);
+
+    // With lazyInit the observer starts at once and buffers events until an init succeeds
+    fake_sidecar::reset();
+    fake_sidecar::fail_next_inits(2);
+    let lazy_config: XatuConfig = serde_json::from_value(serde_json::json!({
+        "enabled": true,
+        "lazyInit": { "retryInterval": "50ms" },
+    }))
+    .expect("lazy init config should parse");
+    let observer = XatuObserver::new_with_full_config(
+        &lazy_config.get_full_config(),
+        Some(network_info.clone()),
+    )
+    .expect("observer should start before the sidecar is up");
+    let handle = observer.handle().expect("observer should expose a handle");
+    let _ = <XatuObserver as Xatu<E>>::on_start(&observer, &network_info);
+    let deadline = std::time::Instant::now() + FLUSH_TIMEOUT;
+    while handle.phase() != ExporterPhase::Running {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "lazy init did not succeed"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    handle
+        .flush(FLUSH_TIMEOUT)
+        .expect("flush after lazy init should succeed");
+    let event_types: Vec<String> = fake_sidecar::batches()
+        .iter()
+        .flat_map(|batch| batch.event_types())
+        .collect();
+    assert_eq!(event_types, ["SESSION_START", "NODE_INFO"]);
+    let _ = <XatuObserver as Xatu<E>>::on_shutdown(&observer);
+    drop(observer);
+
+    // Output auth tokens are injected at init and pushed again once they rotate
+    let token_file = std::env::temp_dir().join(format!("xatu-token-{}", std::process::id()));
+    std::fs::write(&token_file, "first\n").expect("token file should be writable");
+    let config: XatuConfig = serde_json::from_value(serde_json::json!({
+        "enabled": true,
+        "outputs": [{
+            "name": "authed",
+            "type": "xatu",
+            "config": {
+                "address": "localhost:8080",
+                "authToken": {
+                    "file": token_file,
+                    "prefix": "Bearer ",
+                    "refreshInterval": "50ms",
+                },
+            },
+        }],
+    }))
+    .expect("auth token config should parse");
+    let observer =
+        XatuObserver::new_with_full_config(&config.get_full_config(), Some(network_info.clone()))
+            .expect("observer should start with an auth token");
+    assert!(fake_sidecar::config().is_some_and(|yaml| yaml.contains("Bearer first")));
+    std::fs::write(&token_file, "second\n").expect("token file should be writable");
+    let deadline = std::time::Instant::now() + FLUSH_TIMEOUT;
+    while fake_sidecar::output_header("authed", "authorization").as_deref() != Some("Bearer second")
+    {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "token was not refreshed"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let _ = <XatuObserver as Xatu<E>>::on_shutdown(&observer);
+    let _ = std::fs::remove_file(&token_file);
+    drop(observer);
+
+    // Data column events fill the byte budget long before the event limit
+    fake_sidecar::reset();
+    let max_batch_bytes = 16 * 1024;
+    let budget_config: XatuConfig = serde_json::from_value(serde_json::json!({
+        "enabled": true,
+        "maxBatchBytes": max_batch_bytes,
+    }))
+    .expect("byte budget config should parse");
+    let observer = XatuObserver::new_with_full_config(
+        &budget_config.get_full_config(),
+        Some(network_info.clone()),
+    )
+    .expect("observer should start with a byte budget");
+    let column_config = GeneratorConfig {
+        block_probability: 1.0,
+        attestations_per_slot: 0,
+        aggregates_per_slot: 0,
+        columns_per_block: 128,
+        ..GeneratorConfig::fulu()
+    };
+    let mut generator = GossipGenerator::<E>::new(column_config, &spec);
+    let columns: Vec<_> = (0..4)
+        .flat_map(|_| generator.next_slot_messages())
+        .filter(|message| matches!(message, GeneratedMessage::DataColumnSidecar { .. }))
+        .collect();
+    let column_count = columns.len();
+    for message in columns {
+        assert_eq!(message.deliver(&observer), ObserverResult::Ok);
+    }
+    observer.flush().expect("observer flush should succeed");
+    assert!(fake_sidecar::wait_for_delivered(
+        column_count,
+        FLUSH_TIMEOUT
+    ));
+    let batches = fake_sidecar::batches();
+    assert!(batches.len() > 1);
+    let largest_event = batches
+        .iter()
+        .flat_map(|batch| &batch.events)
+        .map(|event| event.to_string().len())
+        .max()
+        .unwrap_or_default();
+    for batch in &batches {
+        assert!(batch.events.len() < column_count);
+        let batch_bytes: usize = batch
+            .events
+            .iter()
+            .map(|event| event.to_string().len())
+            .sum();
+        assert!(batch_bytes < max_batch_bytes + largest_event);
+    }
+    let _ = <XatuObserver as Xatu<E>>::on_shutdown(&observer);
+    drop(observer);
+
+    // Gossip received before genesis is held back, then follows the GENESIS marker
+    fake_sidecar::reset();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock should be after the unix epoch")
+        .as_secs();
+    let pre_genesis_info = NetworkInfo {
+        genesis_time: now + 2,
+        ..network_info.clone()
+    };
+    let observer = XatuObserver::new_with_full_config(
+        &XatuConfig::enabled().get_full_config(),
+        Some(pre_genesis_info),
+    )
+    .expect("observer should start before genesis");
+    let mut generator = GossipGenerator::<E>::new(GeneratorConfig::fulu(), &spec);
+    let held: Vec<_> = generator.next_slot_messages().into_iter().take(5).collect();
+    for message in held {
+        assert_eq!(message.deliver(&observer), ObserverResult::Ok);
+    }
+    observer
+        .flush()
+        .expect("flush before genesis should succeed");
+    assert!(fake_sidecar::delivered_events().is_empty());
+    assert!(fake_sidecar::wait_for_delivered(6, FLUSH_TIMEOUT));
+    let events = fake_sidecar::delivered_events();
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[0]["event_type"], "GENESIS");
+    assert_eq!(events[0]["events_buffered"], 5);
+    assert_eq!(events[0]["events_dropped"], 0);
+    assert!(events[1..].iter().all(|event| event.get("topic").is_some()));
+    let _ = <XatuObserver as Xatu<E>>::on_shutdown(&observer);
+}
@@ -0,0 +1,599 @@
+//! Golden snapshots of the exported event JSON
+//!
+//! Every `EventData` variant is built fully populated and compared against the checked-in
+//! JSON in `tests/golden`. Fixtures are struct literals, so adding a field fails to compile
+//! until the fixture and its snapshot are updated; renaming or retyping one fails the test.
+//!
+//! Run with `XATU_UPDATE_GOLDEN=1` to rewrite the snapshots after an intended schema change.
+
+use serde_json::Value;
+use std::path::PathBuf;
+use xatu::client::ClientFingerprint;
+use xatu::events::{
+    AggregateAndProofEvent, AttestationEvent, AttestationGroupEvent, BandwidthEvent,
+    BeaconBlockEvent, BlobSidecarEvent, ClockSkewEvent, CustodyEvent, DataColumnSidecarEvent,
+    EventData, EventHeader, ExporterStatsEvent, GenesisEvent, GossipHeader, LatencySummary,
+    NetworkChangedEvent, NetworkMismatchEvent, NodeInfoEvent, OutputFailoverEvent, PeerBandwidth,
+    PeerCustodyEvent, PropagationLatencyEvent, SessionStartEvent, SlotAggregateEvent,
+    SubnetCoverageEvent, TopicBandwidth,
+};
+use xatu::peers::{ConnectionDirection, PeerSummary};
+use xatu::schema::expand_dictionary;
+
+const UPDATE_ENV: &str = "XATU_UPDATE_GOLDEN";
+
+const SESSION_ID: &str = "7f1c2a4e-0d5b-4c8e-9f3a-1b2c3d4e5f60";
+const PEER_ID: &str = "16Uiu2HAm7xCg2bBHZXi5Jfr8Y4Z9oQm1kV7nT3sR6wP2dE4fG5hJ";
+const MESSAGE_ID: &str = "9a3c5e7f11223344";
+const TIMESTAMP_MS: i64 = 1_700_000_004_250;
+const CLOCK_OFFSET_MS: i64 = -12;
+
+fn event_id(n: u8) -> String {
+    format!("00000000-0000-4000-8000-0000000000{:02x}", n)
+}
+
+fn root(byte: u8) -> String {
+    format!("0x{}", hex::encode([byte; 32]))
+}
+
+fn client() -> Option<String> {
+    Some("Lighthouse/v7.1.0-abcdef0/x86_64-linux".to_string())
+}
+
+fn client_fingerprint() -> Option<ClientFingerprint> {
+    Some(ClientFingerprint {
+        implementation: "lighthouse".to_string(),
+        version: Some("7.1.0".to_string()),
+        platform: Some("x86_64-linux".to_string()),
+    })
+}
+
+fn peer() -> Option<PeerSummary> {
+    Some(PeerSummary {
+        agent: client(),
+        direction: Some(ConnectionDirection::Outbound),
+        remote_addr: Some("/ip4/10.0.0.2/tcp/9000".to_string()),
+        connection_age_ms: 60_000,
+        protocols: vec!["/meshsub/1.1.0".to_string()],
+    })
+}
+
+fn header(n: u8, sequence: u64) -> EventHeader {
+    EventHeader {
+        session_id: SESSION_ID.to_string(),
+        event_id: event_id(n),
+        sequence,
+        clock_offset_ms: Some(CLOCK_OFFSET_MS),
+    }
+}
+
+/// Header of a gossip event for slot 320 that arrived at `TIMESTAMP_MS`
+fn gossip_header(n: u8, sequence: u64, fork_name: &str, fork_digest: &str) -> GossipHeader {
+    GossipHeader {
+        header: header(n, sequence),
+        propagation_slot_start_diff_ms: 4_250,
+        wallclock_slot: 320,
+        wallclock_epoch: 10,
+        fork_name: Some(fork_name.to_string()),
+        fork_digest: Some(fork_digest.to_string()),
+        topic_fork_name: Some(fork_name.to_string()),
+        topic_fork_digest: Some(fork_digest.to_string()),
+        client_fingerprint: client_fingerprint(),
+        peer: peer(),
+    }
+}
+
+fn beacon_block() -> EventData {
+    BeaconBlockEvent {
+        peer_id: PEER_ID.to_string(),
+        message_id: MESSAGE_ID.to_string(),
+        topic: "/eth2/6a95a1a9/beacon_block/ssz_snappy".to_string(),
+        message_size: 48_213,
+        wire_size: 48_213,
+        decoded_size: 121_876,
+        timestamp_ms: TIMESTAMP_MS,
+        header: gossip_header(1, 1, "electra", "0x6a95a1a9"),
+        slot: 320,
+        epoch: 10,
+        block_root: root(0x11),
+        proposer_index: 4_242,
+        proposer_pubkey: Some(format!("0x{}", hex::encode([0xaa; 48]))),
+        parent_root: root(0x12),
+        state_root: root(0x13),
+        graffiti: "dimhouse".to_string(),
+        attestation_count: 8,
+        deposit_count: 1,
+        voluntary_exit_count: 2,
+        sync_aggregate_participation: Some(510),
+        blob_kzg_commitments_count: Some(6),
+        max_blobs_per_block: Some(9),
+        execution_block_hash: Some(root(0x14)),
+        fee_recipient: Some(format!("0x{}", hex::encode([0x15; 20]))),
+        base_fee_per_gas: Some("7000000000".to_string()),
+        excess_blob_gas: Some(393_216),
+        deposit_request_count: Some(1),
+        withdrawal_request_count: Some(0),
+        consolidation_request_count: Some(2),
+        // Electra block, the limit is only exported from Fulu on
+        max_blob_commitments_per_block: None,
+    }
+    .into()
+}
+
+fn attestation() -> EventData {
+    AttestationEvent {
+        peer_id: PEER_ID.to_string(),
+        slot: 320,
+        epoch: 10,
+        attestation_data_root: root(0x21),
+        subnet_id: 17,
+        timestamp_ms: TIMESTAMP_MS,
+        header: gossip_header(2, 2, "electra", "0x6a95a1a9"),
+        message_id: MESSAGE_ID.to_string(),
+        should_process: true,
+        topic: "/eth2/6a95a1a9/beacon_attestation_17/ssz_snappy".to_string(),
+        message_size: 245,
+        source_epoch: 8,
+        source_root: root(0x22),
+        target_epoch: 10,
+        target_root: root(0x23),
+        committee_index: 3,
+        aggregation_bits: "0x".to_string(),
+        signature: format!("0x{}", hex::encode([0x24; 96])),
+        attester_index: Some(1_337),
+        attester_pubkey: Some(format!("0x{}", hex::encode([0xbb; 48]))),
+    }
+    .into()
+}
+
+fn aggregate_and_proof() -> EventData {
+    AggregateAndProofEvent {
+        peer_id: PEER_ID.to_string(),
+        slot: 320,
+        epoch: 10,
+        attestation_data_root: root(0x31),
+        aggregator_index: 2_024,
+        timestamp_ms: TIMESTAMP_MS,
+        header: gossip_header(3, 3, "electra", "0x6a95a1a9"),
+        message_id: MESSAGE_ID.to_string(),
+        topic: "/eth2/6a95a1a9/beacon_aggregate_and_proof/ssz_snappy".to_string(),
+        message_size: 612,
+        source_epoch: 8,
+        source_root: root(0x32),
+        target_epoch: 10,
+        target_root: root(0x33),
+        committee_index: 5,
+        committee_bits: Some("0x2000000000000000".to_string()),
+        aggregation_bits: "0xff0f".to_string(),
+        aggregation_bits_set_count: 12,
+        signature: format!("0x{}", hex::encode([0x34; 96])),
+        aggregator_pubkey: Some(format!("0x{}", hex::encode([0xcc; 48]))),
+    }
+    .into()
+}
+
+fn blob_sidecar() -> EventData {
+    BlobSidecarEvent {
+        peer_id: PEER_ID.to_string(),
+        slot: 320,
+        epoch: 10,
+        block_root: root(0x41),
+        parent_root: root(0x42),
+        state_root: root(0x43),
+        proposer_index: 4_242,
+        blob_index: 2,
+        kzg_commitment: format!("0x{}", hex::encode([0x44; 48])),
+        versioned_hash: root(0x45),
+        timestamp_ms: TIMESTAMP_MS,
+        header: gossip_header(4, 4, "electra", "0x6a95a1a9"),
+        message_id: MESSAGE_ID.to_string(),
+        client: client(),
+        topic: "/eth2/6a95a1a9/blob_sidecar_2/ssz_snappy".to_string(),
+        message_size: 98_112,
+        wire_size: 98_112,
+        decoded_size: 131_928,
+    }
+    .into()
+}
+
+fn data_column_sidecar() -> EventData {
+    DataColumnSidecarEvent {
+        peer_id: PEER_ID.to_string(),
+        slot: 320,
+        epoch: 10,
+        block_root: root(0x51),
+        parent_root: root(0x52),
+        state_root: root(0x53),
+        proposer_index: 4_242,
+        column_index: 77,
+        subnet_id: 13,
+        kzg_commitments_count: 6,
+        cell_count: 6,
+        kzg_proofs_count: 6,
+        timestamp_ms: TIMESTAMP_MS,
+        header: gossip_header(5, 5, "fulu", "0x1e3a5c7d"),
+        message_id: MESSAGE_ID.to_string(),
+        client: client(),
+        topic: "/eth2/1e3a5c7d/data_column_sidecar_13/ssz_snappy".to_string(),
+        message_size: 14_020,
+        wire_size: 14_020,
+        decoded_size: 13_912,
+    }
+    .into()
+}
+
+fn session_start() -> EventData {
+    SessionStartEvent {
+        header: header(6, 0),
+        timestamp_ms: TIMESTAMP_MS,
+        node_name: "dimhouse-node".to_string(),
+        implementation: "lighthouse".to_string(),
+        version: "0.1.0".to_string(),
+        os: "linux".to_string(),
+        arch: "x86_64".to_string(),
+        network_name: "hoodi".to_string(),
+        network_id: 560_048,
+        genesis_time: 1_742_213_400,
+        seconds_per_slot: 12,
+        slots_per_epoch: 32,
+    }
+    .into()
+}
+
+fn node_info() -> EventData {
+    NodeInfoEvent {
+        header: header(7, 1),
+        timestamp_ms: TIMESTAMP_MS,
+        node_name: "dimhouse-node".to_string(),
+        lighthouse_version: "Lighthouse/v7.1.0-abcdef0".to_string(),
+        xatu_version: "0.1.0".to_string(),
+        sidecar_version: "v0.0.6".to_string(),
+        event_schema_version: 1,
+        network_name: "hoodi".to_string(),
+        genesis_time: 1_742_213_400,
+        custody_group_count: Some(4),
+        enabled_event_types: vec!["BEACON_BLOCK".to_string(), "ATTESTATION".to_string()],
+    }
+    .into()
+}
+
+fn exporter_stats() -> EventData {
+    ExporterStatsEvent {
+        header: header(8, 9),
+        timestamp_ms: TIMESTAMP_MS,
+        events_queued: 1_000,
+        events_dropped: 3,
+        events_sent: 990,
+        batches_sent: 12,
+        send_errors: 1,
+        consecutive_send_failures: 0,
+        batches_in_flight: 1,
+        batches_retried: 1,
+        queue_len: 7,
+    }
+    .into()
+}
+
+fn network_mismatch() -> EventData {
+    NetworkMismatchEvent {
+        header: header(9, 2),
+        timestamp_ms: TIMESTAMP_MS,
+        node_name: "dimhouse-node".to_string(),
+        configured_network_name: "holesky".to_string(),
+        detected_network_name: "hoodi".to_string(),
+        genesis_validators_root:
+            "0x212f13fc4df078b6cb7db228f1c8307566dcecf900867401a92023d7ba99cb5f".to_string(),
+    }
+    .into()
+}
+
+fn custody() -> EventData {
+    CustodyEvent {
+        header: header(10, 14),
+        timestamp_ms: TIMESTAMP_MS,
+        node_name: "dimhouse-node".to_string(),
+        custody_group_count: 8,
+        previous_custody_group_count: Some(4),
+        custody_columns: vec![3, 17, 42, 55, 71, 88, 101, 126],
+    }
+    .into()
+}
+
+fn peer_custody() -> EventData {
+    PeerCustodyEvent {
+        header: header(11, 15),
+        timestamp_ms: TIMESTAMP_MS,
+        peer_id: PEER_ID.to_string(),
+        peer: peer(),
+        custody_group_count: 4,
+        previous_custody_group_count: None,
+        custody_columns: vec![9, 33, 64, 120],
+    }
+    .into()
+}
+
+fn subnet_coverage() -> EventData {
+    SubnetCoverageEvent {
+        header: header(12, 16),
+        timestamp_ms: TIMESTAMP_MS,
+        peer_count: 50,
+        attestation_subnet_peers: vec![12, 9, 0, 15, 7, 11, 8, 10],
+        sync_committee_subnet_peers: vec![4, 2, 5, 3],
+        data_column_subnet_peers: vec![6, 5, 7, 0, 4, 6, 8, 5],
+    }
+    .into()
+}
+
+fn bandwidth() -> EventData {
+    BandwidthEvent {
+        header: header(13, 17),
+        timestamp_ms: TIMESTAMP_MS,
+        interval_ms: 60_004,
+        topics: vec![
+            TopicBandwidth {
+                topic: "/eth2/6a95a1a9/beacon_block/ssz_snappy".to_string(),
+                bytes_in: 2_410_650,
+                bytes_out: 48_213,
+                messages_in: 50,
+                messages_out: 1,
+            },
+            TopicBandwidth {
+                topic: "/eth2/6a95a1a9/data_column_sidecar_17/ssz_snappy".to_string(),
+                bytes_in: 12_582_912,
+                bytes_out: 0,
+                messages_in: 96,
+                messages_out: 0,
+            },
+        ],
+        peers: vec![PeerBandwidth {
+            peer_id: PEER_ID.to_string(),
+            bytes_in: 1_310_720,
+            bytes_out: 48_213,
+            messages_in: 12,
+            messages_out: 1,
+        }],
+    }
+    .into()
+}
+
+fn propagation_latency() -> EventData {
+    PropagationLatencyEvent {
+        header: header(14, 18),
+        timestamp_ms: TIMESTAMP_MS,
+        epoch: 52_042,
+        summaries: vec![
+            LatencySummary {
+                event_type: "BEACON_BLOCK".to_string(),
+                count: 31,
+                min_ms: 1_012,
+                p50_ms: 1_649,
+                p90_ms: 2_389,
+                p99_ms: 3_104,
+                max_ms: 3_104,
+            },
+            LatencySummary {
+                event_type: "ATTESTATION".to_string(),
+                count: 48_211,
+                min_ms: -37,
+                p50_ms: 4_219,
+                p90_ms: 5_079,
+                p99_ms: 7_939,
+                max_ms: 23_511,
+            },
+        ],
+    }
+    .into()
+}
+
+fn slot_aggregate() -> EventData {
+    SlotAggregateEvent {
+        header: header(15, 19),
+        timestamp_ms: TIMESTAMP_MS,
+        slot: 1_665_347,
+        epoch: 52_042,
+        aggregated_event_type: "ATTESTATION".to_string(),
+        count: 1_874,
+        unique_peers: 61,
+        min_arrival_ms: 3_981,
+        median_arrival_ms: 4_233,
+    }
+    .into()
+}
+
+fn attestation_group() -> EventData {
+    AttestationGroupEvent {
+        event_id: event_id(16),
+        sequences: vec![20, 23, 24],
+        session_id: SESSION_ID.to_string(),
+        timestamp_ms: TIMESTAMP_MS,
+        clock_offset_ms: Some(CLOCK_OFFSET_MS),
+        slot: 320,
+        epoch: 10,
+        committee_index: 3,
+        attestation_data_root: root(0x21),
+        source_epoch: 8,
+        source_root: root(0x22),
+        target_epoch: 10,
+        target_root: root(0x23),
+        count: 3,
+        peers: vec![
+            PEER_ID.to_string(),
+            "16Uiu2HAmQ3nR8sT2vW5xY7zA9bC1dE3fG5hJ7kL9mN1pQ3rS5tUv".to_string(),
+        ],
+        attester_indices: vec![1_337, 1_402, 1_519],
+        propagation_slot_start_diff_ms: 4_250,
+    }
+    .into()
+}
+
+fn clock_skew() -> EventData {
+    ClockSkewEvent {
+        header: header(17, 25),
+        timestamp_ms: TIMESTAMP_MS,
+        threshold_ms: 500,
+        block_samples: 64,
+        median_block_arrival_ms: Some(-812),
+        peer_implied_skew_ms: Some(812),
+    }
+    .into()
+}
+
+fn output_failover() -> EventData {
+    OutputFailoverEvent {
+        header: header(18, 26),
+        timestamp_ms: TIMESTAMP_MS,
+        primary_output: "xatu".to_string(),
+        fallback_output: "spool".to_string(),
+        active_output: "spool".to_string(),
+        consecutive_failures: 3,
+        error: Some("Failed to send event".to_string()),
+    }
+    .into()
+}
+
+fn genesis() -> EventData {
+    GenesisEvent {
+        header: header(19, 27),
+        timestamp_ms: TIMESTAMP_MS,
+        genesis_time: 1_700_000_004,
+        events_buffered: 412,
+        events_dropped: 0,
+    }
+    .into()
+}
+
+fn network_changed() -> EventData {
+    NetworkChangedEvent {
+        header: header(20, 28),
+        timestamp_ms: TIMESTAMP_MS,
+        node_name: "dimhouse-node".to_string(),
+        network_name: "devnet-4".to_string(),
+        network_id: 7_032_118_029,
+        genesis_time: 1_700_003_600,
+        genesis_validators_root: Some(root(0xbb)),
+        previous_network_name: "devnet-4".to_string(),
+        previous_network_id: 7_032_118_028,
+        previous_genesis_time: 1_699_996_400,
+        previous_genesis_validators_root: Some(root(0xaa)),
+    }
+    .into()
+}
+
+/// One fixture per variant, keyed by the snapshot file name
+fn fixtures() -> Vec<(&'static str, EventData)> {
+    vec![
+        ("beacon_block", beacon_block()),
+        ("attestation", attestation()),
+        ("aggregate_and_proof", aggregate_and_proof()),
+        ("blob_sidecar", blob_sidecar()),
+        ("data_column_sidecar", data_column_sidecar()),
+        ("session_start", session_start()),
+        ("node_info", node_info()),
+        ("exporter_stats", exporter_stats()),
+        ("network_mismatch", network_mismatch()),
+        ("custody", custody()),
+        ("peer_custody", peer_custody()),
+        ("subnet_coverage", subnet_coverage()),
+        ("bandwidth", bandwidth()),
+        ("propagation_latency", propagation_latency()),
+        ("slot_aggregate", slot_aggregate()),
+        ("attestation_group", attestation_group()),
+        ("clock_skew", clock_skew()),
+        ("output_failover", output_failover()),
+        ("genesis", genesis()),
+        ("network_changed", network_changed()),
+    ]
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.json", name))
+}
+
+#[test]
+fn events_match_golden_json() {
+    let update = std::env::var_os(UPDATE_ENV).is_some();
+    let mut mismatches = Vec::new();
+
+    for (name, event) in fixtures() {
+        let actual = serde_json::to_value(&event).expect("event should serialize");
+        let path = golden_path(name);
+
+        if update {
+            let mut json = serde_json::to_string_pretty(&actual).unwrap();
+            json.push('\n');
+            std::fs::write(&path, json).expect("golden file should be writable");
+            continue;
+        }
+
+        let golden = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("missing golden file {}: {}", path.display(), e));
+        let expected: Value = serde_json::from_str(&golden)
+            .unwrap_or_else(|e| panic!("invalid golden file {}: {}", path.display(), e));
+        if actual != expected {
+            mismatches.push(format!(
+                "{}:\nexpected {}\n  actual {}",
+                name,
+                serde_json::to_string_pretty(&expected).unwrap(),
+                serde_json::to_string_pretty(&actual).unwrap()
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "event JSON drifted from the golden snapshots (rerun with {}=1 if intended):\n{}",
+        UPDATE_ENV,
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn golden_json_round_trips() {
+    for (name, event) in fixtures() {
+        let golden = std::fs::read_to_string(golden_path(name)).unwrap();
+        let parsed: EventData = serde_json::from_str(&golden)
+            .unwrap_or_else(|e| panic!("{} golden file doesn't parse as EventData: {}", name, e));
+        assert_eq!(
+            parsed, event,
+            "{} golden file parsed to a different event",
+            name
+        );
+    }
+}
+
+#[test]
+fn dictionary_batches_expand_to_the_plain_events() {
+    let events: Vec<EventData> = fixtures().into_iter().map(|(_, event)| event).collect();
+    let plain: Value =
+        serde_json::from_str(&EventData::to_json_batch(&events, 2).unwrap()).unwrap();
+    let encoded = EventData::to_json_batch(&events, 3).unwrap();
+    let mut batch: Value = serde_json::from_str(&encoded).unwrap();
+
+    let dictionary = batch["dictionary"].as_array().unwrap().clone();
+    assert!(dictionary.contains(&Value::from(SESSION_ID)));
+    assert!(dictionary.contains(&Value::from(PEER_ID)));
+    assert!(batch["events"][0]["session_id"].is_u64());
+    for event in batch["events"].as_array_mut().unwrap() {
+        expand_dictionary(&dictionary, event);
+    }
+    assert_eq!(batch["events"], plain["events"]);
+    assert!(encoded.len() < plain.to_string().len());
+}
+
+#[test]
+fn every_event_type_has_a_fixture() {
+    let covered: Vec<&str> = fixtures()
+        .iter()
+        .map(|(_, event)| event.event_type())
+        .collect();
+    for kind in xatu::EventKind::ALL {
+        assert!(
+            covered.contains(&kind.event_type()),
+            "no golden fixture for {}",
+            kind.event_type()
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Middleware behavior that doesn't need an exporter behind it
+
+use libp2p::PeerId;
+use lighthouse_network::MessageId;
+use types::{
+    AggregateSignature, Attestation, AttestationBase, AttestationData, BitList, MainnetEthSpec,
+    Slot,
+};
+use xatu::middleware::{AttestationKey, DedupLayer, EventContext, Middleware};
+use xatu::EventKind;
+
+fn base_attestation(set_bits: &[usize]) -> Attestation<MainnetEthSpec> {
+    let mut aggregation_bits = BitList::with_capacity(64).expect("capacity should be in range");
+    for &bit in set_bits {
+        aggregation_bits
+            .set(bit, true)
+            .expect("bit should be in range");
+    }
+    Attestation::Base(AttestationBase {
+        aggregation_bits,
+        data: AttestationData {
+            slot: Slot::new(96),
+            index: 3,
+            ..Default::default()
+        },
+        signature: AggregateSignature::empty(),
+    })
+}
+
+fn context(attestation: &Attestation<MainnetEthSpec>) -> EventContext {
+    EventContext {
+        kind: EventKind::Attestation,
+        message_id: MessageId::new(&[1, 2, 3]),
+        peer_id: PeerId::random(),
+        client: None,
+        slot: 96,
+        timestamp_millis: 0,
+        topic: "beacon_attestation_3".to_string(),
+        message_size: 230,
+        attestation_key: AttestationKey::from_attestation(attestation),
+    }
+}
+
+#[test]
+fn first_seen_dedup_covers_pre_electra_attestations() {
+    let dedup = DedupLayer::new(16);
+
+    // Keyed by the committee position of the one set bit
+    assert!(dedup.before(&mut context(&base_attestation(&[5]))));
+    assert!(!dedup.before(&mut context(&base_attestation(&[5]))));
+    assert!(dedup.before(&mut context(&base_attestation(&[6]))));
+
+    // Anything but a single attester has no key and is always exported
+    let aggregated = base_attestation(&[5, 6]);
+    assert_eq!(AttestationKey::from_attestation(&aggregated), None);
+    assert!(dedup.before(&mut context(&aggregated)));
+    assert!(dedup.before(&mut context(&aggregated)));
+}
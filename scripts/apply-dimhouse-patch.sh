@@ -156,6 +156,9 @@ if ! grep -q '/xatu/src/libxatu.so' .gitignore 2>/dev/null; then
     echo "" >> .gitignore
     echo "# Xatu build artifacts" >> .gitignore
     echo "/xatu/src/libxatu.so" >> .gitignore
+    echo "/xatu/src/libxatu.a" >> .gitignore
+    echo "/xatu/src/xatu.dll" >> .gitignore
+    echo "/xatu/src/xatu.lib" >> .gitignore
     echo "/xatu/src/libxatu.h" >> .gitignore
     echo -e "${GREEN}  Added xatu build artifact entries to .gitignore${NC}"
 else